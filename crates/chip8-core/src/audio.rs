@@ -0,0 +1,61 @@
+/// Where the buzzer sounds. The interpreter only says when the sound timer
+/// starts and stops running; producing the tone is up to the backend, so
+/// the core doesn't depend on any audio library.
+pub trait Audio {
+    fn start_beep(&mut self);
+    fn stop_beep(&mut self);
+
+    /// XO-CHIP: play `pattern` in a loop at `rate` samples per second
+    /// while the sound timer runs, instead of a plain beep. Backends that
+    /// can't keep beeping.
+    fn set_pattern(&mut self, _pattern: AudioPattern, _rate: f32) {}
+
+    /// Called on every timer tick with the emulated time since the last
+    /// one, for backends that produce sound in step with the emulation
+    /// rather than in real time, like a recorder
+    fn advance(&mut self, _seconds: f32) {}
+}
+
+/// An XO-CHIP audio pattern, 128 one-bit samples, most significant first
+pub type AudioPattern = [u8; 16];
+
+/// Samples per second an XO-CHIP pattern plays at for a pitch register
+/// value, 4000 at the default of 64 and doubling every 48 steps
+pub fn pattern_rate(pitch: u8) -> f32 {
+    // powf needs std, so go by whole octaves and then 48ths of one
+    const STEP: f32 = 1.014_545_3;
+    let steps = pitch as i32 - 64;
+    let octave = steps.div_euclid(48);
+    let mut rate = if octave >= 0 {
+        4000. * (1 << octave) as f32
+    } else {
+        4000. / (1 << -octave) as f32
+    };
+    for _ in 0..steps.rem_euclid(48) {
+        rate *= STEP;
+    }
+    rate
+}
+
+/// Silence, for headless runs and machines without a sound device
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoAudio;
+
+impl Audio for NoAudio {
+    fn start_beep(&mut self) {}
+
+    fn stop_beep(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_rate() {
+        assert_eq!(pattern_rate(64), 4000.);
+        assert_eq!(pattern_rate(112), 8000.);
+        assert_eq!(pattern_rate(16), 2000.);
+        assert!((pattern_rate(88) - 4000. * 2f32.sqrt()).abs() < 0.1);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+/// Something the emulated program can observe that does not come from the
+/// machine state itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    Rng,
+    WallClock,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Occurrence {
+    pub first_cycle: u64,
+    pub count: u64,
+}
+
+/// Records every RNG draw and wall-clock read, grouped by the PC that
+/// triggered it. When the run claims to be deterministic, the first
+/// occurrence at each PC is reported as a warning so that replays do not
+/// silently diverge.
+#[derive(Debug)]
+pub struct Audit {
+    deterministic: bool,
+    occurrences: BTreeMap<(Source, u16), Occurrence>,
+}
+
+impl Audit {
+    pub fn new(deterministic: bool) -> Audit {
+        Audit {
+            deterministic,
+            occurrences: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, source: Source, pc: u16, cycle: u64) {
+        let occurrence = self.occurrences.entry((source, pc)).or_insert(Occurrence {
+            first_cycle: cycle,
+            count: 0,
+        });
+        occurrence.count += 1;
+        if occurrence.count == 1 && self.deterministic {
+            tracing::warn!(cycle, "{} at {:#05x} in deterministic mode", source, pc);
+        }
+    }
+
+    pub fn occurrences(&self) -> impl Iterator<Item = (Source, u16, &Occurrence)> {
+        self.occurrences
+            .iter()
+            .map(|(&(source, pc), occurrence)| (source, pc, occurrence))
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.occurrences.is_empty()
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Rng => write!(f, "rng draw"),
+            Source::WallClock => write!(f, "wall-clock read"),
+        }
+    }
+}
+
+impl fmt::Display for Audit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_clean() {
+            return writeln!(f, "audit: no nondeterministic input");
+        }
+        writeln!(f, "audit:")?;
+        for (source, pc, occurrence) in self.occurrences() {
+            writeln!(
+                f,
+                "  {:<15} {:#05x}  {:>8}x, first at cycle {}",
+                source.to_string(),
+                pc,
+                occurrence.count,
+                occurrence.first_cycle
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_groups_by_pc() {
+        let mut audit = Audit::new(false);
+        assert!(audit.is_clean());
+        audit.record(Source::Rng, 0x204, 3);
+        audit.record(Source::Rng, 0x204, 9);
+        audit.record(Source::WallClock, 0x200, 0);
+        let occurrences: Vec<_> = audit.occurrences().collect();
+        assert_eq!(
+            occurrences,
+            vec![
+                (
+                    Source::Rng,
+                    0x204,
+                    &Occurrence {
+                        first_cycle: 3,
+                        count: 2
+                    }
+                ),
+                (
+                    Source::WallClock,
+                    0x200,
+                    &Occurrence {
+                        first_cycle: 0,
+                        count: 1
+                    }
+                ),
+            ]
+        );
+    }
+}
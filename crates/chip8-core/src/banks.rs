@@ -0,0 +1,81 @@
+//! Experimental bank switching for homebrew that outgrows 4K. The machine
+//! gets several full 4K memory banks, each with the font at 0 and program
+//! space from 0x200, and `0BNN` maps bank NN into the address space. The
+//! PC is kept, so code switching banks usually sits at the same address in
+//! each of them.
+
+use super::Mem;
+use crate::prelude::*;
+
+/// High byte of the bank select opcode, `0BNN`, which is otherwise a
+/// machine code call
+pub(crate) const BANK_SELECT: u16 = 0x0B00;
+
+/// Memory banks other than the one mapped in
+#[derive(Clone)]
+pub(crate) struct Banks {
+    /// One slot per bank, the mapped one holds stale contents
+    stored: Vec<Mem>,
+    current: usize,
+}
+
+impl Banks {
+    pub(crate) fn new(count: usize, init: Mem) -> Banks {
+        Banks {
+            stored: vec![init; count],
+            current: 0,
+        }
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.stored.len()
+    }
+
+    pub(crate) fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Map `bank` into `mem`, putting the mapped one back. None if there
+    /// is no such bank.
+    pub(crate) fn select(&mut self, mem: &mut Mem, bank: usize) -> Option<()> {
+        if bank >= self.stored.len() {
+            return None;
+        }
+        self.stored[self.current] = *mem;
+        *mem = self.stored[bank];
+        self.current = bank;
+        Some(())
+    }
+
+    /// Saved contents of `bank`, None if there is no such bank. They are
+    /// stale for the mapped bank, whose contents are in memory.
+    pub(crate) fn stored(&self, bank: usize) -> Option<&Mem> {
+        self.stored.get(bank)
+    }
+
+    /// Write access to the saved contents of `bank`, e.g. to load a ROM
+    /// into banks before they are mapped in. Panics if there is no such
+    /// bank; writes to the mapped bank are lost on the next `select`.
+    pub(crate) fn stored_mut(&mut self, bank: usize) -> &mut Mem {
+        &mut self.stored[bank]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let mut mem = [0; 4096];
+        let mut banks = Banks::new(2, mem);
+        banks.stored_mut(1)[0x200] = 7;
+        mem[0x200] = 3;
+        banks.select(&mut mem, 1).unwrap();
+        assert_eq!(mem[0x200], 7);
+        banks.select(&mut mem, 0).unwrap();
+        assert_eq!(mem[0x200], 3);
+        assert!(banks.select(&mut mem, 2).is_none());
+        assert_eq!(banks.current(), 0);
+    }
+}
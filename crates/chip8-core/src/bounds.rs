@@ -0,0 +1,74 @@
+/// What instructions working on memory at I do when they run past the end
+/// of memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutOfBounds {
+    /// Continue from address 0, as the address bus would
+    #[default]
+    Wrap,
+    /// Read zeros, e.g. blank sprite rows, and drop writes
+    Zeros,
+    /// Stop with a fault
+    Fault,
+}
+
+impl OutOfBounds {
+    pub fn from_name(name: &str) -> Option<OutOfBounds> {
+        match name {
+            "wrap" => Some(OutOfBounds::Wrap),
+            "zeros" => Some(OutOfBounds::Zeros),
+            "fault" => Some(OutOfBounds::Fault),
+            _ => None,
+        }
+    }
+
+    /// Byte at `addr` under this policy, None if it faults
+    pub(crate) fn read(self, mem: &[u8], addr: usize) -> Option<u8> {
+        match mem.get(addr) {
+            Some(&byte) => Some(byte),
+            None => match self {
+                OutOfBounds::Wrap => Some(mem[addr % mem.len()]),
+                OutOfBounds::Zeros => Some(0),
+                OutOfBounds::Fault => None,
+            },
+        }
+    }
+
+    /// Store `byte` at `addr` under this policy, None if it faults
+    pub(crate) fn write(self, mem: &mut [u8], addr: usize, byte: u8) -> Option<()> {
+        let len = mem.len();
+        match mem.get_mut(addr) {
+            Some(slot) => *slot = byte,
+            None => match self {
+                OutOfBounds::Wrap => mem[addr % len] = byte,
+                OutOfBounds::Zeros => {}
+                OutOfBounds::Fault => return None,
+            },
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let mem = [1, 2, 3, 4];
+        assert_eq!(OutOfBounds::Fault.read(&mem, 3), Some(4));
+        assert_eq!(OutOfBounds::Wrap.read(&mem, 5), Some(2));
+        assert_eq!(OutOfBounds::Zeros.read(&mem, 5), Some(0));
+        assert_eq!(OutOfBounds::Fault.read(&mem, 4), None);
+        assert_eq!(OutOfBounds::from_name("zeros"), Some(OutOfBounds::Zeros));
+    }
+
+    #[test]
+    fn test_write() {
+        let mut mem = [0; 4];
+        assert_eq!(OutOfBounds::Wrap.write(&mut mem, 5, 9), Some(()));
+        assert_eq!(OutOfBounds::Zeros.write(&mut mem, 6, 9), Some(()));
+        assert_eq!(OutOfBounds::Fault.write(&mut mem, 4, 9), None);
+        assert_eq!(mem, [0, 9, 0, 0]);
+        assert_eq!(OutOfBounds::from_name("clamp"), None);
+    }
+}
@@ -0,0 +1,240 @@
+use crate::prelude::*;
+use crate::rom::RomSource;
+use crate::{Chip8Error, Chip8Interpreter, Quirks, INSTRUCTIONS_PER_SECOND};
+use rand::RngCore;
+
+enum Rom<'a> {
+    Bytes(&'a [u8]),
+    Source(&'a dyn RomSource),
+}
+
+/// Sets up an interpreter in one expression:
+///
+/// ```
+/// # use chip8_core::Chip8Builder;
+/// let rom = [0x60, 0x2A, 0x00, 0x00];
+/// let cpu = Chip8Builder::new()
+///     .ips(700.)
+///     .shift_quirk(true)
+///     .seed(42)
+///     .rom_bytes(&rom)
+///     .build()
+///     .unwrap();
+/// assert_eq!(cpu.clock_speed(), 700.);
+/// ```
+pub struct Chip8Builder<'a> {
+    quirks: Option<Quirks>,
+    ips: f64,
+    timer_hz: Option<f64>,
+    seed: Option<u64>,
+    rng: Option<Box<dyn RngCore>>,
+    echo_display: bool,
+    rom: Option<Rom<'a>>,
+}
+
+impl<'a> Chip8Builder<'a> {
+    pub fn new() -> Chip8Builder<'a> {
+        Chip8Builder {
+            quirks: None,
+            ips: INSTRUCTIONS_PER_SECOND,
+            timer_hz: None,
+            seed: None,
+            rng: None,
+            echo_display: true,
+            rom: None,
+        }
+    }
+
+    /// Start from a quirks preset; the single quirk settings below apply
+    /// on top of it
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = Some(quirks);
+        self
+    }
+
+    /// Instructions per second
+    pub fn ips(mut self, ips: f64) -> Self {
+        self.ips = ips;
+        self
+    }
+
+    pub fn timer_hz(mut self, hz: f64) -> Self {
+        self.timer_hz = Some(hz);
+        self
+    }
+
+    /// Seed the RNG for repeatable runs
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Draw random numbers from `rng` instead, overriding `seed`
+    pub fn rng(mut self, rng: impl RngCore + 'static) -> Self {
+        self.rng = Some(Box::new(rng));
+        self
+    }
+
+    /// Whether each frame is printed to the terminal
+    pub fn echo_display(mut self, echo: bool) -> Self {
+        self.echo_display = echo;
+        self
+    }
+
+    pub fn shift_quirk(self, on: bool) -> Self {
+        self.quirk(|quirks| quirks.old_shift = on)
+    }
+
+    pub fn add_i_overflow_quirk(self, on: bool) -> Self {
+        self.quirk(|quirks| quirks.add_i_overflow = on)
+    }
+
+    pub fn memory_increment_quirk(self, on: bool) -> Self {
+        self.quirk(|quirks| quirks.memory_increment = on)
+    }
+
+    pub fn jump_vx_quirk(self, on: bool) -> Self {
+        self.quirk(|quirks| quirks.jump_vx = on)
+    }
+
+    fn quirk(mut self, set: impl FnOnce(&mut Quirks)) -> Self {
+        set(self.quirks.get_or_insert_with(Quirks::default));
+        self
+    }
+
+    pub fn rom_bytes(mut self, rom: &'a [u8]) -> Self {
+        self.rom = Some(Rom::Bytes(rom));
+        self
+    }
+
+    pub fn rom_source(mut self, source: &'a dyn RomSource) -> Self {
+        self.rom = Some(Rom::Source(source));
+        self
+    }
+
+    /// The configured interpreter, with the ROM loaded if one was given.
+    /// Settings out of range fail with `Chip8Error::Config`.
+    pub fn build(self) -> Result<Chip8Interpreter, Chip8Error> {
+        let mut cpu = Chip8Interpreter::new();
+        if let Some(quirks) = self.quirks {
+            cpu.set_quirks(quirks);
+        }
+        cpu.set_clock_speed(self.ips).map_err(Chip8Error::Config)?;
+        if let Some(hz) = self.timer_hz {
+            cpu.set_timer_frequency(hz).map_err(Chip8Error::Config)?;
+        }
+        if let Some(seed) = self.seed {
+            cpu.seed_rng(seed);
+        }
+        if let Some(rng) = self.rng {
+            cpu.set_rng(rng);
+        }
+        cpu.set_echo_display(self.echo_display);
+        match self.rom {
+            Some(Rom::Bytes(data)) => cpu.load_rom_bytes(data)?,
+            Some(Rom::Source(source)) => cpu.load_rom_from(source)?,
+            None => {}
+        }
+        Ok(cpu)
+    }
+}
+
+impl Default for Chip8Builder<'_> {
+    fn default() -> Self {
+        Chip8Builder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        // V0 = random; V1 = random
+        let rom = [0xC0, 0xFF, 0xC1, 0xFF];
+        let build = || {
+            Chip8Builder::new()
+                .ips(1200.)
+                .quirks(Quirks::CHIP_48)
+                .shift_quirk(true)
+                .seed(42)
+                .echo_display(false)
+                .rom_bytes(&rom)
+                .build()
+                .unwrap()
+        };
+        let mut cpu = build();
+        assert_eq!(cpu.clock_speed(), 1200.);
+        assert_eq!(
+            cpu.quirks,
+            Quirks {
+                old_shift: true,
+                ..Quirks::CHIP_48
+            }
+        );
+        cpu.step_n(2).unwrap();
+        let mut again = build();
+        again.step_n(2).unwrap();
+        assert_eq!(cpu.registers_v, again.registers_v);
+        // The seed also holds after a reset
+        cpu.reset();
+        cpu.load_rom_bytes(&rom).unwrap();
+        cpu.step_n(2).unwrap();
+        assert_eq!(cpu.registers_v, again.registers_v);
+    }
+
+    #[test]
+    fn test_injected_rng() {
+        /// Counts up from 1
+        struct Counter(u32);
+        impl RngCore for Counter {
+            fn next_u32(&mut self) -> u32 {
+                self.0 += 1;
+                self.0
+            }
+            fn next_u64(&mut self) -> u64 {
+                self.next_u32() as u64
+            }
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                dest.fill(self.next_u32() as u8);
+            }
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+                self.fill_bytes(dest);
+                Ok(())
+            }
+        }
+        // V0 = random; V1 = random AND 0x0E
+        let rom = [0xC0, 0xFF, 0xC1, 0x0E];
+        let mut cpu = Chip8Builder::new()
+            .seed(7)
+            .rng(Counter(0))
+            .rom_bytes(&rom)
+            .build()
+            .unwrap();
+        cpu.step_n(2).unwrap();
+        assert_eq!(cpu.v(0), 1);
+        assert_eq!(cpu.v(1), 2);
+    }
+
+    #[test]
+    fn test_rom_too_large() {
+        let rom = vec![0; 4096];
+        assert!(matches!(
+            Chip8Builder::new().rom_bytes(&rom).build(),
+            Err(Chip8Error::Rom(_))
+        ));
+    }
+
+    #[test]
+    fn test_bad_rates() {
+        assert!(matches!(
+            Chip8Builder::new().timer_hz(0.).build(),
+            Err(Chip8Error::Config(_))
+        ));
+        assert!(matches!(
+            Chip8Builder::new().ips(f64::INFINITY).build(),
+            Err(Chip8Error::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,62 @@
+use core::cell::Cell;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+/// Where `Chip8Interpreter::catch_up` gets the time from
+pub trait Clock {
+    /// Time since the clock started
+    fn now(&self) -> Duration;
+}
+
+/// Wall-clock time, for running in real time
+#[cfg(feature = "std")]
+pub struct RealTimeClock {
+    start: Instant,
+}
+
+#[cfg(feature = "std")]
+impl RealTimeClock {
+    pub fn new() -> RealTimeClock {
+        RealTimeClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for RealTimeClock {
+    fn default() -> Self {
+        RealTimeClock::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for RealTimeClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Time that only moves when told to, so tests can step the machine by
+/// exact amounts of emulated time
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
@@ -0,0 +1,182 @@
+use super::instruction::Instruction;
+use super::{MEMORY_SIZE, PROGRAM_START};
+use crate::prelude::*;
+use alloc::collections::BTreeSet;
+use core::fmt;
+
+/// Every opcode family the interpreter decodes, by `Instruction::pattern`
+const FAMILIES: [&str; 40] = [
+    "0000", "00E0", "00EE", "0NNN", "1NNN", "2NNN", "3XNN", "4XNN", "5XY0", "6XNN", "7XNN", "8XY0",
+    "8XY1", "8XY2", "8XY3", "8XY4", "8XY5", "8XY6", "8XY7", "8XYE", "9XY0", "ANNN", "BNNN", "CXNN",
+    "DXYN", "EX9E", "EXA1", "F002", "FX07", "FX0A", "FX15", "FX18", "FX1E", "FX29", "FX33", "FX3A",
+    "FX55", "FX65", "FX75", "FX85",
+];
+
+/// Which bytes of the ROM ran as instructions and which opcode families
+/// were used, to find code a ROM or a test suite never reaches
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    /// A flag per byte of each bank, both bytes of an instruction are
+    /// marked
+    executed: Vec<bool>,
+    families: BTreeSet<&'static str>,
+    rom_length: usize,
+}
+
+impl Coverage {
+    pub(crate) fn set_rom_length(&mut self, length: usize) {
+        self.rom_length = length;
+    }
+
+    pub(crate) fn record(&mut self, bank: usize, pc: u16, inst: &Instruction) {
+        let addr = bank * MEMORY_SIZE as usize + pc as usize;
+        if self.executed.len() < addr + 2 {
+            self.executed
+                .resize((bank + 1) * MEMORY_SIZE as usize, false);
+        }
+        self.executed[addr] = true;
+        self.executed[addr + 1] = true;
+        self.families.insert(inst.pattern());
+    }
+
+    /// Whether the byte at `addr` in `bank` was part of an executed
+    /// instruction
+    pub fn executed(&self, bank: usize, addr: u16) -> bool {
+        let addr = bank * MEMORY_SIZE as usize + addr as usize;
+        self.executed.get(addr).copied().unwrap_or(false)
+    }
+
+    /// Opcode families that ran, by pattern
+    pub fn families(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.families.iter().copied()
+    }
+
+    /// Opcode families that never ran
+    pub fn unused_families(&self) -> impl Iterator<Item = &'static str> + '_ {
+        FAMILIES
+            .iter()
+            .copied()
+            .filter(move |pattern| !self.families.contains(pattern))
+    }
+
+    pub fn rom_length(&self) -> usize {
+        self.rom_length
+    }
+
+    /// ROM bytes that were part of an executed instruction
+    pub fn rom_bytes_executed(&self) -> usize {
+        self.rom()
+            .filter(|&(bank, addr)| self.executed(bank, addr))
+            .count()
+    }
+
+    /// Bank and address of each ROM byte, as `load_rom_bytes` lays it out
+    fn rom(&self) -> impl Iterator<Item = (usize, u16)> {
+        let bank_length = (MEMORY_SIZE - PROGRAM_START) as usize;
+        (0..self.rom_length).map(move |offset| {
+            (
+                offset / bank_length,
+                PROGRAM_START + (offset % bank_length) as u16,
+            )
+        })
+    }
+
+    /// Runs of ROM bytes that were executed, or weren't, as
+    /// "0x200-0x2a3" (or "1:0x200-0x2a3" outside bank 0)
+    fn ranges(&self, executed: bool) -> Vec<String> {
+        let mut ranges = vec![];
+        let mut run: Option<(usize, u16, u16)> = None;
+        for (bank, addr) in self.rom() {
+            if self.executed(bank, addr) != executed {
+                continue;
+            }
+            match &mut run {
+                Some((run_bank, _, end)) if *run_bank == bank && *end + 1 == addr => *end = addr,
+                _ => ranges.extend(run.replace((bank, addr, addr)).map(range)),
+            }
+        }
+        ranges.extend(run.map(range));
+        ranges
+    }
+}
+
+fn range((bank, start, end): (usize, u16, u16)) -> String {
+    let bank = match bank {
+        0 => String::new(),
+        bank => format!("{}:", bank),
+    };
+    format!("{}{:#05x}-{:#05x}", bank, start, end)
+}
+
+fn list(items: Vec<String>) -> String {
+    match items.is_empty() {
+        true => String::from("none"),
+        false => items.join(", "),
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let executed = self.rom_bytes_executed();
+        writeln!(
+            f,
+            "coverage: {} of {} ROM bytes executed ({:.1}%)",
+            executed,
+            self.rom_length,
+            executed as f64 * 100. / self.rom_length.max(1) as f64
+        )?;
+        writeln!(f, "executed: {}", list(self.ranges(true)))?;
+        writeln!(f, "never executed: {}", list(self.ranges(false)))?;
+        let unused: Vec<String> = self.unused_families().map(String::from).collect();
+        writeln!(
+            f,
+            "opcode families: {} of {} used",
+            self.families.len(),
+            FAMILIES.len()
+        )?;
+        writeln!(
+            f,
+            "  used: {}",
+            list(self.families().map(String::from).collect())
+        )?;
+        writeln!(f, "  never used: {}", list(unused))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage() {
+        let mut coverage = Coverage::default();
+        coverage.set_rom_length(8);
+        let load = Instruction::from_raw_opcode(0x6012).unwrap();
+        coverage.record(0, 0x200, &load);
+        coverage.record(0, 0x202, &load);
+        coverage.record(0, 0x206, &Instruction::from_raw_opcode(0x1206).unwrap());
+        assert!(coverage.executed(0, 0x203));
+        assert!(!coverage.executed(0, 0x204));
+        assert!(!coverage.executed(1, 0x200));
+        assert_eq!(coverage.rom_bytes_executed(), 6);
+        assert_eq!(coverage.families().collect::<Vec<_>>(), ["1NNN", "6XNN"]);
+        assert_eq!(coverage.unused_families().count(), FAMILIES.len() - 2);
+        let report = coverage.to_string();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "coverage: 6 of 8 ROM bytes executed (75.0%)");
+        assert_eq!(lines[1], "executed: 0x200-0x203, 0x206-0x207");
+        assert_eq!(lines[2], "never executed: 0x204-0x205");
+        assert_eq!(lines[3], "opcode families: 2 of 40 used");
+        assert_eq!(lines[4], "  used: 1NNN, 6XNN");
+    }
+
+    #[test]
+    fn test_banked_ranges() {
+        let mut coverage = Coverage::default();
+        coverage.set_rom_length(0xE04);
+        let clear = Instruction::from_raw_opcode(0x00E0).unwrap();
+        coverage.record(1, 0x202, &clear);
+        assert_eq!(coverage.ranges(true), ["1:0x202-0x203"]);
+        assert_eq!(coverage.ranges(false), ["0x200-0xfff", "1:0x200-0x201"]);
+    }
+}
@@ -0,0 +1,310 @@
+use super::MEMORY_SIZE;
+use crate::prelude::*;
+use alloc::collections::BTreeSet;
+use core::fmt;
+use core::ops::Range;
+use core::str::FromStr;
+
+/// A memory address in hex, with or without 0x, e.g. "2A4" or "0x2a4"
+pub fn parse_address(text: &str) -> Option<u16> {
+    let hex = text.strip_prefix("0x").unwrap_or(text);
+    u16::from_str_radix(hex, 16)
+        .ok()
+        .filter(|&addr| addr < MEMORY_SIZE)
+}
+
+/// How an instruction uses memory, apart from fetching it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Either of them, for watchpoints
+    Any,
+}
+
+impl Access {
+    fn covers(self, access: Access) -> bool {
+        self == Access::Any || self == access
+    }
+}
+
+/// Something to pause on right after an instruction touched it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watchpoint {
+    /// VX changed value
+    Register(u8),
+    /// An address from `start` to `end`, inclusive, was read or written
+    Memory {
+        start: u16,
+        end: u16,
+        access: Access,
+    },
+}
+
+/// Parses "V3" for a register, and "300" or "300-30F" for memory, in hex
+/// with or without 0x, optionally followed by ":r" or ":w" to only watch
+/// reads or writes
+impl FromStr for Watchpoint {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Watchpoint, String> {
+        let invalid = || format!("invalid watchpoint '{}'", spec);
+        if let Some(register) = spec.strip_prefix(['V', 'v']) {
+            return match u8::from_str_radix(register, 16) {
+                Ok(x) if register.len() == 1 => Ok(Watchpoint::Register(x)),
+                _ => Err(invalid()),
+            };
+        }
+        let (range, access) = match spec.split_once(':') {
+            Some((range, "r")) => (range, Access::Read),
+            Some((range, "w")) => (range, Access::Write),
+            Some(_) => return Err(invalid()),
+            None => (spec, Access::Any),
+        };
+        let addr = |text: &str| parse_address(text).ok_or_else(invalid);
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (addr(start)?, addr(end)?),
+            None => (addr(range)?, addr(range)?),
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        Ok(Watchpoint::Memory { start, end, access })
+    }
+}
+
+/// Why the machine paused for the debugger
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Break {
+    /// PC reached a breakpoint, before executing the instruction there
+    Breakpoint(u16),
+    /// The instruction at `pc` changed a watched register
+    Register { pc: u16, x: u8, old: u8, new: u8 },
+    /// The instruction at `pc` accessed a watched address
+    Memory { pc: u16, addr: u16, access: Access },
+}
+
+/// "breakpoint at 0x2a4", "V3 changed from 00 to 05 at 0x2a4" or "write
+/// to 0x300 at 0x2a4"
+impl fmt::Display for Break {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Break::Breakpoint(addr) => write!(f, "breakpoint at {:#05x}", addr),
+            Break::Register { pc, x, old, new } => write!(
+                f,
+                "V{:X} changed from {:02X} to {:02X} at {:#05x}",
+                x, old, new, pc
+            ),
+            Break::Memory { pc, addr, access } => {
+                let access = match access {
+                    Access::Read => "read of",
+                    Access::Write => "write to",
+                    Access::Any => "access to",
+                };
+                write!(f, "{} {:#05x} at {:#05x}", access, addr, pc)
+            }
+        }
+    }
+}
+
+/// Breakpoints and watchpoints, and whether the machine is paused on one.
+/// Every `Chip8Interpreter` has one; with nothing set it costs a check per
+/// instruction.
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+    paused: Option<Break>,
+    /// Address to run past once after resuming, so continuing from a
+    /// breakpoint doesn't stop on it straight away
+    resume_from: Option<u16>,
+}
+
+impl Debugger {
+    /// Pause before the instruction at `addr` executes. Returns false if
+    /// there already was a breakpoint there.
+    pub fn set_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    /// Returns false if there was no breakpoint at `addr`
+    pub fn clear_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Breakpoint addresses, lowest first
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Returns false if the same watchpoint was already set
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        if self.watchpoints.contains(&watchpoint) {
+            return false;
+        }
+        self.watchpoints.push(watchpoint);
+        true
+    }
+
+    /// Returns false if there was no such watchpoint
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|&watch| watch != watchpoint);
+        self.watchpoints.len() != count
+    }
+
+    /// Watchpoints, oldest first
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Whether instructions have to be checked against watchpoints
+    pub(crate) fn watching(&self) -> bool {
+        !self.watchpoints.is_empty()
+    }
+
+    /// Pause if the instruction at `pc` changed registers from `before` to
+    /// `after`, or accessed `memory`, where a watchpoint is set
+    pub(crate) fn check_watchpoints(
+        &mut self,
+        pc: u16,
+        before: &[u8; 16],
+        after: &[u8; 16],
+        memory: Option<(Access, Range<usize>)>,
+    ) {
+        self.paused =
+            self.watchpoints
+                .iter()
+                .find_map(|&watchpoint| match watchpoint {
+                    Watchpoint::Register(x) => {
+                        let (old, new) = (before[x as usize], after[x as usize]);
+                        (old != new).then_some(Break::Register { pc, x, old, new })
+                    }
+                    Watchpoint::Memory { start, end, access } => {
+                        let (used, range) = memory.clone()?;
+                        let first = range.start.max(start as usize);
+                        (access.covers(used) && first < range.end && first <= end as usize)
+                            .then_some(Break::Memory {
+                                pc,
+                                addr: first as u16,
+                                access: used,
+                            })
+                    }
+                });
+    }
+
+    pub fn paused(&self) -> Option<Break> {
+        self.paused
+    }
+
+    /// Unpause without running past anything, e.g. on a reset
+    pub(crate) fn reset(&mut self) {
+        self.paused = None;
+        self.resume_from = None;
+    }
+
+    pub(crate) fn resume(&mut self, pc: u16) {
+        // Watchpoints pause after the instruction, there is nothing to
+        // run past
+        if let Some(Break::Breakpoint(_)) = self.paused.take() {
+            self.resume_from = Some(pc);
+        }
+    }
+
+    /// Whether to hold the instruction at `pc` back, pausing if it is on a
+    /// breakpoint
+    pub(crate) fn holds(&mut self, pc: u16) -> bool {
+        if self.paused.is_some() {
+            return true;
+        }
+        if self.resume_from.take() == Some(pc) || !self.breakpoints.contains(&pc) {
+            return false;
+        }
+        self.paused = Some(Break::Breakpoint(pc));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = Debugger::default();
+        assert!(debugger.set_breakpoint(0x2A4));
+        assert!(!debugger.set_breakpoint(0x2A4));
+        debugger.set_breakpoint(0x200);
+        assert_eq!(debugger.breakpoints().collect::<Vec<_>>(), [0x200, 0x2A4]);
+        assert!(debugger.clear_breakpoint(0x200));
+        assert!(!debugger.clear_breakpoint(0x200));
+        assert!(!debugger.holds(0x200));
+        assert!(debugger.holds(0x2A4));
+        assert_eq!(debugger.paused(), Some(Break::Breakpoint(0x2A4)));
+        assert!(debugger.holds(0x2A4));
+        debugger.resume(0x2A4);
+        assert!(!debugger.holds(0x2A4));
+        assert!(debugger.holds(0x2A4));
+        assert_eq!(Break::Breakpoint(0x2A4).to_string(), "breakpoint at 0x2a4");
+    }
+
+    #[test]
+    fn test_parse_watchpoint() {
+        assert_eq!("V3".parse(), Ok(Watchpoint::Register(3)));
+        assert_eq!("vf".parse(), Ok(Watchpoint::Register(0xF)));
+        assert_eq!(
+            "0x300-0x30F:w".parse(),
+            Ok(Watchpoint::Memory {
+                start: 0x300,
+                end: 0x30F,
+                access: Access::Write
+            })
+        );
+        assert_eq!(
+            "2A4".parse(),
+            Ok(Watchpoint::Memory {
+                start: 0x2A4,
+                end: 0x2A4,
+                access: Access::Any
+            })
+        );
+        for invalid in ["V10", "VG", "30F-300", "1000", "300:x", ""] {
+            assert!(invalid.parse::<Watchpoint>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut debugger = Debugger::default();
+        assert!(!debugger.watching());
+        debugger.add_watchpoint("V3".parse().unwrap());
+        debugger.add_watchpoint("300-30F:w".parse().unwrap());
+        let before = [0; 16];
+        let mut after = [0; 16];
+        debugger.check_watchpoints(0x200, &before, &after, Some((Access::Write, 0x2F0..0x300)));
+        assert_eq!(debugger.paused(), None);
+        debugger.check_watchpoints(0x200, &before, &after, Some((Access::Read, 0x300..0x303)));
+        assert_eq!(debugger.paused(), None);
+        debugger.check_watchpoints(0x202, &before, &after, Some((Access::Write, 0x2FE..0x301)));
+        assert_eq!(
+            debugger.paused(),
+            Some(Break::Memory {
+                pc: 0x202,
+                addr: 0x300,
+                access: Access::Write
+            })
+        );
+        after[3] = 5;
+        debugger.check_watchpoints(0x204, &before, &after, None);
+        assert_eq!(
+            debugger.paused().unwrap().to_string(),
+            "V3 changed from 00 to 05 at 0x204"
+        );
+        assert!(debugger.remove_watchpoint(Watchpoint::Register(3)));
+        assert_eq!(debugger.watchpoints().len(), 1);
+    }
+}
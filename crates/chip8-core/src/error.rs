@@ -0,0 +1,103 @@
+use crate::prelude::*;
+use crate::rom::RomError;
+use core::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Chip8Error {
+    /// The ROM could not be read, or doesn't fit in memory
+    Rom(RomError),
+    /// Opcode doesn't match any known instruction
+    Decode { opcode: u16, addr: u16 },
+    /// Strict mode: the instruction behaves differently across interpreters
+    /// and no quirks were configured to pick one
+    QuirkDependent { opcode: u16, addr: u16 },
+    /// 2NNN called with the stack already at its limit. `trace` holds the
+    /// return addresses on the stack, outermost call first.
+    StackOverflow { addr: u16, trace: Vec<u16> },
+    /// 00EE returned with nothing on the stack
+    StackUnderflow { addr: u16 },
+    /// The instruction at `addr` accessed memory at `access`, past the end
+    /// of memory, with the out-of-bounds policy set to fault; or the program
+    /// ran off the end of memory
+    OutOfBounds { addr: u16, access: usize },
+    /// 0NNN called a machine code routine, with the SYS policy set to error
+    MachineCode { opcode: u16, addr: u16 },
+    /// 0BNN selected a memory bank the machine doesn't have
+    NoSuchBank { bank: u8, addr: u16 },
+    /// A setting was out of range when building the interpreter
+    Config(String),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::Rom(err) => write!(f, "{}", err),
+            Chip8Error::Decode { opcode, addr } => write!(
+                f,
+                "cannot decode instruction {:#06x} at address {:#05x}",
+                opcode, addr
+            ),
+            Chip8Error::QuirkDependent { opcode, addr } => write!(
+                f,
+                "instruction {:#06x} at address {:#05x} depends on quirks, but none are configured",
+                opcode, addr
+            ),
+            Chip8Error::StackOverflow { addr, trace } => {
+                write!(f, "stack overflow at address {:#05x}, call trace:", addr)?;
+                // Show the calls themselves, just before where they return to
+                for return_addr in trace {
+                    write!(f, " {:#05x}", return_addr.wrapping_sub(2))?;
+                }
+                Ok(())
+            }
+            Chip8Error::StackUnderflow { addr } => {
+                write!(f, "return at address {:#05x} with an empty stack", addr)
+            }
+            Chip8Error::OutOfBounds { addr, access } => write!(
+                f,
+                "instruction at address {:#05x} accesses {:#05x}, past the end of memory",
+                addr, access
+            ),
+            Chip8Error::MachineCode { opcode, addr } => write!(
+                f,
+                "instruction {:#06x} at address {:#05x} calls a machine code routine",
+                opcode, addr
+            ),
+            Chip8Error::NoSuchBank { bank, addr } => write!(
+                f,
+                "instruction at address {:#05x} selects memory bank {}, which doesn't exist",
+                addr, bank
+            ),
+            Chip8Error::Config(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Chip8Error {}
+
+/// So embedders can `?` loading and running into one error type
+impl From<RomError> for Chip8Error {
+    fn from(err: RomError) -> Self {
+        Chip8Error::Rom(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack_overflow_trace() {
+        // A return address of 0 or 1 can come from a state file or a
+        // program that wrapped around memory
+        let err = Chip8Error::StackOverflow {
+            addr: 0x204,
+            trace: vec![0x202, 0x000],
+        };
+        assert_eq!(
+            err.to_string(),
+            "stack overflow at address 0x204, call trace: 0x200 0xfffe"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use super::{KEYPAD_SIZE, MEMORY_SIZE};
+use crate::prelude::*;
+use core::str::FromStr;
+
+/// Something to go wrong on purpose, to exercise error paths
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FaultKind {
+    /// Invert one bit of a memory byte
+    BitFlip { addr: u16, bit: u8 },
+    /// Make the next CXNN draw this value instead of a random one
+    ForceRng(u8),
+    /// Lose the next press of this keypad key
+    DropKey(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fault {
+    /// Injected right before the instruction at this cycle executes
+    pub cycle: u64,
+    pub kind: FaultKind,
+}
+
+/// Parses "CYCLE:flip:ADDR:BIT", "CYCLE:rng:VALUE" and "CYCLE:dropkey:KEY",
+/// numbers in decimal or 0x-prefixed hex
+impl FromStr for Fault {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Fault, String> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        let number = |text: &str, max: u64| {
+            let value = match text.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => text.parse::<u64>(),
+            };
+            value
+                .ok()
+                .filter(|&value| value <= max)
+                .ok_or_else(|| format!("invalid number {} in fault {}", text, spec))
+        };
+        let kind = match parts[..] {
+            [_, "flip", addr, bit] => FaultKind::BitFlip {
+                addr: number(addr, MEMORY_SIZE as u64 - 1)? as u16,
+                bit: number(bit, 7)? as u8,
+            },
+            [_, "rng", value] => FaultKind::ForceRng(number(value, 0xFF)? as u8),
+            [_, "dropkey", key] => FaultKind::DropKey(number(key, KEYPAD_SIZE as u64 - 1)? as u8),
+            _ => {
+                return Err(format!(
+                    "expected CYCLE:flip:ADDR:BIT, CYCLE:rng:VALUE or CYCLE:dropkey:KEY, got {}",
+                    spec
+                ))
+            }
+        };
+        Ok(Fault {
+            cycle: number(parts[0], u64::MAX)?,
+            kind,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fault() {
+        assert_eq!(
+            "100:flip:0x2A4:3".parse(),
+            Ok(Fault {
+                cycle: 100,
+                kind: FaultKind::BitFlip {
+                    addr: 0x2A4,
+                    bit: 3
+                }
+            })
+        );
+        assert_eq!(
+            "0:rng:0xFF".parse(),
+            Ok(Fault {
+                cycle: 0,
+                kind: FaultKind::ForceRng(0xFF)
+            })
+        );
+        assert_eq!(
+            "5:dropkey:0xA".parse(),
+            Ok(Fault {
+                cycle: 5,
+                kind: FaultKind::DropKey(0xA)
+            })
+        );
+        assert!("1:dropkey:16".parse::<Fault>().is_err());
+        assert!("1:flip:0x1000:0".parse::<Fault>().is_err());
+        assert!("1:flip:0x200:8".parse::<Fault>().is_err());
+        assert!("1:rng:256".parse::<Fault>().is_err());
+        assert!("x:rng:1".parse::<Fault>().is_err());
+        assert!("1:drop".parse::<Fault>().is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use super::hash;
+use crate::prelude::*;
+use core::ops::{Index, IndexMut};
+
+pub const FRAME_BUFFER_WIDTH: usize = 64;
+pub const FRAME_BUFFER_HEIGHT: usize = 32;
+
+/// Bytes in the `packed` form of the display
+pub(crate) const PACKED_SIZE: usize = FRAME_BUFFER_WIDTH * FRAME_BUFFER_HEIGHT / 8;
+
+type Rows = [[u32; FRAME_BUFFER_WIDTH]; FRAME_BUFFER_HEIGHT];
+
+/// The 64x32 monochrome display, indexed as `frame_buffer[y][x]`.
+/// Pixels are 1 when lit and 0 otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameBuffer {
+    rows: Rows,
+}
+
+impl FrameBuffer {
+    /// A blank display
+    pub fn new() -> FrameBuffer {
+        FrameBuffer::from_rows([[0; FRAME_BUFFER_WIDTH]; FRAME_BUFFER_HEIGHT])
+    }
+
+    pub fn from_rows(rows: Rows) -> FrameBuffer {
+        FrameBuffer { rows }
+    }
+
+    pub fn rows(&self) -> core::slice::Iter<'_, [u32; FRAME_BUFFER_WIDTH]> {
+        self.rows.iter()
+    }
+
+    pub fn rows_mut(&mut self) -> core::slice::IterMut<'_, [u32; FRAME_BUFFER_WIDTH]> {
+        self.rows.iter_mut()
+    }
+
+    /// Copy of a sub-rectangle of the display, clipped to the display edges
+    pub fn region(&self, x: usize, y: usize, width: usize, height: usize) -> Region {
+        let x = x.min(FRAME_BUFFER_WIDTH);
+        let y = y.min(FRAME_BUFFER_HEIGHT);
+        let width = width.min(FRAME_BUFFER_WIDTH - x);
+        let height = height.min(FRAME_BUFFER_HEIGHT - y);
+        let pixels = self.rows[y..y + height]
+            .iter()
+            .flat_map(|row| row[x..x + width].iter().map(|&pixel| pixel == 1))
+            .collect();
+        Region {
+            x,
+            y,
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Rows top to bottom, 8 pixels per byte with the leftmost in the
+    /// most significant bit, as saved in state files
+    pub fn packed(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PACKED_SIZE);
+        for row in self.rows() {
+            for chunk in row.chunks(8) {
+                bytes.push(
+                    chunk
+                        .iter()
+                        .fold(0u8, |byte, &pixel| (byte << 1) | (pixel & 1) as u8),
+                );
+            }
+        }
+        bytes
+    }
+
+    /// The display from `packed` bytes, None unless there are exactly 256
+    pub fn from_packed(bytes: &[u8]) -> Option<FrameBuffer> {
+        if bytes.len() != PACKED_SIZE {
+            return None;
+        }
+        let mut frame_buffer = FrameBuffer::new();
+        for (row, packed) in frame_buffer
+            .rows_mut()
+            .zip(bytes.chunks(FRAME_BUFFER_WIDTH / 8))
+        {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = ((packed[x / 8] >> (7 - x % 8)) & 1) as u32;
+            }
+        }
+        Some(frame_buffer)
+    }
+
+    /// Stable hash of the whole display, equal to the hash of the
+    /// full-screen region
+    pub fn hash(&self) -> u64 {
+        self.region(0, 0, FRAME_BUFFER_WIDTH, FRAME_BUFFER_HEIGHT)
+            .hash()
+    }
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        FrameBuffer::new()
+    }
+}
+
+impl Index<usize> for FrameBuffer {
+    type Output = [u32; FRAME_BUFFER_WIDTH];
+
+    fn index(&self, y: usize) -> &Self::Output {
+        &self.rows[y]
+    }
+}
+
+impl IndexMut<usize> for FrameBuffer {
+    fn index_mut(&mut self, y: usize) -> &mut Self::Output {
+        &mut self.rows[y]
+    }
+}
+
+/// Serialized as the `packed` bytes
+#[cfg(feature = "serde")]
+impl serde::Serialize for FrameBuffer {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.packed())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FrameBuffer {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        FrameBuffer::from_packed(&bytes).ok_or_else(|| {
+            serde::de::Error::invalid_length(bytes.len(), &"the packed 64x32 display")
+        })
+    }
+}
+
+/// A rectangle captured from the display, e.g. just the score area
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Region {
+    /// Whether the pixel at (x, y), relative to the region, is lit
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Stable hash of the region's size and contents, but not its position,
+    /// so the same picture hashes the same wherever it was captured
+    pub fn hash(&self) -> u64 {
+        let mut bytes = vec![self.width as u8, self.height as u8];
+        for row in self.pixels.chunks(self.width.max(1)) {
+            for chunk in row.chunks(8) {
+                bytes.push(chunk.iter().fold(0, |byte, &lit| (byte << 1) | lit as u8));
+            }
+        }
+        hash::fnv1a(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region() {
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer[2][3] = 1;
+        let region = frame_buffer.region(2, 1, 4, 4);
+        assert_eq!((region.width, region.height), (4, 4));
+        assert!(region.get(1, 1));
+        assert!(!region.get(0, 0));
+
+        let clipped = frame_buffer.region(60, 30, 10, 10);
+        assert_eq!((clipped.width, clipped.height), (4, 2));
+    }
+
+    #[test]
+    fn test_region_hash() {
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer[2][3] = 1;
+        frame_buffer[20][40] = 1;
+        // Same picture in two places hashes the same
+        assert_eq!(
+            frame_buffer.region(2, 1, 4, 4).hash(),
+            frame_buffer.region(39, 19, 4, 4).hash()
+        );
+        assert_ne!(
+            frame_buffer.region(2, 1, 4, 4).hash(),
+            frame_buffer.region(2, 1, 4, 5).hash()
+        );
+        assert_ne!(frame_buffer.hash(), FrameBuffer::new().hash());
+    }
+
+    #[test]
+    fn test_packed() {
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer[0][0] = 1;
+        frame_buffer[31][63] = 1;
+        let packed = frame_buffer.packed();
+        assert_eq!(packed.len(), PACKED_SIZE);
+        assert_eq!((packed[0], packed[PACKED_SIZE - 1]), (0x80, 0x01));
+        assert_eq!(FrameBuffer::from_packed(&packed), Some(frame_buffer));
+        assert_eq!(FrameBuffer::from_packed(&packed[1..]), None);
+    }
+}
@@ -0,0 +1,82 @@
+use super::{Chip8Error, Chip8Interpreter, FrameBuffer};
+
+/// State of the machine at the end of one 60Hz frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub index: u64,
+    pub framebuffer: FrameBuffer,
+    pub sound_active: bool,
+}
+
+/// Runs the interpreter headlessly, one frame per `next()`.
+/// Ends after the ROM halts or the debugger pauses it, or after yielding
+/// the first error.
+pub struct Frames<'i> {
+    cpu: &'i mut Chip8Interpreter,
+    index: u64,
+    done: bool,
+}
+
+impl<'i> Frames<'i> {
+    pub(super) fn new(cpu: &'i mut Chip8Interpreter) -> Self {
+        Frames {
+            cpu,
+            index: 0,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<Frame, Chip8Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.cpu.halted() || self.cpu.paused().is_some() {
+            return None;
+        }
+        if let Err(err) = self.cpu.run_frame() {
+            self.done = true;
+            return Some(Err(err));
+        }
+        let frame = Frame {
+            index: self.index,
+            framebuffer: self.cpu.frame_buffer,
+            sound_active: self.cpu.sound_timer > 0,
+        };
+        self.index += 1;
+        Some(Ok(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        // 00E0, 1202: clear the screen then spin forever
+        cpu.load_rom_bytes(&[0x00, 0xE0, 0x12, 0x02]).unwrap();
+        let frames: Vec<Frame> = cpu.frames().take(3).map(Result::unwrap).collect();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].index, 2);
+        assert!(!frames[0].sound_active);
+    }
+
+    #[test]
+    fn test_frames_end_on_halt_and_error() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.load_rom_bytes(&[0x00, 0x00]).unwrap();
+        assert_eq!(cpu.frames().count(), 1);
+
+        let mut cpu = Chip8Interpreter::new();
+        cpu.load_rom_bytes(&[0xFF, 0xFF]).unwrap();
+        let mut frames = cpu.frames();
+        assert!(matches!(
+            frames.next(),
+            Some(Err(Chip8Error::Decode { .. }))
+        ));
+        assert!(frames.next().is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use super::debugger::Access;
+use super::MEMORY_SIZE;
+use crate::prelude::*;
+use core::fmt::Write;
+use core::ops::Range;
+
+/// How often each memory address was read, written and executed, to tell
+/// code from data in a ROM
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    reads: Vec<u64>,
+    writes: Vec<u64>,
+    executes: Vec<u64>,
+}
+
+impl Default for Heatmap {
+    fn default() -> Heatmap {
+        Heatmap {
+            reads: vec![0; MEMORY_SIZE as usize],
+            writes: vec![0; MEMORY_SIZE as usize],
+            executes: vec![0; MEMORY_SIZE as usize],
+        }
+    }
+}
+
+impl Heatmap {
+    /// Count the fetch of the instruction at `pc` and the memory it
+    /// accesses. Accesses past the end of memory aren't counted.
+    pub(crate) fn record(&mut self, pc: u16, access: Option<(Access, Range<usize>)>) {
+        for addr in [pc as usize, pc as usize + 1] {
+            if let Some(count) = self.executes.get_mut(addr) {
+                *count += 1;
+            }
+        }
+        let (counts, range) = match access {
+            Some((Access::Write, range)) => (&mut self.writes, range),
+            Some((_, range)) => (&mut self.reads, range),
+            None => return,
+        };
+        for addr in range {
+            if let Some(count) = counts.get_mut(addr) {
+                *count += 1;
+            }
+        }
+    }
+
+    /// Reads of `addr` by instructions such as DXYN and FX65
+    pub fn reads(&self, addr: u16) -> u64 {
+        self.reads.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    /// Writes to `addr` by FX33 and FX55
+    pub fn writes(&self, addr: u16) -> u64 {
+        self.writes.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    /// Fetches of `addr` as either byte of an instruction
+    pub fn executes(&self, addr: u16) -> u64 {
+        self.executes.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    /// The highest count of any kind, to scale a picture of the map
+    pub fn max(&self) -> u64 {
+        self.reads
+            .iter()
+            .chain(self.writes.iter())
+            .chain(self.executes.iter())
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// "addr,reads,writes,executes" and a row per address
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("addr,reads,writes,executes\n");
+        for addr in 0..MEMORY_SIZE {
+            let _ = writeln!(
+                csv,
+                "{:#05x},{},{},{}",
+                addr,
+                self.reads(addr),
+                self.writes(addr),
+                self.executes(addr)
+            );
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heatmap() {
+        let mut heatmap = Heatmap::default();
+        heatmap.record(0x200, None);
+        heatmap.record(0x202, Some((Access::Read, 0x300..0x305)));
+        heatmap.record(0x202, Some((Access::Write, 0xFFE..0x1001)));
+        assert_eq!(heatmap.executes(0x203), 2);
+        assert_eq!(heatmap.reads(0x304), 1);
+        assert_eq!(heatmap.reads(0x305), 0);
+        assert_eq!(heatmap.writes(0xFFF), 1);
+        assert_eq!(heatmap.max(), 2);
+        let csv = heatmap.to_csv();
+        assert_eq!(csv.lines().count(), MEMORY_SIZE as usize + 1);
+        assert_eq!(csv.lines().nth(0x203 + 1), Some("0x203,0,0,2"));
+        assert_eq!(csv.lines().nth(0x300 + 1), Some("0x300,1,0,0"));
+    }
+}
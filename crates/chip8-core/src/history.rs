@@ -0,0 +1,126 @@
+use super::instruction;
+use super::trace::{self, Registers};
+use alloc::collections::VecDeque;
+use core::fmt;
+
+/// An executed instruction with the registers before and after it
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub(crate) before: Registers,
+    pub(crate) after: Registers,
+}
+
+impl HistoryEntry {
+    /// V registers and I before the instruction ran
+    pub fn before(&self) -> (&[u8; 16], u16) {
+        (&self.before.0, self.before.1)
+    }
+
+    /// V registers and I after the instruction ran
+    pub fn after(&self) -> (&[u8; 16], u16) {
+        (&self.after.0, self.after.1)
+    }
+}
+
+/// "    118  0x204  600C  LD V0, 0x0C  V0 00->0c"
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>10}  {:#05x}  {:04X}  {}  {}",
+            self.cycle,
+            self.pc,
+            self.opcode,
+            instruction::mnemonic(self.opcode),
+            trace::changes(&self.before, &self.after)
+        )
+    }
+}
+
+/// The last few executed instructions and what they changed, to see how
+/// the machine got where it is
+#[derive(Clone, Debug)]
+pub struct History {
+    capacity: usize,
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn record(&mut self, entry: HistoryEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Oldest first
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    /// The `count` most recent entries, oldest first
+    pub fn last(&self, count: usize) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries
+            .iter()
+            .skip(self.entries.len().saturating_sub(count))
+    }
+}
+
+/// One line per entry, oldest first
+impl fmt::Display for History {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in self.entries.iter() {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cycle: u64) -> HistoryEntry {
+        let before = ([0; 16], 0);
+        let mut after = before;
+        after.0[0] = cycle as u8;
+        HistoryEntry {
+            cycle,
+            pc: 0x200 + cycle as u16 * 2,
+            opcode: 0x6000 | cycle as u16,
+            before,
+            after,
+        }
+    }
+
+    #[test]
+    fn test_keeps_last_entries() {
+        let mut history = History::new(3);
+        for cycle in 1..6 {
+            history.record(entry(cycle));
+        }
+        let cycles: Vec<u64> = history.entries().map(|entry| entry.cycle).collect();
+        assert_eq!(cycles, [3, 4, 5]);
+        assert_eq!(history.last(2).next().unwrap().cycle, 4);
+        assert_eq!(
+            history.to_string().lines().last(),
+            Some("         5  0x20a  6005  LD V0, 0x05  V0 00->05")
+        );
+        history.clear();
+        assert_eq!(history.entries().len(), 0);
+    }
+}
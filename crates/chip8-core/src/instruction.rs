@@ -0,0 +1,626 @@
+//! Decoding and encoding of CHIP-8 opcodes. The interpreter executes what
+//! `decode` returns, and the offline tools analyse and produce programs
+//! through the same types, so they agree on what every opcode means.
+
+use crate::prelude::*;
+use core::fmt;
+
+/// Decode a big-endian opcode word
+pub fn decode(raw_opcode: u16) -> Result<Instruction, DecodeError> {
+    Instruction::from_raw_opcode(raw_opcode)
+}
+
+/// The opcode word for an instruction, the inverse of `decode`
+pub fn encode(instruction: &Instruction) -> u16 {
+    instruction.opcode().raw
+}
+
+/// The mnemonic for an opcode word, or "????" if it doesn't decode
+pub fn mnemonic(raw_opcode: u16) -> String {
+    match decode(raw_opcode) {
+        Ok(instruction) => instruction.to_string(),
+        Err(_) => String::from("????"),
+    }
+}
+
+/// An opcode word split into its operand fields. Each field is a view of
+/// `raw`; which ones an instruction uses depends on its pattern.
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub struct Opcode {
+    pub raw: u16,
+    pub x: u8,
+    pub y: u8,
+    pub n: u8,
+    pub nnn: u16,
+    pub kk: u8,
+}
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum Instruction {
+    End(Opcode),
+    /// Clear screen
+    I00E0(Opcode),
+    /// Return subroutine. i.e: pc = stack.pop()
+    I00EE(Opcode),
+
+    /// Call machine code routine at nnn, handled by the SYS policy
+    I0NNN(Opcode),
+
+    /// Jump to instruction ~ pc = nnn
+    I1NNN(Opcode),
+
+    /// Create subroutine ~ stack.push(pc) & pc = nnn
+    I2NNN(Opcode),
+
+    /// Skip next instruction if x = nn
+    I3XNN(Opcode),
+
+    /// Skip next instruction if x != nn
+    I4XNN(Opcode),
+
+    /// Skip next instruction if x == y
+    I5XY0(Opcode),
+
+    /// Set v[x] = nn
+    I6XNN(Opcode),
+
+    /// Add nn to v[x] ~ v[x] += nn
+    I7XNN(Opcode),
+
+    /// Set v[x] = v[y]
+    I8XY0(Opcode),
+
+    /// v[x] = v[x] OR v[y]
+    I8XY1(Opcode), 
+
+    /// v[x] = v[x] AND v[y]
+    I8XY2(Opcode), 
+
+    /// v[x] = v[x] XOR v[y]
+    I8XY3(Opcode), 
+
+    /// v[x] = v[x] + v[y], set v[f] = 1 if overflow
+    I8XY4(Opcode), 
+
+    /// v[x] = v[x] - v[y], set v[f] = 0 if underflow
+    I8XY5(Opcode), 
+
+    /// v[x] = v[y] - v[x], set v[f] = 0 if underflow
+    I8XY7(Opcode), 
+
+    /// if old_shift: v[x] = v[y], v[x] >> 1 
+    I8XY6(Opcode), 
+
+    /// if old_shift: v[x] = v[y], v[x] << 1 
+    I8XYE(Opcode), 
+
+    /// Skip next instruction if x != y
+    I9XY0(Opcode),
+
+    /// Set vi = nnn
+    IANNN(Opcode),
+
+    /// Jump to address nnn + v[0], or with jump_vx to xnn + v[x]
+    IBNNN(Opcode),
+
+    /// vx = rand() & nn
+    ICXNN(Opcode),
+
+    /// vi += v[x], with add_i_overflow: v[f] = 1 if vi > 0xFFF else 0
+    IFX1E(Opcode),
+
+    /// Draw
+    IDXYN(Opcode),
+
+    /// Skip next instruction if key v[x] is pressed
+    IEX9E(Opcode),
+
+    /// Skip next instruction if key v[x] is not pressed
+    IEXA1(Opcode),
+
+    /// Wait until a key is pressed and released, then v[x] = key
+    IFX0A(Opcode),
+
+    /// v[x] = delay timer
+    IFX07(Opcode),
+
+    /// delay timer = v[x]
+    IFX15(Opcode),
+
+    /// sound timer = v[x]
+    IFX18(Opcode),
+
+    /// Set vi to the font sprite for the hex digit in v[x]
+    IFX29(Opcode),
+
+    /// Store the decimal digits of v[x] at vi, vi + 1 and vi + 2
+    IFX33(Opcode),
+
+    /// Store v[0] to v[x] at vi onwards
+    IFX55(Opcode),
+
+    /// Load v[0] to v[x] from vi onwards
+    IFX65(Opcode),
+
+    /// XO-CHIP: load the 16-byte audio pattern from vi onwards
+    IF002(Opcode),
+
+    /// XO-CHIP: set the audio pattern's pitch to v[x]
+    IFX3A(Opcode),
+
+    /// SCHIP: store v[0] to v[x] in the RPL user flags, x at most 7
+    IFX75(Opcode),
+
+    /// SCHIP: load v[0] to v[x] from the RPL user flags, x at most 7
+    IFX85(Opcode),
+}
+
+/// Why an opcode doesn't decode to any instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The family has no instruction with this low nibble or byte, e.g.
+    /// 8XYF or EX00
+    UnknownVariant(u16),
+    /// 5XYN and 9XYN only exist with N = 0
+    NonZeroNibble(u16),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnknownVariant(raw) => write!(f, "no instruction {:04X} in the {:X}xxx family", raw, raw >> 12),
+            DecodeError::NonZeroNibble(raw) => write!(f, "{:04X} needs a last nibble of 0", raw),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Cowgod's mnemonics, e.g. "LD V3, 0x1F", "DRW V0, V1, 5" or "JP 0x2A4",
+/// with "END" for 0000 and Octo's names for the XO-CHIP audio
+/// instructions
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = self.opcode();
+        let (x, y, kk, nnn) = (op.x, op.y, op.kk, op.nnn);
+        match self {
+            Instruction::End(_) => write!(f, "END"),
+            Instruction::I00E0(_) => write!(f, "CLS"),
+            Instruction::I00EE(_) => write!(f, "RET"),
+            Instruction::I0NNN(_) => write!(f, "SYS 0x{:03X}", nnn),
+            Instruction::I1NNN(_) => write!(f, "JP 0x{:03X}", nnn),
+            Instruction::I2NNN(_) => write!(f, "CALL 0x{:03X}", nnn),
+            Instruction::I3XNN(_) => write!(f, "SE V{:X}, 0x{:02X}", x, kk),
+            Instruction::I4XNN(_) => write!(f, "SNE V{:X}, 0x{:02X}", x, kk),
+            Instruction::I5XY0(_) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::I6XNN(_) => write!(f, "LD V{:X}, 0x{:02X}", x, kk),
+            Instruction::I7XNN(_) => write!(f, "ADD V{:X}, 0x{:02X}", x, kk),
+            Instruction::I8XY0(_) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::I8XY1(_) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::I8XY2(_) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::I8XY3(_) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::I8XY4(_) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::I8XY5(_) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::I8XY6(_) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::I8XY7(_) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::I8XYE(_) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::I9XY0(_) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::IANNN(_) => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::IBNNN(_) => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::ICXNN(_) => write!(f, "RND V{:X}, 0x{:02X}", x, kk),
+            Instruction::IDXYN(_) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, op.n),
+            Instruction::IEX9E(_) => write!(f, "SKP V{:X}", x),
+            Instruction::IEXA1(_) => write!(f, "SKNP V{:X}", x),
+            Instruction::IFX07(_) => write!(f, "LD V{:X}, DT", x),
+            Instruction::IFX0A(_) => write!(f, "LD V{:X}, K", x),
+            Instruction::IFX15(_) => write!(f, "LD DT, V{:X}", x),
+            Instruction::IFX18(_) => write!(f, "LD ST, V{:X}", x),
+            Instruction::IFX1E(_) => write!(f, "ADD I, V{:X}", x),
+            Instruction::IFX29(_) => write!(f, "LD F, V{:X}", x),
+            Instruction::IFX33(_) => write!(f, "LD B, V{:X}", x),
+            Instruction::IFX55(_) => write!(f, "LD [I], V{:X}", x),
+            Instruction::IFX65(_) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::IF002(_) => write!(f, "AUDIO"),
+            Instruction::IFX3A(_) => write!(f, "PITCH V{:X}", x),
+            Instruction::IFX75(_) => write!(f, "LD R, V{:X}", x),
+            Instruction::IFX85(_) => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
+
+impl Instruction {
+    pub fn from_raw_opcode(raw_opcode: u16) -> Result<Instruction, DecodeError> {
+        let opcode = Opcode::new(raw_opcode);
+        let family = raw_opcode >> 12;
+        let instruction = match (family, opcode.n) {
+            (0x0, _) => match raw_opcode {
+                0x0000 => Instruction::End(opcode),
+                0x00E0 => Instruction::I00E0(opcode),
+                0x00EE => Instruction::I00EE(opcode),
+                _ => Instruction::I0NNN(opcode),
+            },
+            (0x1, _) => Instruction::I1NNN(opcode),
+            (0x2, _) => Instruction::I2NNN(opcode),
+            (0x3, _) => Instruction::I3XNN(opcode),
+            (0x4, _) => Instruction::I4XNN(opcode),
+            (0x5, 0x0) => Instruction::I5XY0(opcode),
+            (0x6, _) => Instruction::I6XNN(opcode),
+            (0x7, _) => Instruction::I7XNN(opcode),
+            (0x8, 0x0) => Instruction::I8XY0(opcode),
+            (0x8, 0x1) => Instruction::I8XY1(opcode),
+            (0x8, 0x2) => Instruction::I8XY2(opcode),
+            (0x8, 0x3) => Instruction::I8XY3(opcode),
+            (0x8, 0x4) => Instruction::I8XY4(opcode),
+            (0x8, 0x5) => Instruction::I8XY5(opcode),
+            (0x8, 0x6) => Instruction::I8XY6(opcode),
+            (0x8, 0x7) => Instruction::I8XY7(opcode),
+            (0x8, 0xE) => Instruction::I8XYE(opcode),
+            (0x9, 0x0) => Instruction::I9XY0(opcode),
+            (0xA, _) => Instruction::IANNN(opcode),
+            (0xB, _) => Instruction::IBNNN(opcode),
+            (0xC, _) => Instruction::ICXNN(opcode),
+            (0xD, _) => Instruction::IDXYN(opcode),
+            (0xE, _) => match opcode.kk {
+                0x9E => Instruction::IEX9E(opcode),
+                0xA1 => Instruction::IEXA1(opcode),
+                _ => return Err(DecodeError::UnknownVariant(raw_opcode)),
+            },
+            (0xF, _) => match opcode.kk {
+                0x07 => Instruction::IFX07(opcode),
+                0x0A => Instruction::IFX0A(opcode),
+                0x15 => Instruction::IFX15(opcode),
+                0x18 => Instruction::IFX18(opcode),
+                0x1E => Instruction::IFX1E(opcode),
+                0x29 => Instruction::IFX29(opcode),
+                0x33 => Instruction::IFX33(opcode),
+                0x55 => Instruction::IFX55(opcode),
+                0x65 => Instruction::IFX65(opcode),
+                0x02 if opcode.x == 0 => Instruction::IF002(opcode),
+                0x3A => Instruction::IFX3A(opcode),
+                0x75 => Instruction::IFX75(opcode),
+                0x85 => Instruction::IFX85(opcode),
+                _ => return Err(DecodeError::UnknownVariant(raw_opcode)),
+            },
+            (0x5, _) | (0x9, _) => return Err(DecodeError::NonZeroNibble(raw_opcode)),
+            _ => return Err(DecodeError::UnknownVariant(raw_opcode)),
+        };
+        Ok(instruction)
+    }
+
+    /// Opcode pattern as written in the spec, e.g. "DXYN"
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Instruction::End(_) => "0000",
+            Instruction::I00E0(_) => "00E0",
+            Instruction::I00EE(_) => "00EE",
+            Instruction::I0NNN(_) => "0NNN",
+            Instruction::I1NNN(_) => "1NNN",
+            Instruction::I2NNN(_) => "2NNN",
+            Instruction::I3XNN(_) => "3XNN",
+            Instruction::I4XNN(_) => "4XNN",
+            Instruction::I5XY0(_) => "5XY0",
+            Instruction::I6XNN(_) => "6XNN",
+            Instruction::I7XNN(_) => "7XNN",
+            Instruction::I8XY0(_) => "8XY0",
+            Instruction::I8XY1(_) => "8XY1",
+            Instruction::I8XY2(_) => "8XY2",
+            Instruction::I8XY3(_) => "8XY3",
+            Instruction::I8XY4(_) => "8XY4",
+            Instruction::I8XY5(_) => "8XY5",
+            Instruction::I8XY7(_) => "8XY7",
+            Instruction::I8XY6(_) => "8XY6",
+            Instruction::I8XYE(_) => "8XYE",
+            Instruction::I9XY0(_) => "9XY0",
+            Instruction::IANNN(_) => "ANNN",
+            Instruction::IBNNN(_) => "BNNN",
+            Instruction::ICXNN(_) => "CXNN",
+            Instruction::IFX1E(_) => "FX1E",
+            Instruction::IDXYN(_) => "DXYN",
+            Instruction::IEX9E(_) => "EX9E",
+            Instruction::IEXA1(_) => "EXA1",
+            Instruction::IFX0A(_) => "FX0A",
+            Instruction::IFX07(_) => "FX07",
+            Instruction::IFX15(_) => "FX15",
+            Instruction::IFX18(_) => "FX18",
+            Instruction::IFX29(_) => "FX29",
+            Instruction::IFX33(_) => "FX33",
+            Instruction::IFX55(_) => "FX55",
+            Instruction::IFX65(_) => "FX65",
+            Instruction::IF002(_) => "F002",
+            Instruction::IFX3A(_) => "FX3A",
+            Instruction::IFX75(_) => "FX75",
+            Instruction::IFX85(_) => "FX85",
+        }
+    }
+
+    /// The opcode word, see `encode`
+    pub fn raw(&self) -> u16 {
+        self.opcode().raw
+    }
+
+    /// Register operand X, the second nibble
+    pub fn x(&self) -> u8 {
+        self.opcode().x
+    }
+
+    /// Register operand Y, the third nibble
+    pub fn y(&self) -> u8 {
+        self.opcode().y
+    }
+
+    /// 4-bit immediate N, the last nibble
+    pub fn n(&self) -> u8 {
+        self.opcode().n
+    }
+
+    /// 8-bit immediate NN, the low byte
+    pub fn kk(&self) -> u8 {
+        self.opcode().kk
+    }
+
+    /// 12-bit address NNN
+    pub fn nnn(&self) -> u16 {
+        self.opcode().nnn
+    }
+
+    pub fn opcode(&self) -> &Opcode {
+        match self {
+            Instruction::End(op)
+            | Instruction::I00E0(op)
+            | Instruction::I00EE(op)
+            | Instruction::I0NNN(op)
+            | Instruction::I1NNN(op)
+            | Instruction::I2NNN(op)
+            | Instruction::I3XNN(op)
+            | Instruction::I4XNN(op)
+            | Instruction::I5XY0(op)
+            | Instruction::I6XNN(op)
+            | Instruction::I7XNN(op)
+            | Instruction::I8XY0(op)
+            | Instruction::I8XY1(op)
+            | Instruction::I8XY2(op)
+            | Instruction::I8XY3(op)
+            | Instruction::I8XY4(op)
+            | Instruction::I8XY5(op)
+            | Instruction::I8XY7(op)
+            | Instruction::I8XY6(op)
+            | Instruction::I8XYE(op)
+            | Instruction::I9XY0(op)
+            | Instruction::IANNN(op)
+            | Instruction::IBNNN(op)
+            | Instruction::ICXNN(op)
+            | Instruction::IFX1E(op)
+            | Instruction::IDXYN(op)
+            | Instruction::IEX9E(op)
+            | Instruction::IEXA1(op)
+            | Instruction::IFX0A(op)
+            | Instruction::IFX07(op)
+            | Instruction::IFX15(op)
+            | Instruction::IFX18(op)
+            | Instruction::IFX29(op)
+            | Instruction::IFX33(op)
+            | Instruction::IFX55(op)
+            | Instruction::IFX65(op)
+            | Instruction::IF002(op)
+            | Instruction::IFX3A(op)
+            | Instruction::IFX75(op)
+            | Instruction::IFX85(op) => op,
+        }
+    }
+}
+
+impl Opcode {
+    pub fn new(raw: u16) -> Opcode {
+        let x = take_param_x(raw);
+        let y = take_param_y(raw);
+        let n = take_param_n(raw);
+        let nnn = take_param_nnn(raw);
+        let kk = take_param_kk(raw);
+
+        Opcode {raw, x, y, n, nnn, kk}
+    }
+
+    /// Assemble `family` (the first nibble) with an address, e.g. 1NNN
+    pub fn with_nnn(family: u8, nnn: u16) -> Opcode {
+        Opcode::new((family as u16 & 0xF) << 12 | nnn & 0xFFF)
+    }
+
+    /// Assemble `family` with a register and a byte, e.g. 6XNN
+    pub fn with_x_kk(family: u8, x: u8, kk: u8) -> Opcode {
+        Opcode::with_nnn(family, (x as u16 & 0xF) << 8 | kk as u16)
+    }
+
+    /// Assemble `family` with two registers and a nibble, e.g. DXYN
+    pub fn with_x_y_n(family: u8, x: u8, y: u8, n: u8) -> Opcode {
+        Opcode::with_x_kk(family, x, (y & 0xF) << 4 | n & 0xF)
+    }
+
+    /// The first nibble, which picks the instruction family
+    pub fn family(&self) -> u8 {
+        (self.raw >> 12) as u8
+    }
+}
+
+impl PartialEq<u16> for Opcode {
+    fn eq(&self, raw: &u16) -> bool {
+        self.raw == *raw
+    }
+}
+
+fn take_param_n(opcode: u16) -> u8 {
+    (opcode & 0x000F) as u8
+}
+
+fn take_param_nnn(opcode: u16) -> u16 {
+    opcode & 0x0FFF
+}
+
+fn take_param_kk(opcode: u16) -> u8 {
+    (opcode & 0x00FF) as u8
+}
+
+fn take_param_x(opcode: u16) -> u8 {
+    ((opcode & 0x0F00) >> 8) as u8
+}
+
+fn take_param_y(opcode: u16) -> u8 {
+    ((opcode & 0x00F0) >> 4) as u8
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_param_n() {
+        assert_eq!(take_param_n(0x8C74), 0x04);
+        assert_eq!(take_param_n(0x8B74), 0x04);
+    }
+
+    #[test]
+    fn test_take_param_nnn() {
+        assert_eq!(take_param_nnn(0x8C74), 0x0C74);
+        assert_eq!(take_param_nnn(0xAB12), 0x0B12);
+    }
+
+    #[test]
+    fn test_take_param_kk() {
+        assert_eq!(take_param_kk(0x8C74), 0x74);
+        assert_eq!(take_param_kk(0xAB12), 0x12);
+    }
+
+    #[test]
+    fn test_take_param_x() {
+        assert_eq!(take_param_x(0x8C74), 0x0C);
+        assert_eq!(take_param_x(0xAB12), 0x0B);
+    }
+
+    #[test]
+    fn test_take_param_y() {
+        assert_eq!(take_param_y(0x8C74), 0x07);
+        assert_eq!(take_param_y(0xAB12), 0x01);
+    }
+
+    #[test]
+    fn test_instruction_from_raw_code() {
+        assert_eq!(Instruction::from_raw_opcode(0x0).unwrap(), Instruction::End(Opcode::new(0x0)));
+        assert_eq!(Instruction::from_raw_opcode(0xE0).unwrap(), Instruction::I00E0(Opcode::new(0xE0)));
+        assert_eq!(Instruction::from_raw_opcode(0xEE).unwrap(), Instruction::I00EE(Opcode::new(0xEE)));
+        assert_eq!(Instruction::from_raw_opcode(0x0234).unwrap(), Instruction::I0NNN(Opcode::new(0x0234)));
+        assert_eq!(Instruction::from_raw_opcode(0x1234).unwrap(), Instruction::I1NNN(Opcode::new(0x1234)));
+        assert_eq!(Instruction::from_raw_opcode(0x2234).unwrap(), Instruction::I2NNN(Opcode::new(0x2234)));
+        assert_eq!(Instruction::from_raw_opcode(0x3234).unwrap(), Instruction::I3XNN(Opcode::new(0x3234)));
+        assert_eq!(Instruction::from_raw_opcode(0x4234).unwrap(), Instruction::I4XNN(Opcode::new(0x4234)));
+        assert_eq!(Instruction::from_raw_opcode(0x5230).unwrap(), Instruction::I5XY0(Opcode::new(0x5230)));
+        assert_eq!(Instruction::from_raw_opcode(0x6234).unwrap(), Instruction::I6XNN(Opcode::new(0x6234)));
+        assert_eq!(Instruction::from_raw_opcode(0x7234).unwrap(), Instruction::I7XNN(Opcode::new(0x7234)));
+        assert_eq!(Instruction::from_raw_opcode(0x8230).unwrap(), Instruction::I8XY0(Opcode::new(0x8230)));
+        assert_eq!(Instruction::from_raw_opcode(0x8231).unwrap(), Instruction::I8XY1(Opcode::new(0x8231)));
+        assert_eq!(Instruction::from_raw_opcode(0x8232).unwrap(), Instruction::I8XY2(Opcode::new(0x8232)));
+        assert_eq!(Instruction::from_raw_opcode(0x8233).unwrap(), Instruction::I8XY3(Opcode::new(0x8233)));
+        assert_eq!(Instruction::from_raw_opcode(0x8234).unwrap(), Instruction::I8XY4(Opcode::new(0x8234)));
+        assert_eq!(Instruction::from_raw_opcode(0x8235).unwrap(), Instruction::I8XY5(Opcode::new(0x8235)));
+        assert_eq!(Instruction::from_raw_opcode(0x8236).unwrap(), Instruction::I8XY6(Opcode::new(0x8236)));
+        assert_eq!(Instruction::from_raw_opcode(0x8237).unwrap(), Instruction::I8XY7(Opcode::new(0x8237)));
+        assert_eq!(Instruction::from_raw_opcode(0x823E).unwrap(), Instruction::I8XYE(Opcode::new(0x823E)));
+        assert_eq!(Instruction::from_raw_opcode(0x9230).unwrap(), Instruction::I9XY0(Opcode::new(0x9230)));
+        assert_eq!(Instruction::from_raw_opcode(0xA234).unwrap(), Instruction::IANNN(Opcode::new(0xA234)));
+        assert_eq!(Instruction::from_raw_opcode(0xB234).unwrap(), Instruction::IBNNN(Opcode::new(0xB234)));
+        assert_eq!(Instruction::from_raw_opcode(0xC234).unwrap(), Instruction::ICXNN(Opcode::new(0xC234)));
+        assert_eq!(Instruction::from_raw_opcode(0xD234).unwrap(), Instruction::IDXYN(Opcode::new(0xD234)));
+        assert_eq!(Instruction::from_raw_opcode(0xE39E).unwrap(), Instruction::IEX9E(Opcode::new(0xE39E)));
+        assert_eq!(Instruction::from_raw_opcode(0xE3A1).unwrap(), Instruction::IEXA1(Opcode::new(0xE3A1)));
+        assert_eq!(Instruction::from_raw_opcode(0xE3A2), Err(DecodeError::UnknownVariant(0xE3A2)));
+        assert_eq!(Instruction::from_raw_opcode(0xF30A).unwrap(), Instruction::IFX0A(Opcode::new(0xF30A)));
+        assert_eq!(Instruction::from_raw_opcode(0xF307).unwrap(), Instruction::IFX07(Opcode::new(0xF307)));
+        assert_eq!(Instruction::from_raw_opcode(0xF315).unwrap(), Instruction::IFX15(Opcode::new(0xF315)));
+        assert_eq!(Instruction::from_raw_opcode(0xF318).unwrap(), Instruction::IFX18(Opcode::new(0xF318)));
+        assert_eq!(Instruction::from_raw_opcode(0xF31E).unwrap(), Instruction::IFX1E(Opcode::new(0xF31E)));
+        assert_eq!(Instruction::from_raw_opcode(0xF329).unwrap(), Instruction::IFX29(Opcode::new(0xF329)));
+        assert_eq!(Instruction::from_raw_opcode(0xF333).unwrap(), Instruction::IFX33(Opcode::new(0xF333)));
+        assert_eq!(Instruction::from_raw_opcode(0xF355).unwrap(), Instruction::IFX55(Opcode::new(0xF355)));
+        assert_eq!(Instruction::from_raw_opcode(0xF365).unwrap(), Instruction::IFX65(Opcode::new(0xF365)));
+        assert_eq!(Instruction::from_raw_opcode(0xF002).unwrap(), Instruction::IF002(Opcode::new(0xF002)));
+        assert!(Instruction::from_raw_opcode(0xF102).is_err());
+        assert_eq!(Instruction::from_raw_opcode(0xF33A).unwrap(), Instruction::IFX3A(Opcode::new(0xF33A)));
+        assert_eq!(Instruction::from_raw_opcode(0xF375).unwrap(), Instruction::IFX75(Opcode::new(0xF375)));
+        assert_eq!(Instruction::from_raw_opcode(0xF385).unwrap(), Instruction::IFX85(Opcode::new(0xF385)));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(Instruction::from_raw_opcode(0x823F), Err(DecodeError::UnknownVariant(0x823F)));
+        assert_eq!(Instruction::from_raw_opcode(0xF3FF), Err(DecodeError::UnknownVariant(0xF3FF)));
+        assert_eq!(Instruction::from_raw_opcode(0x5231), Err(DecodeError::NonZeroNibble(0x5231)));
+        assert_eq!(Instruction::from_raw_opcode(0x9232), Err(DecodeError::NonZeroNibble(0x9232)));
+        assert_eq!(DecodeError::UnknownVariant(0x823F).to_string(), "no instruction 823F in the 8xxx family");
+    }
+
+    #[test]
+    fn test_pattern() {
+        assert_eq!(Instruction::from_raw_opcode(0xD235).unwrap().pattern(), "DXYN");
+        assert_eq!(Instruction::from_raw_opcode(0x823E).unwrap().pattern(), "8XYE");
+        assert_eq!(Instruction::from_raw_opcode(0xA22A).unwrap().opcode().nnn, 0x22A);
+    }
+
+    #[test]
+    fn test_display() {
+        let mnemonics = [
+            (0x0000, "END"),
+            (0x00E0, "CLS"),
+            (0x00EE, "RET"),
+            (0x12A4, "JP 0x2A4"),
+            (0x2300, "CALL 0x300"),
+            (0x631F, "LD V3, 0x1F"),
+            (0x7A01, "ADD VA, 0x01"),
+            (0x8124, "ADD V1, V2"),
+            (0x812E, "SHL V1, V2"),
+            (0xA22A, "LD I, 0x22A"),
+            (0xB300, "JP V0, 0x300"),
+            (0xD015, "DRW V0, V1, 5"),
+            (0xE4A1, "SKNP V4"),
+            (0xF20A, "LD V2, K"),
+            (0xF555, "LD [I], V5"),
+            (0xF265, "LD V2, [I]"),
+            (0xF002, "AUDIO"),
+            (0xF385, "LD V3, R"),
+        ];
+        for (raw, text) in mnemonics {
+            assert_eq!(decode(raw).unwrap().to_string(), text);
+        }
+        assert_eq!(mnemonic(0x8CDF), "????");
+    }
+
+    #[test]
+    fn test_encode() {
+        for raw in [0x00E0, 0x1234, 0x6A2B, 0x8125, 0xD01F, 0xF355] {
+            let instruction = decode(raw).unwrap();
+            assert_eq!(encode(&instruction), raw);
+            assert_eq!(instruction.raw(), raw);
+        }
+        assert_eq!(Opcode::with_nnn(0x1, 0x234).raw, 0x1234);
+        assert_eq!(Opcode::with_x_kk(0x6, 0xA, 0x2B).raw, 0x6A2B);
+        assert_eq!(Opcode::with_x_y_n(0xD, 0x0, 0x1, 0xF).raw, 0xD01F);
+        assert_eq!(Opcode::with_x_y_n(0x8, 0x1, 0x2, 0x5).family(), 0x8);
+        let draw = decode(0xD23F).unwrap();
+        assert_eq!((draw.x(), draw.y(), draw.n()), (0x2, 0x3, 0xF));
+        assert_eq!(decode(0x7A42).unwrap().kk(), 0x42);
+        assert_eq!(decode(0xA123).unwrap().nnn(), 0x123);
+    }
+
+    #[test]
+    fn test_opcode() {
+        let op = Opcode::new(0xFABC);
+        assert_eq!(op.kk, 0xBC);
+        assert_eq!(op.nnn, 0xABC);
+        assert_eq!(op.x, 0xA);
+        assert_eq!(op.y, 0xB);
+        assert_eq!(op.n, 0xC);
+    }
+}
\ No newline at end of file
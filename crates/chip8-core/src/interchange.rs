@@ -0,0 +1,196 @@
+//! JSON interchange format for machine state, meant for moving states
+//! between this emulator and other tools:
+//!
+//! ```json
+//! {
+//!   "format": "chip8-state",
+//!   "version": 1,
+//!   "v": [0, 0, ...],          // V0-VF
+//!   "i": 554, "pc": 528, "dt": 0, "st": 0,
+//!   "stack": [514],            // return addresses, oldest first
+//!   "memory": "<base64>",      // all 4096 bytes
+//!   "display": {
+//!     "width": 64, "height": 32,
+//!     "bits": "<base64>"       // rows top to bottom, 8 pixels per byte, MSB leftmost
+//!   },
+//!   "quirks": {                // optional
+//!     "old_shift": false, "add_i_overflow": false, ...
+//!   },
+//!   "rom_hash": "<16 hex digits>" // optional, FNV-1a of the ROM
+//! }
+//! ```
+use super::framebuffer::PACKED_SIZE;
+use super::{
+    FrameBuffer, MachineState, Quirks, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH, MEMORY_SIZE,
+};
+use crate::prelude::*;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+
+const FORMAT: &str = "chip8-state";
+const VERSION: u64 = 1;
+
+impl MachineState {
+    pub fn to_json(&self) -> String {
+        let mut value = json!({
+            "format": FORMAT,
+            "version": VERSION,
+            "v": self.registers_v,
+            "i": self.register_i,
+            "pc": self.register_pc,
+            "dt": self.delay_timer,
+            "st": self.sound_timer,
+            "stack": self.stack,
+            "memory": BASE64.encode(self.mem),
+            "display": {
+                "width": FRAME_BUFFER_WIDTH,
+                "height": FRAME_BUFFER_HEIGHT,
+                "bits": BASE64.encode(self.frame_buffer.packed()),
+            },
+        });
+        if let Some(quirks) = self.quirks {
+            let flags = Quirks::FLAGS
+                .iter()
+                .map(|&flag| (flag.to_string(), Value::Bool(quirks.get(flag).unwrap())))
+                .collect();
+            value["quirks"] = Value::Object(flags);
+        }
+        if let Some(rom_hash) = self.rom_hash {
+            value["rom_hash"] = json!(format!("{:016x}", rom_hash));
+        }
+        serde_json::to_string_pretty(&value).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<MachineState, String> {
+        let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        if value["format"] != FORMAT {
+            return Err(format!("not a {} document", FORMAT));
+        }
+        if value["version"] != VERSION {
+            return Err(format!("unsupported version {}", value["version"]));
+        }
+
+        let registers = value["v"].as_array().filter(|v| v.len() == 16);
+        let registers = registers.ok_or("\"v\" must hold 16 registers")?;
+        let mut registers_v = [0; 16];
+        for (reg, value) in registers_v.iter_mut().zip(registers) {
+            *reg = number(value, "v", 0xFF)? as u8;
+        }
+        let stack = value["stack"]
+            .as_array()
+            .ok_or("\"stack\" must be a list")?;
+        let stack = stack
+            .iter()
+            .map(|addr| number(addr, "stack", 0xFFFF).map(|addr| addr as u16))
+            .collect::<Result<Vec<u16>, String>>()?;
+
+        let memory = base64_field(&value["memory"], "memory", MEMORY_SIZE as usize)?;
+        let mut mem = [0; MEMORY_SIZE as usize];
+        mem.copy_from_slice(&memory);
+
+        let display = &value["display"];
+        if display["width"] != FRAME_BUFFER_WIDTH || display["height"] != FRAME_BUFFER_HEIGHT {
+            return Err(format!(
+                "display must be {}x{}",
+                FRAME_BUFFER_WIDTH, FRAME_BUFFER_HEIGHT
+            ));
+        }
+        let bits = base64_field(&display["bits"], "display.bits", PACKED_SIZE)?;
+        let frame_buffer = FrameBuffer::from_packed(&bits).unwrap();
+
+        let rom_hash = match &value["rom_hash"] {
+            Value::Null => None,
+            hash => Some(
+                hash.as_str()
+                    .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                    .ok_or("\"rom_hash\" must be a hex string")?,
+            ),
+        };
+        let quirks = match &value["quirks"] {
+            Value::Null => None,
+            flags => {
+                let mut quirks = Quirks::default();
+                for flag in Quirks::FLAGS {
+                    let on = flags[flag]
+                        .as_bool()
+                        .ok_or_else(|| format!("\"quirks.{}\" must be true or false", flag))?;
+                    quirks.set(flag, on)?;
+                }
+                Some(quirks)
+            }
+        };
+
+        Ok(MachineState {
+            registers_v,
+            register_i: number(&value["i"], "i", 0xFFFF)? as u16,
+            delay_timer: number(&value["dt"], "dt", 0xFFFF)? as u16,
+            sound_timer: number(&value["st"], "st", 0xFFFF)? as u16,
+            register_pc: number(&value["pc"], "pc", 0xFFFF)? as u16,
+            stack,
+            mem,
+            frame_buffer,
+            quirks,
+            rom_hash,
+        })
+    }
+}
+
+fn number(value: &Value, field: &str, max: u64) -> Result<u64, String> {
+    value
+        .as_u64()
+        .filter(|&n| n <= max)
+        .ok_or_else(|| format!("\"{}\" must be a number up to {}", field, max))
+}
+
+fn base64_field(value: &Value, field: &str, len: usize) -> Result<Vec<u8>, String> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| format!("\"{}\" must be a base64 string", field))?;
+    let bytes = BASE64
+        .decode(text)
+        .map_err(|err| format!("\"{}\": {}", field, err))?;
+    if bytes.len() != len {
+        return Err(format!("\"{}\" must hold {} bytes", field, len));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8Interpreter;
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.registers_v[0xA] = 0x7F;
+        cpu.register_pc = 0x2F4;
+        cpu.stack = vec![0x204];
+        cpu.mem[0x300] = 0xAB;
+        cpu.frame_buffer[31][63] = 1;
+        cpu.frame_buffer[0][0] = 1;
+        let state = MachineState::of(&cpu);
+        let json = state.to_json();
+        assert_eq!(MachineState::from_json(&json), Ok(state));
+    }
+
+    #[test]
+    fn test_json_rejects_bad_documents() {
+        let state = MachineState::of(&Chip8Interpreter::new());
+        let mut value: Value = serde_json::from_str(&state.to_json()).unwrap();
+        value["v"][3] = json!(256);
+        assert!(MachineState::from_json(&value.to_string()).is_err());
+        value["v"][3] = json!(0);
+        value["quirks"]["jump_vx"] = json!(1);
+        assert!(MachineState::from_json(&value.to_string()).is_err());
+        value["quirks"] = Value::Null;
+        assert_eq!(
+            MachineState::from_json(&value.to_string()).unwrap().quirks,
+            None
+        );
+        value["memory"] = json!("AAAA");
+        assert!(MachineState::from_json(&value.to_string()).is_err());
+        assert!(MachineState::from_json("{}").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use super::{Chip8Error, Chip8Interpreter, MEMORY_SIZE};
+use crate::prelude::*;
+use alloc::collections::VecDeque;
+use core::fmt::Write;
+
+/// Rows of 16 bytes shown before and after the faulting instruction's row
+const MEMORY_CONTEXT_ROWS: usize = 2;
+
+/// One executed instruction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JournalEntry {
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+}
+
+/// Keeps the last few executed instructions so that, when the machine
+/// faults, a single file with everything needed for a bug report can be
+/// written
+pub struct Journal {
+    path: String,
+    capacity: usize,
+    entries: VecDeque<JournalEntry>,
+}
+
+impl Journal {
+    pub fn new(path: &str, capacity: usize) -> Journal {
+        Journal {
+            path: path.to_string(),
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.entries.iter()
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// Render the journal for a fault that just happened on `cpu`
+pub fn report(cpu: &Chip8Interpreter, journal: &Journal, fault: &Chip8Error) -> String {
+    let mut out = String::from("chip8emu journal\n");
+    writeln!(out, "rom hash: {:016x}", cpu.rom_hash).unwrap();
+    writeln!(
+        out,
+        "settings: quirks={:?} (configured: {}), strict={}, stack_limit={:?}, out_of_bounds={:?}, sys={:?}",
+        cpu.quirks,
+        cpu.quirks_configured,
+        cpu.strict,
+        cpu.stack_limit,
+        cpu.out_of_bounds,
+        cpu.sys_policy
+    )
+    .unwrap();
+    writeln!(out, "fault at cycle {}: {}", cpu.cycles, fault).unwrap();
+    write!(out, "{}", cpu.state()).unwrap();
+    writeln!(out, "last {} instructions:", journal.entries.len()).unwrap();
+    for entry in journal.entries() {
+        writeln!(
+            out,
+            "  {:>10}  {:#05x}  {:04X}",
+            entry.cycle, entry.pc, entry.opcode
+        )
+        .unwrap();
+    }
+    let pc = journal
+        .entries
+        .back()
+        .map_or(cpu.register_pc, |entry| entry.pc);
+    writeln!(out, "memory around {:#05x}:", pc).unwrap();
+    let row = pc as usize / 16;
+    let rows = row.saturating_sub(MEMORY_CONTEXT_ROWS)
+        ..(row + MEMORY_CONTEXT_ROWS + 1).min(MEMORY_SIZE as usize / 16);
+    for row in rows {
+        let bytes: Vec<String> = cpu.mem[row * 16..row * 16 + 16]
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        let marker = if row == pc as usize / 16 { '>' } else { ' ' };
+        writeln!(out, "{} {:#05x}  {}", marker, row * 16, bytes.join(" ")).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_keeps_last_entries() {
+        let mut journal = Journal::new("unused", 2);
+        for cycle in 0..5 {
+            journal.record(JournalEntry {
+                cycle,
+                pc: 0x200,
+                opcode: 0x1200,
+            });
+        }
+        let cycles: Vec<u64> = journal.entries().map(|e| e.cycle).collect();
+        assert_eq!(cycles, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_report() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.enable_journal("unused", 8);
+        // 6012: V0 = 0x12, then an undecodable opcode
+        cpu.load_rom_bytes(&[0x60, 0x12, 0xFF, 0xFF]).unwrap();
+        cpu.step().unwrap();
+        let fault = cpu.step().unwrap_err();
+        let report = report(&cpu, cpu.journal.as_ref().unwrap(), &fault);
+        assert!(report.contains("fault at cycle 1: cannot decode instruction 0xffff"));
+        assert!(report.contains("V0 12"));
+        assert!(report.contains("0x200  6012"));
+        assert!(report.contains("0x202  FFFF"));
+        assert!(report.contains("memory around 0x202:\n  0x1e0"));
+        assert!(report.contains("> 0x200  60 12 FF FF 00"));
+    }
+}
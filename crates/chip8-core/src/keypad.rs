@@ -0,0 +1,78 @@
+/// Number of keys on the hex keypad, 0 to F
+pub const KEYPAD_SIZE: u8 = 16;
+
+/// State of the 16-key hex keypad, one bit per key
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Keypad {
+    pressed: u16,
+    /// Keys whose next press is to be lost, see `drop_next_press`
+    drop_next: u16,
+    /// Keys held down while their press is being ignored
+    swallowed: u16,
+}
+
+impl Keypad {
+    /// Update one key from a host key event or polled key state
+    pub fn set(&mut self, key: u8, down: bool) {
+        let bit = 1 << (key & 0xF);
+        if !down {
+            self.pressed &= !bit;
+            self.swallowed &= !bit;
+        } else if self.pressed & bit == 0 && self.swallowed & bit == 0 {
+            if self.drop_next & bit != 0 {
+                self.drop_next &= !bit;
+                self.swallowed |= bit;
+            } else {
+                self.pressed |= bit;
+            }
+        }
+    }
+
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.pressed & (1 << (key & 0xF)) != 0
+    }
+
+    /// Whether the host has `key` down, also while its press is ignored
+    pub(crate) fn is_held(&self, key: u8) -> bool {
+        (self.pressed | self.swallowed) & (1 << (key & 0xF)) != 0
+    }
+
+    /// Keys currently held down, lowest first
+    pub fn pressed(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..KEYPAD_SIZE).filter(move |&key| self.is_pressed(key))
+    }
+
+    /// Lose the next press of `key` until it is released again, as a flaky
+    /// keyboard would
+    pub(crate) fn drop_next_press(&mut self, key: u8) {
+        self.drop_next |= 1 << (key & 0xF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypad() {
+        let mut keypad = Keypad::default();
+        keypad.set(0xA, true);
+        keypad.set(0x3, true);
+        assert_eq!(keypad.pressed().collect::<Vec<_>>(), [0x3, 0xA]);
+        keypad.set(0xA, false);
+        assert!(!keypad.is_pressed(0xA));
+        assert!(keypad.is_pressed(0x3));
+    }
+
+    #[test]
+    fn test_drop_next_press() {
+        let mut keypad = Keypad::default();
+        keypad.drop_next_press(0x5);
+        keypad.set(0x5, true);
+        keypad.set(0x5, true);
+        assert!(!keypad.is_pressed(0x5));
+        keypad.set(0x5, false);
+        keypad.set(0x5, true);
+        assert!(keypad.is_pressed(0x5));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::instruction::Instruction;
+use crate::prelude::*;
+
+/// Bytes overwritten by a hot patch, kept to undo it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patch {
+    pub addr: u16,
+    pub original: Vec<u8>,
+}
+
+/// Parse a snippet of opcodes written as hex words, e.g. "6005 A300 D015",
+/// checking that each one decodes
+pub fn parse_snippet(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    for word in text.split_whitespace() {
+        let word = word.trim_start_matches("0x");
+        let opcode = u16::from_str_radix(word, 16)
+            .ok()
+            .filter(|_| word.len() == 4)
+            .ok_or_else(|| format!("expected a 4-digit hex opcode, got {}", word))?;
+        Instruction::from_raw_opcode(opcode).map_err(|err| err.to_string())?;
+        bytes.extend_from_slice(&opcode.to_be_bytes());
+    }
+    if bytes.is_empty() {
+        return Err(String::from("empty snippet"));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snippet() {
+        assert_eq!(
+            parse_snippet("6005 0xA300"),
+            Ok(vec![0x60, 0x05, 0xA3, 0x00])
+        );
+        assert!(parse_snippet("").is_err());
+        assert!(parse_snippet("600").is_err());
+        assert!(parse_snippet("E3A2").is_err());
+    }
+}
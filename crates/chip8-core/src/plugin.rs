@@ -0,0 +1,129 @@
+//! Extension points for code outside this repository. Plugins are plain
+//! trait objects: a crate that wants to extend the emulator implements one
+//! of the traits below and registers a factory for it under a name, which
+//! users then pick on the command line.
+use super::FrameBuffer;
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+
+/// Observes execution, e.g. to gather statistics about a ROM
+pub trait AnalysisPass {
+    fn name(&self) -> &str;
+    /// Called after each instruction that executed without a fault
+    fn observe(&mut self, pc: u16, opcode: u16);
+    /// Summary shown when the run ends
+    fn report(&self) -> String;
+}
+
+/// Turns the display into pixels for a window of the given size
+pub trait Renderer {
+    /// Returns `width` * `height` 0RGB pixels, row by row
+    fn render(&mut self, frame_buffer: &FrameBuffer, width: usize, height: usize) -> Vec<u32>;
+}
+
+pub type AnalysisFactory = Box<dyn Fn() -> Box<dyn AnalysisPass>>;
+pub type RendererFactory = Box<dyn Fn() -> Box<dyn Renderer>>;
+
+/// Named plugin factories, filled in at startup before options are parsed
+#[derive(Default)]
+pub struct Registry {
+    analyses: BTreeMap<String, AnalysisFactory>,
+    renderers: BTreeMap<String, RendererFactory>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    pub fn register_analysis(
+        &mut self,
+        name: &str,
+        factory: AnalysisFactory,
+    ) -> Result<(), String> {
+        insert_unique(&mut self.analyses, "analysis", name, factory)
+    }
+
+    pub fn register_renderer(
+        &mut self,
+        name: &str,
+        factory: RendererFactory,
+    ) -> Result<(), String> {
+        insert_unique(&mut self.renderers, "renderer", name, factory)
+    }
+
+    pub fn analysis(&self, name: &str) -> Option<Box<dyn AnalysisPass>> {
+        self.analyses.get(name).map(|factory| factory())
+    }
+
+    pub fn renderer(&self, name: &str) -> Option<Box<dyn Renderer>> {
+        self.renderers.get(name).map(|factory| factory())
+    }
+
+    pub fn analysis_names(&self) -> impl Iterator<Item = &str> {
+        self.analyses.keys().map(String::as_str)
+    }
+
+    pub fn renderer_names(&self) -> impl Iterator<Item = &str> {
+        self.renderers.keys().map(String::as_str)
+    }
+}
+
+fn insert_unique<T>(
+    map: &mut BTreeMap<String, T>,
+    kind: &str,
+    name: &str,
+    value: T,
+) -> Result<(), String> {
+    if map.contains_key(name) {
+        return Err(format!("{} {} is already registered", kind, name));
+    }
+    map.insert(name.to_string(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8Interpreter;
+
+    struct CountJumps(u64);
+
+    impl AnalysisPass for CountJumps {
+        fn name(&self) -> &str {
+            "jumps"
+        }
+
+        fn observe(&mut self, _pc: u16, opcode: u16) {
+            if opcode >> 12 == 0x1 {
+                self.0 += 1;
+            }
+        }
+
+        fn report(&self) -> String {
+            format!("{} jumps", self.0)
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::new();
+        registry
+            .register_analysis("jumps", Box::new(|| Box::new(CountJumps(0))))
+            .unwrap();
+        assert!(registry
+            .register_analysis("jumps", Box::new(|| Box::new(CountJumps(0))))
+            .is_err());
+        assert_eq!(registry.analysis_names().collect::<Vec<_>>(), ["jumps"]);
+        assert!(registry.analysis("calls").is_none());
+
+        let mut cpu = Chip8Interpreter::new();
+        cpu.add_analysis(registry.analysis("jumps").unwrap());
+        cpu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        for _ in 0..3 {
+            cpu.step().unwrap();
+        }
+        let reports: Vec<String> = cpu.analyses().map(|pass| pass.report()).collect();
+        assert_eq!(reports, ["3 jumps"]);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::framebuffer::{FrameBuffer, PACKED_SIZE};
+
+/// Where the COSMAC VIP interpreter keeps V0-VF and the display
+const VIP_REGISTERS: usize = 0xEF0;
+const VIP_DISPLAY: usize = 0xF00;
+
+/// What the registers and the display hold at power on. Memory itself is
+/// zeros and the font, or the power-on image if one is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PowerOn {
+    /// Both cleared, as modern interpreters start
+    #[default]
+    Blank,
+    /// The COSMAC VIP interpreter keeps V0-VF at 0xEF0-0xEFF and the
+    /// display at 0xF00-0xFFF and clears neither when it starts, so a
+    /// program finds whatever memory held there. The interpreter itself in
+    /// 0x000-0x1FF is RCA's code and isn't bundled; pass a dump of a real
+    /// machine as the power-on image for that area and the ones above.
+    CosmacVip,
+}
+
+impl PowerOn {
+    pub fn from_name(name: &str) -> Option<PowerOn> {
+        match name {
+            "blank" => Some(PowerOn::Blank),
+            "vip" => Some(PowerOn::CosmacVip),
+            _ => None,
+        }
+    }
+
+    /// V0-VF at power on, given the initial memory
+    pub(crate) fn registers(self, mem: &[u8]) -> [u8; 16] {
+        let mut registers = [0; 16];
+        if self == PowerOn::CosmacVip {
+            if let Some(saved) = mem.get(VIP_REGISTERS..VIP_REGISTERS + 16) {
+                registers.copy_from_slice(saved);
+            }
+        }
+        registers
+    }
+
+    /// The display at power on, given the initial memory
+    pub(crate) fn display(self, mem: &[u8]) -> FrameBuffer {
+        match self {
+            PowerOn::Blank => FrameBuffer::new(),
+            // One bit per pixel, rows top to bottom, like the packed form
+            PowerOn::CosmacVip => mem
+                .get(VIP_DISPLAY..VIP_DISPLAY + PACKED_SIZE)
+                .and_then(FrameBuffer::from_packed)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(PowerOn::from_name("vip"), Some(PowerOn::CosmacVip));
+        assert_eq!(PowerOn::from_name("random"), None);
+    }
+
+    #[test]
+    fn test_vip_layout() {
+        let mut mem = [0; 4096];
+        mem[0xEF0] = 0x11;
+        mem[0xEFF] = 0xFF;
+        mem[0xF00] = 0x80;
+        mem[0xFFF] = 0x01;
+        let registers = PowerOn::CosmacVip.registers(&mem);
+        assert_eq!((registers[0], registers[15]), (0x11, 0xFF));
+        assert_eq!(PowerOn::Blank.registers(&mem), [0; 16]);
+        let display = PowerOn::CosmacVip.display(&mem);
+        assert_eq!((display[0][0], display[0][1], display[31][63]), (1, 0, 1));
+        assert_eq!(PowerOn::Blank.display(&mem), FrameBuffer::new());
+        // Too little memory to hold them reads as cleared
+        assert_eq!(PowerOn::CosmacVip.registers(&mem[..0xEFF]), [0; 16]);
+        assert_eq!(
+            PowerOn::CosmacVip.display(&mem[..0xFFF]),
+            FrameBuffer::new()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use super::instruction::Instruction;
+use super::PROGRAM_START;
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+use core::cmp::Reverse;
+use core::fmt;
+
+/// Attributes executed instructions to the chain of subroutine calls that
+/// was active at the time, following 2NNN and 00EE
+#[derive(Debug)]
+pub struct CallProfile {
+    stack: Vec<u16>,
+    counts: BTreeMap<Vec<u16>, u64>,
+}
+
+/// A subroutine in the call tree. `total` includes everything it called,
+/// `own` only counts its own instructions.
+#[derive(Debug, PartialEq)]
+pub struct FlameNode {
+    pub addr: u16,
+    pub total: u64,
+    pub own: u64,
+    pub children: Vec<FlameNode>,
+}
+
+impl CallProfile {
+    pub fn new() -> CallProfile {
+        CallProfile {
+            stack: vec![PROGRAM_START],
+            counts: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, inst: &Instruction) {
+        *self.counts.entry(self.stack.clone()).or_insert(0) += 1;
+        match inst {
+            Instruction::I2NNN(opcode) => self.stack.push(opcode.nnn),
+            // Never pop the entry point, even if the ROM returns too often
+            Instruction::I00EE(_) if self.stack.len() > 1 => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Build the call tree, children sorted by how much time they took
+    pub fn tree(&self) -> FlameNode {
+        let mut root = FlameNode::new(PROGRAM_START);
+        for (path, &count) in self.counts.iter() {
+            let mut node = &mut root;
+            node.total += count;
+            for &addr in &path[1..] {
+                let idx = match node.children.iter().position(|child| child.addr == addr) {
+                    Some(idx) => idx,
+                    None => {
+                        node.children.push(FlameNode::new(addr));
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[idx];
+                node.total += count;
+            }
+            node.own += count;
+        }
+        root.sort();
+        root
+    }
+}
+
+impl Default for CallProfile {
+    fn default() -> Self {
+        CallProfile::new()
+    }
+}
+
+impl FlameNode {
+    fn new(addr: u16) -> FlameNode {
+        FlameNode {
+            addr,
+            total: 0,
+            own: 0,
+            children: vec![],
+        }
+    }
+
+    fn sort(&mut self) {
+        self.children.sort_by_key(|child| Reverse(child.total));
+        for child in self.children.iter_mut() {
+            child.sort();
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, depth: usize, root_total: u64) -> fmt::Result {
+        const BAR_WIDTH: u64 = 40;
+        let bar = (self.total * BAR_WIDTH / root_total.max(1)) as usize;
+        writeln!(
+            f,
+            "{:indent$}{:#05x} {:<bar_width$} {:>5.1}% ({} own)",
+            "",
+            self.addr,
+            "█".repeat(bar.max(1)),
+            self.total as f64 * 100. / root_total.max(1) as f64,
+            self.own,
+            indent = depth * 2,
+            bar_width = BAR_WIDTH as usize,
+        )?;
+        for child in self.children.iter() {
+            child.write(f, depth + 1, root_total)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text flame view: one line per subroutine, indented by call depth, with
+/// a bar for its share of all executed instructions
+impl fmt::Display for FlameNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0, self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(profile: &mut CallProfile, opcodes: &[u16]) {
+        for &opcode in opcodes {
+            profile.record(&Instruction::from_raw_opcode(opcode).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_call_tree() {
+        let mut profile = CallProfile::new();
+        // main calls 0x300 which calls 0x400, then calls 0x400 directly
+        run(
+            &mut profile,
+            &[
+                0x6000, 0x2300, 0x6000, 0x2400, 0x6000, 0x00EE, 0x00EE, 0x2400, 0x00EE,
+            ],
+        );
+        let tree = profile.tree();
+        assert_eq!((tree.addr, tree.total, tree.own), (0x200, 9, 3));
+        assert_eq!(tree.children.len(), 2);
+        let sub_300 = &tree.children[0];
+        assert_eq!((sub_300.addr, sub_300.total, sub_300.own), (0x300, 5, 3));
+        assert_eq!(
+            (sub_300.children[0].addr, sub_300.children[0].total),
+            (0x400, 2)
+        );
+        let sub_400 = &tree.children[1];
+        assert_eq!((sub_400.addr, sub_400.total, sub_400.own), (0x400, 1, 1));
+    }
+}
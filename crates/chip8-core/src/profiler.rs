@@ -0,0 +1,104 @@
+use super::instruction::Instruction;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// How often each instruction variant ran and the host time spent
+/// executing it, to see where time goes when tuning the clock speed
+#[derive(Clone, Debug, Default)]
+pub struct OpcodeProfile {
+    opcodes: BTreeMap<&'static str, (u64, Duration)>,
+}
+
+impl OpcodeProfile {
+    pub fn record(&mut self, inst: &Instruction, elapsed: Duration) {
+        let (count, time) = self.opcodes.entry(inst.pattern()).or_default();
+        *count += 1;
+        *time += elapsed;
+    }
+
+    /// Executions of the instructions matching `pattern`, e.g. "DXYN"
+    pub fn count(&self, pattern: &str) -> u64 {
+        self.opcodes.get(pattern).map_or(0, |&(count, _)| count)
+    }
+
+    /// Host time spent executing the instructions matching `pattern`
+    pub fn time(&self, pattern: &str) -> Duration {
+        self.opcodes
+            .get(pattern)
+            .map_or(Duration::ZERO, |&(_, time)| time)
+    }
+
+    pub fn total_count(&self) -> u64 {
+        self.opcodes.values().map(|&(count, _)| count).sum()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.opcodes.values().map(|&(_, time)| time).sum()
+    }
+}
+
+/// A row per variant, the most expensive first
+impl fmt::Display for OpcodeProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total_count = self.total_count();
+        let total_time = self.total_time();
+        let mut rows: Vec<_> = self.opcodes.iter().collect();
+        rows.sort_by(|a, b| (b.1).1.cmp(&(a.1).1).then(a.0.cmp(b.0)));
+        writeln!(
+            f,
+            "{:<6}{:>10}{:>12}{:>8}{:>10}",
+            "opcode", "count", "time (us)", "time%", "mean (ns)"
+        )?;
+        for (pattern, &(count, time)) in rows {
+            writeln!(
+                f,
+                "{:<6}{:>10}{:>12}{:>7.1}%{:>10}",
+                pattern,
+                count,
+                time.as_micros(),
+                time.as_secs_f64() * 100. / total_time.as_secs_f64().max(f64::MIN_POSITIVE),
+                time.as_nanos() / count.max(1) as u128
+            )?;
+        }
+        let mean = total_time.as_secs_f64() / total_count.max(1) as f64;
+        writeln!(
+            f,
+            "{} instructions in {:.3}ms, {:.0}ns each, ~{:.0} per second at most",
+            total_count,
+            total_time.as_secs_f64() * 1000.,
+            mean * 1e9,
+            1. / mean.max(f64::MIN_POSITIVE)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let mut profile = OpcodeProfile::default();
+        let load = Instruction::from_raw_opcode(0x6012).unwrap();
+        let draw = Instruction::from_raw_opcode(0xD015).unwrap();
+        profile.record(&load, Duration::from_nanos(100));
+        profile.record(&load, Duration::from_nanos(300));
+        profile.record(&draw, Duration::from_micros(3));
+        assert_eq!(profile.count("6XNN"), 2);
+        assert_eq!(profile.time("6XNN"), Duration::from_nanos(400));
+        assert_eq!(profile.count("00E0"), 0);
+        assert_eq!(profile.total_count(), 3);
+        let report = profile.to_string();
+        let rows: Vec<Vec<&str>> = report
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(rows[1], ["DXYN", "1", "3", "88.2%", "3000"]);
+        assert_eq!(rows[2], ["6XNN", "2", "0", "11.8%", "200"]);
+        assert_eq!(
+            report.lines().last(),
+            Some("3 instructions in 0.003ms, 1133ns each, ~882353 per second at most")
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::instruction::Instruction;
+use crate::prelude::*;
+
+/// Behaviours that differ between CHIP-8 interpreters. ROMs written for one
+/// interpreter may misbehave on another, so these are selectable per ROM.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quirks {
+    /// 8XY6/8XYE copy v[y] into v[x] before shifting (COSMAC VIP)
+    pub old_shift: bool,
+    /// FX1E sets VF to 1 when I passes 0xFFF and to 0 otherwise, as the
+    /// Amiga interpreter did (Spacefight 2091! relies on it)
+    pub add_i_overflow: bool,
+    /// FX55/FX65 leave I pointing past the last register stored or loaded
+    /// (COSMAC VIP) rather than unchanged
+    pub memory_increment: bool,
+    /// BNNN is read as BXNN and jumps to XNN + VX (CHIP-48, SUPER-CHIP)
+    /// instead of NNN + V0
+    pub jump_vx: bool,
+}
+
+impl Quirks {
+    /// Original COSMAC VIP interpreter
+    pub const COSMAC_VIP: Quirks = Quirks {
+        old_shift: true,
+        add_i_overflow: false,
+        memory_increment: true,
+        jump_vx: false,
+    };
+    /// CHIP-48 on the HP-48 calculators
+    pub const CHIP_48: Quirks = Quirks {
+        old_shift: false,
+        add_i_overflow: false,
+        memory_increment: false,
+        jump_vx: true,
+    };
+    /// SUPER-CHIP 1.1, which inherited all of these from CHIP-48. It is
+    /// accepted as a preset name but not listed as a separate preset.
+    pub const SCHIP: Quirks = Quirks::CHIP_48;
+
+    pub const PRESETS: [(&'static str, Quirks); 2] = [
+        ("cosmac-vip", Quirks::COSMAC_VIP),
+        ("chip-48", Quirks::CHIP_48),
+    ];
+
+    /// Names of the individual quirk settings
+    pub const FLAGS: [&'static str; 4] =
+        ["old_shift", "add_i_overflow", "memory_increment", "jump_vx"];
+
+    pub fn preset(name: &str) -> Option<Quirks> {
+        if name == "schip" {
+            return Some(Quirks::SCHIP);
+        }
+        Quirks::PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|&(_, quirks)| quirks)
+    }
+
+    /// Whether a single quirk, named as in `FLAGS`, is on
+    pub fn get(&self, flag: &str) -> Option<bool> {
+        match flag {
+            "old_shift" => Some(self.old_shift),
+            "add_i_overflow" => Some(self.add_i_overflow),
+            "memory_increment" => Some(self.memory_increment),
+            "jump_vx" => Some(self.jump_vx),
+            _ => None,
+        }
+    }
+
+    /// Turn a single quirk, named as in `FLAGS`, on or off
+    pub fn set(&mut self, flag: &str, on: bool) -> Result<(), String> {
+        match flag {
+            "old_shift" => self.old_shift = on,
+            "add_i_overflow" => self.add_i_overflow = on,
+            "memory_increment" => self.memory_increment = on,
+            "jump_vx" => self.jump_vx = on,
+            _ => return Err(format!("unknown quirk '{}'", flag)),
+        }
+        Ok(())
+    }
+
+    /// One bit per quirk, in `FLAGS` order from the lowest, as saved in
+    /// state files
+    pub fn bits(&self) -> u8 {
+        Quirks::FLAGS
+            .iter()
+            .enumerate()
+            .filter(|(_, flag)| self.get(flag) == Some(true))
+            .fold(0, |bits, (bit, _)| bits | 1 << bit)
+    }
+
+    pub fn from_bits(bits: u8) -> Quirks {
+        let mut quirks = Quirks::default();
+        for (bit, flag) in Quirks::FLAGS.iter().enumerate() {
+            quirks.set(flag, bits & 1 << bit != 0).unwrap();
+        }
+        quirks
+    }
+
+    /// Whether the result of the instruction depends on any quirk setting
+    pub fn affects(inst: &Instruction) -> bool {
+        matches!(
+            inst,
+            Instruction::I8XY6(_)
+                | Instruction::I8XYE(_)
+                | Instruction::IBNNN(_)
+                | Instruction::IFX1E(_)
+                | Instruction::IFX55(_)
+                | Instruction::IFX65(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset() {
+        assert_eq!(Quirks::preset("cosmac-vip"), Some(Quirks::COSMAC_VIP));
+        assert_eq!(Quirks::preset("schip"), Some(Quirks::SCHIP));
+        assert_eq!(Quirks::preset("nope"), None);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut quirks = Quirks::CHIP_48;
+        quirks.set("add_i_overflow", true).unwrap();
+        assert!(quirks.add_i_overflow);
+        assert_eq!(quirks.get("add_i_overflow"), Some(true));
+        assert_eq!(quirks.get("memory_increment"), Some(false));
+        assert!(quirks.set("wrap_sprites", true).is_err());
+    }
+
+    #[test]
+    fn test_bits() {
+        assert_eq!(Quirks::COSMAC_VIP.bits(), 0b0101);
+        for (_, quirks) in Quirks::PRESETS {
+            assert_eq!(Quirks::from_bits(quirks.bits()), quirks);
+        }
+    }
+
+    #[test]
+    fn test_affects() {
+        assert!(Quirks::affects(
+            &Instruction::from_raw_opcode(0x8126).unwrap()
+        ));
+        assert!(!Quirks::affects(
+            &Instruction::from_raw_opcode(0x8124).unwrap()
+        ));
+    }
+}
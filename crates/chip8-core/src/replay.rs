@@ -0,0 +1,239 @@
+use super::{Quirks, KEYPAD_SIZE};
+use crate::prelude::*;
+use core::fmt;
+
+/// First line of every replay file
+const HEADER: &str = "chip8 replay 1";
+
+/// Input the machine took from the host during a recorded run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// A keypad key went down or came up
+    Key { key: u8, down: bool },
+    /// The delay and sound timers counted down
+    TimerTick,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayEvent {
+    /// Taken right before the instruction at this cycle executes
+    pub cycle: u64,
+    pub input: Input,
+}
+
+/// Everything a run took from outside the program: the RNG seed, keypad
+/// changes and timer ticks, each at the cycle it happened. Playing it back
+/// on the same ROM and quirks reproduces the run exactly, whether it was
+/// recorded in a window against the wall clock or headlessly.
+///
+/// Saved as text, one event per line after a short header:
+///
+/// ```text
+/// chip8 replay 1
+/// seed 2a
+/// rom 3a6b1cf0e1d22a57
+/// quirks 05
+/// 118 tick
+/// 120 down A
+/// 131 up A
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    /// Hash of the ROM the run was recorded on
+    pub rom_hash: u64,
+    pub quirks: Quirks,
+    /// Oldest first
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, rom_hash: u64, quirks: Quirks) -> Replay {
+        Replay {
+            seed,
+            rom_hash,
+            quirks,
+            events: vec![],
+        }
+    }
+
+    pub(crate) fn record(&mut self, cycle: u64, input: Input) {
+        self.events.push(ReplayEvent { cycle, input });
+    }
+
+    /// Cycle of the last event, after which playback hands the keypad and
+    /// timers back to the host
+    pub fn length(&self) -> u64 {
+        self.events.last().map_or(0, |event| event.cycle)
+    }
+
+    /// Whether the replay can be played on the ROM with `rom_hash`
+    pub fn check_rom(&self, rom_hash: u64) -> Result<(), String> {
+        if self.rom_hash != rom_hash {
+            return Err(format!(
+                "replay was recorded on a different ROM (hash {:016x}, running {:016x})",
+                self.rom_hash, rom_hash
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(format!("not a replay, expected '{}' first", HEADER));
+        }
+        let mut field = |name: &str| match lines.next() {
+            Some((number, line)) => line
+                .strip_prefix(name)
+                .and_then(|value| u64::from_str_radix(value.trim(), 16).ok())
+                .ok_or_else(|| format!("line {}: expected {} and a hex number", number, name)),
+            None => Err(format!("expected {} before the events", name)),
+        };
+        let seed = field("seed")?;
+        let rom_hash = field("rom")?;
+        let quirks = Quirks::from_bits(field("quirks")? as u8);
+        let mut replay = Replay::new(seed, rom_hash, quirks);
+        for (number, line) in lines {
+            let event = parse_event(line)
+                .ok_or_else(|| format!("line {}: invalid event '{}'", number, line))?;
+            if event.cycle < replay.length() {
+                return Err(format!("line {}: event out of order", number));
+            }
+            replay.events.push(event);
+        }
+        Ok(replay)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        Replay::parse(&text).map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+/// "CYCLE tick", "CYCLE down KEY" or "CYCLE up KEY", the key in hex
+fn parse_event(line: &str) -> Option<ReplayEvent> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let input = match parts[1..] {
+        ["tick"] => Input::TimerTick,
+        [change @ ("down" | "up"), key] => Input::Key {
+            key: u8::from_str_radix(key, 16)
+                .ok()
+                .filter(|&key| key < KEYPAD_SIZE)?,
+            down: change == "down",
+        },
+        _ => return None,
+    };
+    Some(ReplayEvent {
+        cycle: parts.first()?.parse().ok()?,
+        input,
+    })
+}
+
+/// The replay file format, see `Replay`
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "seed {:x}", self.seed)?;
+        writeln!(f, "rom {:016x}", self.rom_hash)?;
+        writeln!(f, "quirks {:02x}", self.quirks.bits())?;
+        for event in &self.events {
+            match event.input {
+                Input::Key { key, down: true } => writeln!(f, "{} down {:X}", event.cycle, key)?,
+                Input::Key { key, down: false } => writeln!(f, "{} up {:X}", event.cycle, key)?,
+                Input::TimerTick => writeln!(f, "{} tick", event.cycle)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A replay being played back, with how far it got
+pub(crate) struct Playback {
+    pub replay: Replay,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback { replay, next: 0 }
+    }
+
+    /// Take the next event if it is due before the instruction at `cycle`
+    pub fn next_due(&mut self, cycle: u64) -> Option<Input> {
+        let event = self.replay.events.get(self.next)?;
+        if event.cycle > cycle {
+            return None;
+        }
+        self.next += 1;
+        Some(event.input)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next == self.replay.events.len()
+    }
+
+    pub fn restart(&mut self) {
+        self.next = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut replay = Replay::new(42, 0x3a6b1cf0e1d22a57, Quirks::CHIP_48);
+        replay.record(118, Input::TimerTick);
+        replay.record(
+            120,
+            Input::Key {
+                key: 0xA,
+                down: true,
+            },
+        );
+        replay.record(
+            131,
+            Input::Key {
+                key: 0xA,
+                down: false,
+            },
+        );
+        let text = replay.to_string();
+        assert!(text.starts_with("chip8 replay 1\nseed 2a\nrom 3a6b1cf0e1d22a57\n"));
+        assert!(text.ends_with("118 tick\n120 down A\n131 up A\n"));
+        assert_eq!(Replay::parse(&text), Ok(replay));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let header = "chip8 replay 1\nseed 1\nrom 0\nquirks 0\n";
+        assert!(Replay::parse("seed 1\n").is_err());
+        assert!(Replay::parse("chip8 replay 1\nseed 1\n").is_err());
+        assert!(Replay::parse(&format!("{}5 down 10\n", header)).is_err());
+        assert!(Replay::parse(&format!("{}5 press A\n", header)).is_err());
+        assert_eq!(
+            Replay::parse(&format!("{}5 tick\n4 tick\n", header)),
+            Err("line 6: event out of order".to_string())
+        );
+        assert!(Replay::parse(&format!("{}\n5 tick\n", header)).is_ok());
+    }
+
+    #[test]
+    fn test_check_rom() {
+        let replay = Replay::new(0, 0x1234, Quirks::default());
+        assert!(replay.check_rom(0x1234).is_ok());
+        assert!(replay.check_rom(0x1235).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use super::MachineState;
+use alloc::collections::VecDeque;
+
+/// The machine states before recent instructions, newest last, to step
+/// back through. Taking one every instruction allows stepping backwards
+/// in a debugger; one per frame is enough to rewind a game.
+pub struct Rewind {
+    states: VecDeque<MachineState>,
+    capacity: usize,
+    /// Instructions between snapshots
+    interval: u64,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: u64) -> Rewind {
+        Rewind {
+            states: VecDeque::with_capacity(capacity),
+            capacity,
+            interval: interval.max(1),
+        }
+    }
+
+    /// Whether a snapshot is due before running instruction `cycle`
+    pub fn due(&self, cycle: u64) -> bool {
+        self.capacity > 0 && cycle.is_multiple_of(self.interval)
+    }
+
+    pub fn record(&mut self, state: MachineState) {
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(state);
+    }
+
+    /// Take out the most recent snapshot
+    pub fn pop(&mut self) -> Option<MachineState> {
+        self.states.pop_back()
+    }
+
+    /// Snapshots left to go back through
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8Interpreter;
+
+    #[test]
+    fn test_capacity() {
+        let mut rewind = Rewind::new(2, 1);
+        let mut cpu = Chip8Interpreter::new();
+        for value in 1..=3 {
+            cpu.set_v(0, value);
+            rewind.record(cpu.state());
+        }
+        assert_eq!(rewind.len(), 2);
+        assert_eq!(rewind.pop().unwrap().registers_v[0], 3);
+        assert_eq!(rewind.pop().unwrap().registers_v[0], 2);
+        assert!(rewind.pop().is_none());
+    }
+
+    #[test]
+    fn test_due() {
+        let rewind = Rewind::new(10, 4);
+        assert!(rewind.due(0));
+        assert!(!rewind.due(3));
+        assert!(rewind.due(8));
+        assert!(!Rewind::new(0, 1).due(0));
+    }
+}
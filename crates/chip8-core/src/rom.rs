@@ -0,0 +1,205 @@
+use crate::prelude::*;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+/// Somewhere a ROM image can be read from. All frontends load ROMs through
+/// this, so errors and hashing are handled the same everywhere.
+pub trait RomSource {
+    /// Describes the source in messages, e.g. the file path or URL
+    fn name(&self) -> String;
+    fn read(&self) -> Result<Vec<u8>, RomError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RomError {
+    pub source: String,
+    pub message: String,
+}
+
+impl RomError {
+    pub fn new(source: &dyn RomSource, message: impl fmt::Display) -> RomError {
+        RomError {
+            source: source.name(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RomError {}
+
+/// A ROM file on disk
+#[cfg(feature = "std")]
+pub struct FileRom {
+    pub path: PathBuf,
+}
+
+#[cfg(feature = "std")]
+impl FileRom {
+    pub fn new(path: impl Into<PathBuf>) -> FileRom {
+        FileRom { path: path.into() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl RomSource for FileRom {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn read(&self) -> Result<Vec<u8>, RomError> {
+        std::fs::read(&self.path).map_err(|err| RomError::new(self, err))
+    }
+}
+
+/// A ROM already in memory, e.g. from `include_bytes!`
+pub struct BytesRom<'a> {
+    pub name: String,
+    pub data: &'a [u8],
+}
+
+impl RomSource for BytesRom<'_> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn read(&self) -> Result<Vec<u8>, RomError> {
+        Ok(self.data.to_vec())
+    }
+}
+
+/// An entry in a zip archive. Without an entry name, the first .ch8 file
+/// in the archive is used.
+#[cfg(feature = "zip")]
+pub struct ZipRom {
+    pub archive: PathBuf,
+    pub entry: Option<String>,
+}
+
+#[cfg(feature = "zip")]
+impl RomSource for ZipRom {
+    fn name(&self) -> String {
+        match &self.entry {
+            Some(entry) => format!("{}#{}", self.archive.display(), entry),
+            None => self.archive.display().to_string(),
+        }
+    }
+
+    fn read(&self) -> Result<Vec<u8>, RomError> {
+        use std::io::Read;
+
+        let file = std::fs::File::open(&self.archive).map_err(|err| RomError::new(self, err))?;
+        let mut archive = zip::ZipArchive::new(file).map_err(|err| RomError::new(self, err))?;
+        let entry = match &self.entry {
+            Some(entry) => entry.clone(),
+            None => archive
+                .file_names()
+                .filter(|name| name.ends_with(".ch8"))
+                .min()
+                .ok_or_else(|| RomError::new(self, "no .ch8 file in archive"))?
+                .to_string(),
+        };
+        let mut rom = vec![];
+        archive
+            .by_name(&entry)
+            .map_err(|err| RomError::new(self, err))?
+            .read_to_end(&mut rom)
+            .map_err(|err| RomError::new(self, err))?;
+        Ok(rom)
+    }
+}
+
+/// A ROM downloaded over HTTP(S)
+#[cfg(feature = "http")]
+pub struct HttpRom {
+    pub url: String,
+}
+
+#[cfg(feature = "http")]
+impl RomSource for HttpRom {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    fn read(&self) -> Result<Vec<u8>, RomError> {
+        use std::io::Read;
+
+        let response = ureq::get(&self.url)
+            .call()
+            .map_err(|err| RomError::new(self, err))?;
+        let mut rom = vec![];
+        // Anything bigger than memory is rejected later, no need to read it all
+        response
+            .into_reader()
+            .take(super::MEMORY_SIZE as u64 + 1)
+            .read_to_end(&mut rom)
+            .map_err(|err| RomError::new(self, err))?;
+        Ok(rom)
+    }
+}
+
+/// Whether `open` can fetch ROMs from URLs in this build
+pub fn supports_urls() -> bool {
+    cfg!(feature = "http")
+}
+
+/// Pick a source from a command line argument: `http(s)://...` URLs,
+/// `archive.zip` or `archive.zip#entry.ch8`, or else a plain file path
+#[cfg(feature = "std")]
+pub fn open(spec: &str) -> Box<dyn RomSource> {
+    #[cfg(feature = "http")]
+    {
+        if spec.starts_with("http://") || spec.starts_with("https://") {
+            return Box::new(HttpRom {
+                url: spec.to_string(),
+            });
+        }
+    }
+    #[cfg(feature = "zip")]
+    {
+        let (archive, entry) = match spec.split_once('#') {
+            Some((archive, entry)) => (archive, Some(entry.to_string())),
+            None => (spec, None),
+        };
+        if archive.ends_with(".zip") {
+            return Box::new(ZipRom {
+                archive: PathBuf::from(archive),
+                entry,
+            });
+        }
+    }
+    Box::new(FileRom::new(spec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_file_rom() {
+        let rom = open("tests/resource/0xABBC.txt");
+        assert_eq!(rom.read(), Ok(vec![0xAB, 0xBC]));
+
+        let missing = open("tests/resource/missing.ch8");
+        let err = missing.read().unwrap_err();
+        assert_eq!(err.source, "tests/resource/missing.ch8");
+    }
+
+    #[test]
+    fn test_bytes_rom() {
+        let rom = BytesRom {
+            name: String::from("inline"),
+            data: &[0x12, 0x00],
+        };
+        assert_eq!(rom.name(), "inline");
+        assert_eq!(rom.read(), Ok(vec![0x12, 0x00]));
+    }
+}
@@ -0,0 +1,188 @@
+use super::Chip8Interpreter;
+use crate::prelude::*;
+use core::fmt;
+
+/// One micro program run by `Chip8Interpreter::self_check`
+struct Case {
+    name: &'static str,
+    program: &'static [u8],
+    /// Timer ticks after the program ran, standing in for the host clock
+    ticks: usize,
+    verify: fn(&Chip8Interpreter) -> Result<(), String>,
+}
+
+const CASES: [Case; 7] = [
+    Case {
+        name: "add with carry",
+        // V0 = FF, V1 = 02, V0 += V1
+        program: &[0x60, 0xFF, 0x61, 0x02, 0x80, 0x14],
+        ticks: 0,
+        verify: |cpu| expect_registers(cpu, &[(0x0, 0x01), (0xF, 1)]),
+    },
+    Case {
+        name: "subtract with borrow",
+        // V0 = 01, V1 = 02, V0 -= V1, which borrows and so clears VF
+        program: &[0x60, 0x01, 0x61, 0x02, 0x80, 0x15],
+        ticks: 0,
+        verify: |cpu| expect_registers(cpu, &[(0x0, 0xFF), (0xF, 0)]),
+    },
+    Case {
+        name: "logic",
+        // V0 = 0C, V1 = 0A, then V2 = V0 | V1, V3 = V0 & V1, V4 = V0 ^ V1
+        program: &[
+            0x60, 0x0C, 0x61, 0x0A, 0x82, 0x00, 0x82, 0x11, 0x83, 0x00, 0x83, 0x12, 0x84, 0x00,
+            0x84, 0x13,
+        ],
+        ticks: 0,
+        verify: |cpu| expect_registers(cpu, &[(0x2, 0x0E), (0x3, 0x08), (0x4, 0x06)]),
+    },
+    Case {
+        name: "draw and collide",
+        // Draw the font's 0 twice at 0,0: the second draw erases the first
+        program: &[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0xD0, 0x05],
+        ticks: 0,
+        verify: |cpu| {
+            expect_registers(cpu, &[(0xF, 1)])?;
+            if cpu.frame_buffer.rows().flatten().any(|&pixel| pixel != 0) {
+                return Err("display not blank after XOR redraw".to_string());
+            }
+            Ok(())
+        },
+    },
+    Case {
+        name: "draw without collision",
+        // The font's 0 at 0,0 and again at 8,0, where they don't overlap
+        program: &[0x60, 0x00, 0x61, 0x08, 0xF0, 0x29, 0xD0, 0x05, 0xD1, 0x05],
+        ticks: 0,
+        verify: |cpu| expect_registers(cpu, &[(0xF, 0)]),
+    },
+    Case {
+        name: "timers",
+        // V0 = 0A, DT = V0, ST = V0, then four ticks of the mock clock
+        program: &[0x60, 0x0A, 0xF0, 0x15, 0xF0, 0x18],
+        ticks: 4,
+        verify: |cpu| {
+            if cpu.delay_timer != 6 || cpu.sound_timer != 6 {
+                return Err(format!(
+                    "timers at {}/{}, expected 6/6",
+                    cpu.delay_timer, cpu.sound_timer
+                ));
+            }
+            Ok(())
+        },
+    },
+    Case {
+        name: "BCD and memory",
+        // V0 = FE, I = 300, BCD of V0, then load V0..V2 back from 300
+        program: &[0x60, 0xFE, 0xA3, 0x00, 0xF0, 0x33, 0xA3, 0x00, 0xF2, 0x65],
+        ticks: 0,
+        verify: |cpu| {
+            if cpu.mem[0x300..0x303] != [2, 5, 4] {
+                return Err(format!("BCD wrote {:?}", &cpu.mem[0x300..0x303]));
+            }
+            expect_registers(cpu, &[(0x0, 2), (0x1, 5), (0x2, 4)])
+        },
+    },
+];
+
+fn expect_registers(cpu: &Chip8Interpreter, expected: &[(usize, u8)]) -> Result<(), String> {
+    for &(register, value) in expected {
+        let actual = cpu.registers_v[register];
+        if actual != value {
+            return Err(format!(
+                "V{:X} = {:02X}, expected {:02X}",
+                register, actual, value
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Result of one case in a `SelfCheck`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub outcome: Result<(), String>,
+}
+
+/// Report from `Chip8Interpreter::self_check`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfCheck {
+    pub results: Vec<CheckResult>,
+}
+
+impl SelfCheck {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.outcome.is_ok())
+    }
+}
+
+impl fmt::Display for SelfCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            match &result.outcome {
+                Ok(()) => writeln!(f, "ok    {}", result.name)?,
+                Err(reason) => writeln!(f, "FAIL  {}: {}", result.name, reason)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn run() -> SelfCheck {
+    let results = CASES
+        .iter()
+        .map(|case| CheckResult {
+            name: case.name,
+            outcome: run_case(case),
+        })
+        .collect();
+    SelfCheck { results }
+}
+
+fn run_case(case: &Case) -> Result<(), String> {
+    let mut cpu = Chip8Interpreter::new();
+    cpu.set_echo_display(false);
+    cpu.load_rom_bytes(case.program)
+        .map_err(|err| err.to_string())?;
+    for _ in 0..case.program.len() / 2 {
+        cpu.step().map_err(|err| err.to_string())?;
+    }
+    for _ in 0..case.ticks {
+        cpu.tick_timers();
+    }
+    (case.verify)(&cpu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_check() {
+        let check = run();
+        assert_eq!(check.results.len(), CASES.len());
+        assert!(check.passed(), "{}", check);
+    }
+
+    #[test]
+    fn test_report() {
+        let check = SelfCheck {
+            results: vec![
+                CheckResult {
+                    name: "timers",
+                    outcome: Ok(()),
+                },
+                CheckResult {
+                    name: "logic",
+                    outcome: Err("V2 = 00, expected 0E".to_string()),
+                },
+            ],
+        };
+        assert!(!check.passed());
+        assert_eq!(
+            check.to_string(),
+            "ok    timers\nFAIL  logic: V2 = 00, expected 0E\n"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use super::banks::Banks;
+use super::{pattern_rate, AudioPattern, Chip8Interpreter, Fault, Keypad, MachineState};
+use super::{RandomSource, RPL_FLAG_COUNT};
+use crate::prelude::*;
+use alloc::collections::VecDeque;
+use core::time::Duration;
+use rand::rngs::StdRng;
+
+/// A point of execution to branch from, taken with
+/// `Chip8Interpreter::snapshot`. Unlike a `MachineState` it also holds what
+/// the next instructions depend on beyond the registers and memory: the
+/// cycle count, keypad, pending key wait, timer phase, memory banks, RPL
+/// flags and the RNG, so running on from `restore_snapshot` gives the same
+/// result every time. It is kept in memory only; cloning one costs about
+/// as much as a copy of the 4K memory per bank.
+#[derive(Clone)]
+pub struct Snapshot {
+    state: MachineState,
+    cycles: u64,
+    timer_phase: f64,
+    clocked: Duration,
+    keypad: Keypad,
+    key_wait: Option<u8>,
+    awaiting_key: bool,
+    input_polled: bool,
+    halted: bool,
+    banks: Option<Banks>,
+    audio_pattern: Option<AudioPattern>,
+    pitch: u8,
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    faults: Vec<Fault>,
+    forced_rng: VecDeque<u8>,
+    /// None when the RNG came from `set_rng`, which cannot be copied
+    rng: Option<StdRng>,
+}
+
+impl Snapshot {
+    pub(crate) fn of(cpu: &Chip8Interpreter) -> Snapshot {
+        Snapshot {
+            state: cpu.state(),
+            cycles: cpu.cycles,
+            timer_phase: cpu.timer_phase,
+            clocked: cpu.clocked,
+            keypad: cpu.keypad,
+            key_wait: cpu.key_wait,
+            awaiting_key: cpu.awaiting_key,
+            input_polled: cpu.input_polled,
+            halted: cpu.halted,
+            banks: cpu.banks.clone(),
+            audio_pattern: cpu.audio_pattern,
+            pitch: cpu.pitch,
+            rpl_flags: cpu.rpl_flags,
+            faults: cpu.faults.clone(),
+            forced_rng: cpu.forced_rng.clone(),
+            rng: match &cpu.rng {
+                RandomSource::Builtin(rng) => Some(StdRng::clone(rng)),
+                RandomSource::Custom(_) => None,
+            },
+        }
+    }
+
+    pub(crate) fn apply(&self, cpu: &mut Chip8Interpreter) {
+        cpu.restore(&self.state);
+        cpu.cycles = self.cycles;
+        cpu.timer_phase = self.timer_phase;
+        cpu.clocked = self.clocked;
+        cpu.keypad = self.keypad;
+        cpu.key_wait = self.key_wait;
+        cpu.awaiting_key = self.awaiting_key;
+        cpu.input_polled = self.input_polled;
+        cpu.halted = self.halted;
+        cpu.banks.clone_from(&self.banks);
+        cpu.rpl_flags = self.rpl_flags;
+        cpu.faults.clone_from(&self.faults);
+        cpu.forced_rng.clone_from(&self.forced_rng);
+        if let Some(rng) = &self.rng {
+            cpu.rng = RandomSource::Builtin(Box::new(rng.clone()));
+        }
+        cpu.pitch = self.pitch;
+        cpu.audio_pattern = self.audio_pattern;
+        if let Some(pattern) = self.audio_pattern {
+            cpu.audio.set_pattern(pattern, pattern_rate(self.pitch));
+        }
+    }
+
+    /// The registers, memory and display at the snapshot
+    pub fn state(&self) -> &MachineState {
+        &self.state
+    }
+
+    /// Instructions executed before the snapshot was taken
+    pub fn cycle(&self) -> u64 {
+        self.cycles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branching() {
+        // V0 = random; skip if key V1 (0) is pressed; V2 += 1; jump back
+        let rom = [0xC0, 0xFF, 0xE1, 0x9E, 0x72, 0x01, 0x12, 0x00];
+        let mut cpu = Chip8Interpreter::new();
+        cpu.seed_rng(3);
+        cpu.load_rom_bytes(&rom).unwrap();
+        cpu.step_n(8).unwrap();
+        let snapshot = cpu.snapshot();
+        cpu.step_n(40).unwrap();
+        let first = cpu.state();
+        // A different branch, with the key held down
+        cpu.restore_snapshot(&snapshot);
+        cpu.set_key(0x0, true);
+        cpu.step_n(40).unwrap();
+        assert_ne!(cpu.v(2), first.registers_v[2]);
+        // Back again without the key gives the first branch exactly,
+        // random numbers included
+        cpu.restore_snapshot(&snapshot);
+        assert_eq!(cpu.cycles, 8);
+        assert!(!cpu.keypad().is_pressed(0x0));
+        cpu.step_n(40).unwrap();
+        assert_eq!(cpu.state(), first);
+        assert_eq!(snapshot.cycle(), 8);
+    }
+}
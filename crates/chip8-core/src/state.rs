@@ -0,0 +1,374 @@
+use super::framebuffer::PACKED_SIZE;
+use super::instruction;
+use super::{Chip8Interpreter, FrameBuffer, Mem, Quirks, MEMORY_SIZE};
+use crate::prelude::*;
+use core::fmt;
+
+/// Instructions shown before and after PC when printing the state
+const CODE_CONTEXT: u16 = 2;
+/// Start of every .c8state file
+const MAGIC: &[u8; 4] = b"C8SV";
+/// Version of the .c8state layout written by `to_bytes`
+pub const STATE_VERSION: u8 = 3;
+/// Flags in the .c8state header
+const HAS_ROM_HASH: u8 = 1;
+const HAS_QUIRKS: u8 = 2;
+
+/// A copy of everything that makes up the machine, as saved in .c8state files
+/// or, for paths ending in .json, the interchange format
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MachineState {
+    pub registers_v: [u8; 16],
+    pub register_i: u16,
+    pub delay_timer: u16,
+    pub sound_timer: u16,
+    pub register_pc: u16,
+    pub stack: Vec<u16>,
+    #[cfg_attr(feature = "serde", serde(with = "memory"))]
+    pub mem: Mem,
+    pub frame_buffer: FrameBuffer,
+    /// Quirks the program ran with, restored along with the rest; None in
+    /// states saved before they were recorded
+    pub quirks: Option<Quirks>,
+    /// Hash of the ROM that was running, to refuse restoring the state on
+    /// another one; None in states saved before it was recorded
+    pub rom_hash: Option<u64>,
+}
+
+impl MachineState {
+    pub fn of(cpu: &Chip8Interpreter) -> MachineState {
+        MachineState {
+            registers_v: cpu.registers_v,
+            register_i: cpu.register_i,
+            delay_timer: cpu.delay_timer,
+            sound_timer: cpu.sound_timer,
+            register_pc: cpu.register_pc,
+            stack: cpu.stack.clone(),
+            mem: cpu.mem,
+            frame_buffer: cpu.frame_buffer,
+            quirks: Some(cpu.quirks),
+            rom_hash: Some(cpu.rom_hash),
+        }
+    }
+
+    /// Layout: the `MAGIC` bytes, the format version, a byte of flags for
+    /// which of the ROM hash and quirks are known, the ROM hash (big endian
+    /// u64) and `Quirks::bits`, then the machine: V0-VF, I, DT, ST, PC (big
+    /// endian u16s), stack length and entries (u16s, as the stack has no
+    /// limit by default), memory, and the display packed 8 pixels per byte
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(STATE_VERSION);
+        let flags =
+            self.rom_hash.map_or(0, |_| HAS_ROM_HASH) | self.quirks.map_or(0, |_| HAS_QUIRKS);
+        bytes.push(flags);
+        bytes.extend_from_slice(&self.rom_hash.unwrap_or(0).to_be_bytes());
+        bytes.push(self.quirks.map_or(0, |quirks| quirks.bits()));
+        bytes.extend_from_slice(&self.registers_v);
+        for value in [
+            self.register_i,
+            self.delay_timer,
+            self.sound_timer,
+            self.register_pc,
+        ] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes.extend_from_slice(&(self.stack.len() as u16).to_be_bytes());
+        for addr in self.stack.iter() {
+            bytes.extend_from_slice(&addr.to_be_bytes());
+        }
+        bytes.extend_from_slice(&self.mem);
+        bytes.extend_from_slice(&self.frame_buffer.packed());
+        bytes
+    }
+
+    /// Read a state saved in format version `STATE_VERSION`
+    pub fn from_bytes(bytes: &[u8]) -> Result<MachineState, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(String::from("not a machine state"));
+        }
+        let mut reader = Reader {
+            bytes,
+            pos: MAGIC.len(),
+        };
+        let version = reader.take(1)?[0];
+        if version != STATE_VERSION {
+            return Err(format!(
+                "machine state is format version {}, this build reads version {}",
+                version, STATE_VERSION
+            ));
+        }
+        let flags = reader.take(1)?[0];
+        let rom_hash = reader.u64()?;
+        let quirks = Quirks::from_bits(reader.take(1)?[0]);
+        let mut state = reader.machine()?;
+        reader.finish()?;
+        state.rom_hash = Some(rom_hash).filter(|_| flags & HAS_ROM_HASH != 0);
+        state.quirks = Some(quirks).filter(|_| flags & HAS_QUIRKS != 0);
+        Ok(state)
+    }
+
+    /// Whether the state can be restored on a machine running the ROM with
+    /// `rom_hash`, which it can unless it was saved from a different one
+    pub fn check_rom(&self, rom_hash: u64) -> Result<(), String> {
+        match self.rom_hash {
+            Some(saved) if saved != rom_hash => Err(format!(
+                "machine state was saved from a different ROM (hash {:016x}, running {:016x})",
+                saved, rom_hash
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if is_json(path) {
+            std::fs::write(path, self.to_json())
+        } else {
+            std::fs::write(path, self.to_bytes())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load(path: &str) -> Result<MachineState, String> {
+        let bytes = std::fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+        let state = if is_json(path) {
+            MachineState::from_json(&String::from_utf8_lossy(&bytes))
+        } else {
+            MachineState::from_bytes(&bytes)
+        };
+        state.map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+/// Memory as a plain byte sequence, since serde only handles arrays of up
+/// to 32 elements
+#[cfg(feature = "serde")]
+mod memory {
+    use super::Mem;
+    use crate::prelude::*;
+    use core::convert::TryFrom;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(mem: &Mem, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(mem.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Mem, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        Mem::try_from(bytes.as_slice())
+            .map_err(|_| serde::de::Error::invalid_length(bytes.len(), &"4096 bytes of memory"))
+    }
+}
+
+/// Register table, timers, stack and the code around PC, e.g.
+///
+/// ```text
+/// PC 0x202  I 0x000  DT   0  ST   0
+/// V0 12  V1 00  V2 00  V3 00  V4 00  V5 00  V6 00  V7 00
+/// V8 00  V9 00  VA 00  VB 00  VC 00  VD 00  VE 00  VF 00
+/// stack: 0x204
+///   0x200  6012  LD V0, 0x12
+/// > 0x202  FFFF  ????
+/// ```
+impl fmt::Display for MachineState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "PC {:#05x}  I {:#05x}  DT {:>3}  ST {:>3}",
+            self.register_pc, self.register_i, self.delay_timer, self.sound_timer
+        )?;
+        for (half, values) in self.registers_v.chunks(8).enumerate() {
+            let cells: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(x, value)| format!("V{:X} {:02x}", half * 8 + x, value))
+                .collect();
+            writeln!(f, "{}", cells.join("  "))?;
+        }
+        if self.stack.is_empty() {
+            writeln!(f, "stack: empty")?;
+        } else {
+            let addrs: Vec<String> = self.stack.iter().map(|a| format!("{:#05x}", a)).collect();
+            writeln!(f, "stack: {}", addrs.join(" "))?;
+        }
+        let first = self.register_pc.saturating_sub(CODE_CONTEXT * 2);
+        let last = self
+            .register_pc
+            .saturating_add(CODE_CONTEXT * 2)
+            .min(MEMORY_SIZE - 2);
+        for addr in (first..=last).step_by(2) {
+            let opcode = u16::from_be_bytes([self.mem[addr as usize], self.mem[addr as usize + 1]]);
+            let marker = if addr == self.register_pc { '>' } else { ' ' };
+            let mnemonic = instruction::mnemonic(opcode);
+            writeln!(f, "{} {:#05x}  {:04X}  {}", marker, addr, opcode, mnemonic)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn is_json(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".json")
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos + len;
+        if end > self.bytes.len() {
+            return Err(String::from("machine state is truncated"));
+        }
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    /// The machine part of the layout
+    fn machine(&mut self) -> Result<MachineState, String> {
+        let mut registers_v = [0; 16];
+        registers_v.copy_from_slice(self.take(16)?);
+        let register_i = self.u16()?;
+        let delay_timer = self.u16()?;
+        let sound_timer = self.u16()?;
+        let register_pc = self.u16()?;
+        let stack_len = self.u16()?;
+        let mut stack = vec![];
+        for _ in 0..stack_len {
+            stack.push(self.u16()?);
+        }
+        let mut mem = [0; MEMORY_SIZE as usize];
+        mem.copy_from_slice(self.take(MEMORY_SIZE as usize)?);
+        let frame_buffer = FrameBuffer::from_packed(self.take(PACKED_SIZE)?).unwrap();
+        Ok(MachineState {
+            registers_v,
+            register_i,
+            delay_timer,
+            sound_timer,
+            register_pc,
+            stack,
+            mem,
+            frame_buffer,
+            quirks: None,
+            rom_hash: None,
+        })
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        if self.pos != self.bytes.len() {
+            return Err(String::from("trailing data after machine state"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.registers_v[3] = 0x42;
+        cpu.register_i = 0x30A;
+        cpu.stack = vec![0x202, 0x2F0];
+        cpu.mem[0xFFF] = 0x99;
+        cpu.frame_buffer[5][9] = 1;
+        let state = MachineState::of(&cpu);
+        let bytes = state.to_bytes();
+        assert_eq!(MachineState::from_bytes(&bytes), Ok(state));
+        assert!(MachineState::from_bytes(&bytes[..100]).is_err());
+        // The stack has no limit by default, so it can be deeper than 255
+        cpu.stack = vec![0x202; 300];
+        let state = MachineState::of(&cpu);
+        assert_eq!(MachineState::from_bytes(&state.to_bytes()), Ok(state));
+    }
+
+    #[test]
+    fn test_versions() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        cpu.set_quirks(Quirks::CHIP_48);
+        let state = cpu.state();
+        let bytes = state.to_bytes();
+        assert_eq!(&bytes[..5], b"C8SV\x03");
+        assert_eq!(MachineState::from_bytes(&bytes), Ok(state));
+        // Older layouts aren't read
+        let mut older = bytes.clone();
+        older[4] = 2;
+        assert!(MachineState::from_bytes(&older).is_err());
+        assert!(MachineState::from_bytes(&bytes[15..]).is_err());
+        let mut newer = bytes.clone();
+        newer[4] = STATE_VERSION + 1;
+        let err = MachineState::from_bytes(&newer).unwrap_err();
+        assert!(err.contains("format version 4"), "{}", err);
+    }
+
+    #[test]
+    fn test_check_rom() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        let state = cpu.state();
+        assert!(cpu.load_state(&state).is_ok());
+        cpu.load_rom_bytes(&[0x12, 0x02]).unwrap();
+        assert!(cpu.load_state(&state).is_err());
+        let unknown = MachineState {
+            rom_hash: None,
+            ..state
+        };
+        assert!(cpu.load_state(&unknown).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_round_trip() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_quirks(Quirks::COSMAC_VIP);
+        cpu.registers_v[0xF] = 1;
+        cpu.stack = vec![0x206];
+        cpu.mem[0x400] = 0x5A;
+        cpu.frame_buffer[10][20] = 1;
+        let state = MachineState::of(&cpu);
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<MachineState>(&json).unwrap(), state);
+        let truncated = json.replacen("[0,", "[", 1);
+        assert!(serde_json::from_str::<MachineState>(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.load_rom_bytes(&[0x60, 0x12, 0xFF, 0xFF]).unwrap();
+        cpu.step().unwrap();
+        cpu.stack = vec![0x204];
+        let text = cpu.state().to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "PC 0x202  I 0x000  DT   0  ST   0");
+        assert!(lines[1].starts_with("V0 12  V1 00"));
+        assert!(lines[2].ends_with("VF 00"));
+        assert_eq!(lines[3], "stack: 0x204");
+        assert_eq!(lines[5], "  0x200  6012  LD V0, 0x12");
+        assert_eq!(lines[6], "> 0x202  FFFF  ????");
+        assert_eq!(lines.len(), 9);
+        // A PC past the end of memory, as a state file can have
+        cpu.register_pc = 0xFFFF;
+        let text = cpu.state().to_string();
+        assert!(text.starts_with("PC 0xffff"));
+    }
+}
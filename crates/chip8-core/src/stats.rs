@@ -0,0 +1,106 @@
+use super::instruction::Instruction;
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+/// Approximate time in microseconds the original COSMAC VIP interpreter
+/// spent on each instruction. Draw and BCD times vary with their operands
+/// on real hardware, these are typical values.
+fn vip_microseconds(pattern: &str) -> u64 {
+    match pattern {
+        "0000" => 0,
+        "00E0" => 109,
+        "00EE" | "1NNN" | "2NNN" | "BNNN" => 105,
+        "3XNN" | "4XNN" | "ANNN" => 55,
+        "5XY0" | "9XY0" | "EX9E" | "EXA1" => 73,
+        "6XNN" => 27,
+        "7XNN" | "FX07" | "FX15" | "FX18" => 45,
+        "CXNN" => 164,
+        "DXYN" => 22734,
+        "FX1E" => 86,
+        "FX29" => 91,
+        "FX33" => 927,
+        "FX55" | "FX65" => 605,
+        // The ALU instructions
+        _ => 200,
+    }
+}
+
+/// How often each instruction ran, and what that would have cost on the
+/// original hardware
+#[derive(Debug, Default)]
+pub struct Stats {
+    counts: BTreeMap<&'static str, u64>,
+}
+
+impl Stats {
+    pub fn record(&mut self, inst: &Instruction) {
+        *self.counts.entry(inst.pattern()).or_insert(0) += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Estimated COSMAC VIP time for everything that ran
+    pub fn vip_microseconds(&self) -> u64 {
+        self.counts
+            .iter()
+            .map(|(pattern, count)| count * vip_microseconds(pattern))
+            .sum()
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.total().max(1);
+        let total_time = self.vip_microseconds().max(1);
+        let mut rows: Vec<_> = self.counts.iter().collect();
+        rows.sort_by(|a, b| b.1.cmp(a.1));
+        writeln!(
+            f,
+            "{:<6}{:>10}{:>8}{:>14}{:>8}",
+            "opcode", "count", "count%", "VIP time (us)", "time%"
+        )?;
+        for (pattern, &count) in rows {
+            let time = count * vip_microseconds(pattern);
+            writeln!(
+                f,
+                "{:<6}{:>10}{:>7.1}%{:>14}{:>7.1}%",
+                pattern,
+                count,
+                count as f64 * 100. / total as f64,
+                time,
+                time as f64 * 100. / total_time as f64
+            )?;
+        }
+        writeln!(
+            f,
+            "{} instructions, ~{:.3}s on a COSMAC VIP",
+            self.total(),
+            self.vip_microseconds() as f64 / 1_000_000.
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let mut stats = Stats::default();
+        stats.record(&Instruction::from_raw_opcode(0x6012).unwrap());
+        stats.record(&Instruction::from_raw_opcode(0x6134).unwrap());
+        stats.record(&Instruction::from_raw_opcode(0xD015).unwrap());
+        assert_eq!(stats.total(), 3);
+        assert_eq!(stats.vip_microseconds(), 2 * 27 + 22734);
+        let report = stats.to_string();
+        let rows: Vec<Vec<&str>> = report
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(rows[1], ["6XNN", "2", "66.7%", "54", "0.2%"]);
+        assert_eq!(rows[2], ["DXYN", "1", "33.3%", "22734", "99.8%"]);
+    }
+}
@@ -0,0 +1,132 @@
+use super::debugger::parse_address;
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+use core::fmt;
+use core::str::FromStr;
+
+/// Names for addresses, such as the labels an assembler emits, so the
+/// debugger, disassembly and traces can say `main_loop` instead of 0x204
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Symbols {
+    names: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn new() -> Symbols {
+        Symbols::default()
+    }
+
+    /// Name `addr`, replacing any name it had
+    pub fn insert(&mut self, addr: u16, name: &str) {
+        self.names.insert(addr, name.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Addresses and their names, lowest address first
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> + '_ {
+        self.names.iter().map(|(&addr, name)| (addr, name.as_str()))
+    }
+
+    /// The name given to exactly `addr`
+    pub fn name(&self, addr: u16) -> Option<&str> {
+        self.names.get(&addr).map(String::as_str)
+    }
+
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.iter()
+            .find(|&(_, symbol)| symbol == name)
+            .map(|(addr, _)| addr)
+    }
+
+    /// A name, or an address in hex as `parse_address` takes it
+    pub fn resolve(&self, text: &str) -> Option<u16> {
+        self.address(text).or_else(|| parse_address(text))
+    }
+
+    /// "main_loop" for a named address, "main_loop+4" for one past the
+    /// nearest name below it, or None before the first name
+    pub fn label(&self, addr: u16) -> Option<String> {
+        let (&start, name) = self.names.range(..=addr).next_back()?;
+        Some(match addr - start {
+            0 => name.clone(),
+            offset => format!("{}+{}", name, offset),
+        })
+    }
+
+    /// Read a symbol file, see `FromStr`
+    #[cfg(feature = "std")]
+    pub fn load(path: &str) -> Result<Symbols, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        text.parse().map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+/// Parses a line per symbol with its address in hex and its name, e.g.
+/// "0x204 main_loop", skipping blank lines and # comments
+impl FromStr for Symbols {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Symbols, String> {
+        let mut symbols = Symbols::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || format!("line {}: expected an address and a name", index + 1);
+            let (addr, name) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let addr = parse_address(addr).ok_or_else(invalid)?;
+            let name = name.trim();
+            if !is_name(name) {
+                return Err(format!("line {}: invalid name '{}'", index + 1, name));
+            }
+            symbols.insert(addr, name);
+        }
+        Ok(symbols)
+    }
+}
+
+/// The symbol file format `FromStr` reads
+impl fmt::Display for Symbols {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (addr, name) in self.iter() {
+            writeln!(f, "{:#05x} {}", addr, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Letters, digits and underscores, not starting with a digit so names
+/// can't be mistaken for addresses
+fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_label() {
+        let symbols: Symbols = "# from the assembler\n0x200 start\n\n204 main_loop  # spin\n"
+            .parse()
+            .unwrap();
+        assert_eq!(symbols.name(0x204), Some("main_loop"));
+        assert_eq!(symbols.address("start"), Some(0x200));
+        assert_eq!(symbols.resolve("main_loop"), Some(0x204));
+        assert_eq!(symbols.resolve("2a4"), Some(0x2A4));
+        assert_eq!(symbols.resolve("nowhere"), None);
+        assert_eq!(symbols.label(0x1FE), None);
+        assert_eq!(symbols.label(0x200).as_deref(), Some("start"));
+        assert_eq!(symbols.label(0x208).as_deref(), Some("main_loop+4"));
+        assert_eq!(symbols.to_string(), "0x200 start\n0x204 main_loop\n");
+        assert_eq!(symbols.to_string().parse::<Symbols>().unwrap(), symbols);
+        assert!("0x200".parse::<Symbols>().is_err());
+        assert!("0x200 2fast".parse::<Symbols>().is_err());
+        assert!("0x1000 high".parse::<Symbols>().is_err());
+    }
+}
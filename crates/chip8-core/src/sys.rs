@@ -0,0 +1,34 @@
+/// What 0NNN does. On the COSMAC VIP it called a machine code routine at
+/// NNN, which can't be emulated without emulating the CPU itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SysPolicy {
+    /// Skip the call, as most modern interpreters do
+    #[default]
+    Ignore,
+    /// End the program as if it reached 0000
+    Halt,
+    /// Stop with a fault
+    Error,
+}
+
+impl SysPolicy {
+    pub fn from_name(name: &str) -> Option<SysPolicy> {
+        match name {
+            "ignore" => Some(SysPolicy::Ignore),
+            "halt" => Some(SysPolicy::Halt),
+            "error" => Some(SysPolicy::Error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(SysPolicy::from_name("halt"), Some(SysPolicy::Halt));
+        assert_eq!(SysPolicy::from_name("call"), None);
+    }
+}
@@ -0,0 +1,163 @@
+use core::fmt::Write;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+/// Fixed-width histogram of durations. The last bucket collects everything
+/// that does not fit in the others.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    bucket_us: u64,
+    counts: Vec<u64>,
+    sum_us: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    pub fn new(bucket_us: u64, buckets: usize) -> Histogram {
+        Histogram {
+            bucket_us,
+            counts: vec![0; buckets + 1],
+            sum_us: 0,
+            max_us: 0,
+        }
+    }
+
+    pub fn record(&mut self, duration: Duration) {
+        let us = duration.as_micros() as u64;
+        let last = self.counts.len() - 1;
+        self.counts[((us / self.bucket_us) as usize).min(last)] += 1;
+        self.sum_us += us;
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn mean_us(&self) -> u64 {
+        self.sum_us / self.count().max(1)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "bucket_us": self.bucket_us,
+            "count": self.count(),
+            "mean_us": self.mean_us(),
+            "max_us": self.max_us,
+            "buckets": self.counts,
+        })
+    }
+}
+
+/// Times between presented frames and how long each batch of instructions
+/// took to execute, for diagnosing stutter
+#[derive(Clone, Debug)]
+pub struct FrameTiming {
+    path: String,
+    pub present_intervals: Histogram,
+    pub batch_durations: Histogram,
+    last_present: Option<Instant>,
+}
+
+impl FrameTiming {
+    pub fn new(path: &str) -> FrameTiming {
+        FrameTiming {
+            path: path.to_string(),
+            // 0.5ms buckets up to 50ms
+            present_intervals: Histogram::new(500, 100),
+            // 10us buckets up to 1ms
+            batch_durations: Histogram::new(10, 100),
+            last_present: None,
+        }
+    }
+
+    pub fn record_batch(&mut self, duration: Duration) {
+        self.batch_durations.record(duration);
+    }
+
+    pub fn record_present(&mut self, now: Instant) {
+        if let Some(last) = self.last_present {
+            self.present_intervals.record(now - last);
+        }
+        self.last_present = Some(now);
+    }
+
+    /// One row per bucket: series, bucket start and end in microseconds
+    /// (end is empty for the overflow bucket), count
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("series,start_us,end_us,count\n");
+        for (series, histogram) in self.series() {
+            let last = histogram.counts.len() - 1;
+            for (idx, count) in histogram.counts.iter().enumerate() {
+                let start = idx as u64 * histogram.bucket_us;
+                let end = if idx == last {
+                    String::new()
+                } else {
+                    (start + histogram.bucket_us).to_string()
+                };
+                writeln!(out, "{},{},{},{}", series, start, end, count).unwrap();
+            }
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut value = json!({});
+        for (series, histogram) in self.series() {
+            value[series] = histogram.to_json();
+        }
+        serde_json::to_string_pretty(&value).unwrap()
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Write as JSON for paths ending in .json, CSV otherwise
+    pub fn save(&self) -> std::io::Result<()> {
+        if self.path.to_ascii_lowercase().ends_with(".json") {
+            std::fs::write(&self.path, self.to_json())
+        } else {
+            std::fs::write(&self.path, self.to_csv())
+        }
+    }
+
+    fn series(&self) -> [(&'static str, &Histogram); 2] {
+        [
+            ("present_interval", &self.present_intervals),
+            ("batch_duration", &self.batch_durations),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::new(10, 3);
+        for us in [0, 9, 10, 25, 1000] {
+            histogram.record(Duration::from_micros(us));
+        }
+        assert_eq!(histogram.counts, vec![2, 1, 1, 1]);
+        assert_eq!(histogram.mean_us(), 208);
+        assert_eq!(histogram.max_us, 1000);
+    }
+
+    #[test]
+    fn test_export() {
+        let mut timing = FrameTiming::new("timing.csv");
+        let start = Instant::now();
+        timing.record_present(start);
+        timing.record_present(start + Duration::from_micros(16_700));
+        timing.record_batch(Duration::from_micros(35));
+        let csv = timing.to_csv();
+        assert!(csv.contains("present_interval,16500,17000,1\n"));
+        assert!(csv.contains("batch_duration,30,40,1\n"));
+        assert!(csv.contains("batch_duration,1000,,0\n"));
+        let json: serde_json::Value = serde_json::from_str(&timing.to_json()).unwrap();
+        assert_eq!(json["present_interval"]["count"], 1);
+        assert_eq!(json["batch_duration"]["max_us"], 35);
+    }
+}
@@ -0,0 +1,66 @@
+use super::instruction::Instruction;
+use super::symbols::Symbols;
+use crate::prelude::*;
+use tracing::trace;
+
+/// The registers an instruction can change, taken before and after it
+pub(crate) type Registers = ([u8; 16], u16);
+
+/// Log an executed instruction at TRACE level, with its operands, the
+/// registers it changed and the label of its address, if there is one
+pub(crate) fn instruction(
+    pc: u16,
+    inst: &Instruction,
+    before: &Registers,
+    after: &Registers,
+    symbols: &Symbols,
+) {
+    let op = inst.opcode();
+    trace!(
+        pc = format_args!("{:#05x}", pc),
+        label = symbols.label(pc).unwrap_or_default().as_str(),
+        opcode = format_args!("{:04X}", op.raw),
+        instruction = format_args!("{}", inst),
+        x = op.x,
+        y = op.y,
+        n = op.n,
+        kk = op.kk,
+        nnn = op.nnn,
+        changed = changes(before, after).as_str(),
+        "executed"
+    );
+}
+
+/// "V3 00->05 I 0x300->0x302", or "none"
+pub(crate) fn changes(before: &Registers, after: &Registers) -> String {
+    let mut changes: Vec<String> = (0..16)
+        .filter(|&x| before.0[x] != after.0[x])
+        .map(|x| format!("V{:X} {:02x}->{:02x}", x, before.0[x], after.0[x]))
+        .collect();
+    if before.1 != after.1 {
+        changes.push(format!("I {:#05x}->{:#05x}", before.1, after.1));
+    }
+    if changes.is_empty() {
+        return String::from("none");
+    }
+    changes.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes() {
+        let before = ([0; 16], 0x300);
+        let mut after = before;
+        assert_eq!(changes(&before, &after), "none");
+        after.0[3] = 5;
+        after.0[0xF] = 1;
+        after.1 = 0x302;
+        assert_eq!(
+            changes(&before, &after),
+            "V3 00->05 VF 00->01 I 0x300->0x302"
+        );
+    }
+}
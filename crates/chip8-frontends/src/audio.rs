@@ -0,0 +1,282 @@
+//! The buzzer: a tone that plays while the sound timer runs, on the
+//! default output device. Playback needs the `audio` feature.
+
+use chip8_core::{Audio, AudioPattern};
+
+/// Peak amplitude, out of 1
+const VOLUME: f32 = 0.2;
+
+/// Shape of the buzzer's tone
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Waveform {
+    /// Harsh, like the original hardware
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+    /// White noise, changing value every half period of the pitch
+    Noise,
+}
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        match name {
+            "square" => Some(Waveform::Square),
+            "sine" => Some(Waveform::Sine),
+            "triangle" => Some(Waveform::Triangle),
+            "noise" => Some(Waveform::Noise),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Waveform::Square => "square",
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+            Waveform::Noise => "noise",
+        }
+    }
+}
+
+/// What the buzzer sounds like
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tone {
+    pub waveform: Waveform,
+    /// Pitch in Hz
+    pub pitch: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Tone {
+            waveform: Waveform::Square,
+            pitch: 440.,
+        }
+    }
+}
+
+/// Generates a tone one sample at a time
+pub(crate) struct Oscillator {
+    waveform: Waveform,
+    /// Fraction of a period per sample
+    step: f32,
+    phase: f32,
+    /// Xorshift state for noise
+    noise: u32,
+    noise_value: f32,
+    sample_rate: f32,
+    /// XO-CHIP pattern played instead of the tone, once a ROM loads one
+    pattern: Option<AudioPattern>,
+    /// Pattern bits per sample, and the bit being played
+    pattern_step: f32,
+    pattern_position: f32,
+}
+
+impl Oscillator {
+    pub(crate) fn new(tone: Tone, sample_rate: f32) -> Oscillator {
+        Oscillator {
+            waveform: tone.waveform,
+            step: tone.pitch / sample_rate,
+            phase: 0.,
+            noise: 0x2545_F491,
+            noise_value: 0.,
+            sample_rate,
+            pattern: None,
+            pattern_step: 0.,
+            pattern_position: 0.,
+        }
+    }
+
+    pub(crate) fn set_pattern(&mut self, pattern: AudioPattern, rate: f32) {
+        self.pattern = Some(pattern);
+        self.pattern_step = rate / self.sample_rate;
+    }
+
+    /// The next sample, between -VOLUME and VOLUME
+    pub(crate) fn next_sample(&mut self) -> f32 {
+        if let Some(pattern) = &self.pattern {
+            let bit = self.pattern_position as usize;
+            let on = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+            self.pattern_position = (self.pattern_position + self.pattern_step) % 128.;
+            return if on { VOLUME } else { -VOLUME };
+        }
+        let value = match self.waveform {
+            Waveform::Square if self.phase < 0.5 => 1.,
+            Waveform::Square => -1.,
+            Waveform::Sine => (self.phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => 1. - 4. * (self.phase - 0.5).abs(),
+            Waveform::Noise => self.noise_value,
+        };
+        let phase = self.phase + self.step;
+        if self.waveform == Waveform::Noise && (phase * 2.).floor() != (self.phase * 2.).floor() {
+            self.noise ^= self.noise << 13;
+            self.noise ^= self.noise >> 17;
+            self.noise ^= self.noise << 5;
+            self.noise_value = self.noise as f32 / u32::MAX as f32 * 2. - 1.;
+        }
+        self.phase = phase % 1.;
+        value * VOLUME
+    }
+}
+
+/// What the emulator thread tells the audio stream
+#[cfg(feature = "audio")]
+#[derive(Default)]
+struct Controls {
+    active: std::sync::atomic::AtomicBool,
+    /// XO-CHIP pattern and rate waiting to be picked up by the stream
+    pattern: std::sync::Mutex<Option<(AudioPattern, f32)>>,
+}
+
+/// A tone on the default output device, silent until switched on
+pub struct Buzzer {
+    #[cfg(feature = "audio")]
+    controls: std::sync::Arc<Controls>,
+    /// Playback stops when the stream is dropped
+    #[cfg(feature = "audio")]
+    _stream: cpal::Stream,
+}
+
+impl Buzzer {
+    /// Start a stream on the default output device. Fails without a device,
+    /// or when built without the `audio` feature.
+    #[cfg(feature = "audio")]
+    pub fn open(tone: Tone) -> Result<Buzzer, String> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+        use cpal::SampleFormat;
+        use std::sync::Arc;
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device")?;
+        let config = device
+            .default_output_config()
+            .map_err(|err| err.to_string())?;
+        let controls = Arc::new(Controls::default());
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => build::<f32>(&device, &config.into(), tone, controls.clone()),
+            SampleFormat::I16 => build::<i16>(&device, &config.into(), tone, controls.clone()),
+            SampleFormat::U16 => build::<u16>(&device, &config.into(), tone, controls.clone()),
+            format => return Err(format!("unsupported sample format {:?}", format)),
+        }
+        .map_err(|err| err.to_string())?;
+        stream.play().map_err(|err| err.to_string())?;
+        Ok(Buzzer {
+            controls,
+            _stream: stream,
+        })
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub fn open(_tone: Tone) -> Result<Buzzer, String> {
+        Err(String::from("built without audio support"))
+    }
+
+    fn set_active(&self, on: bool) {
+        #[cfg(feature = "audio")]
+        self.controls
+            .active
+            .store(on, std::sync::atomic::Ordering::Relaxed);
+        #[cfg(not(feature = "audio"))]
+        let _ = on;
+    }
+}
+
+impl Audio for Buzzer {
+    fn start_beep(&mut self) {
+        self.set_active(true);
+    }
+
+    fn stop_beep(&mut self) {
+        self.set_active(false);
+    }
+
+    fn set_pattern(&mut self, pattern: AudioPattern, rate: f32) {
+        #[cfg(feature = "audio")]
+        if let Ok(mut waiting) = self.controls.pattern.lock() {
+            *waiting = Some((pattern, rate));
+        }
+        #[cfg(not(feature = "audio"))]
+        let _ = (pattern, rate);
+    }
+}
+
+#[cfg(feature = "audio")]
+fn build<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    tone: Tone,
+    controls: std::sync::Arc<Controls>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    use cpal::traits::DeviceTrait;
+    use std::sync::atomic::Ordering;
+
+    let mut oscillator = Oscillator::new(tone, config.sample_rate.0 as f32);
+    let channels = config.channels as usize;
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            // Never block the audio thread, a busy lock is retried next time
+            if let Some((pattern, rate)) =
+                controls.pattern.try_lock().ok().and_then(|mut w| w.take())
+            {
+                oscillator.set_pattern(pattern, rate);
+            }
+            let on = controls.active.load(Ordering::Relaxed);
+            for frame in data.chunks_mut(channels) {
+                let value = if on { oscillator.next_sample() } else { 0. };
+                for sample in frame {
+                    *sample = T::from_sample(value);
+                }
+            }
+        },
+        |err| eprintln!("Err: audio stream: {}", err),
+        None,
+    )
+}
+
+/// Whether this build can play sound
+pub fn supports_audio() -> bool {
+    cfg!(feature = "audio")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(waveform: Waveform) -> Vec<f32> {
+        let tone = Tone {
+            waveform,
+            pitch: 1.,
+        };
+        let mut oscillator = Oscillator::new(tone, 4.);
+        (0..4).map(|_| oscillator.next_sample() / VOLUME).collect()
+    }
+
+    #[test]
+    fn test_waveforms() {
+        assert_eq!(samples(Waveform::Square), [1., 1., -1., -1.]);
+        assert_eq!(samples(Waveform::Triangle), [-1., 0., 1., 0.]);
+        let sine = samples(Waveform::Sine);
+        assert!((sine[1] - 1.).abs() < 1e-6 && (sine[3] + 1.).abs() < 1e-6);
+        let noise = samples(Waveform::Noise);
+        assert!(noise.iter().all(|value| (-1. ..=1.).contains(value)));
+        assert_ne!(noise[1], noise[3]);
+        assert_eq!(Waveform::from_name("sine"), Some(Waveform::Sine));
+    }
+
+    #[test]
+    fn test_pattern_playback() {
+        let mut oscillator = Oscillator::new(Tone::default(), 8000.);
+        let mut pattern = [0; 16];
+        pattern[0] = 0b1010_0000;
+        // Half a bit per sample
+        oscillator.set_pattern(pattern, 4000.);
+        let samples: Vec<f32> = (0..6).map(|_| oscillator.next_sample() / VOLUME).collect();
+        assert_eq!(samples, [1., 1., -1., -1., 1., 1.]);
+    }
+}
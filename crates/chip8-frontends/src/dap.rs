@@ -0,0 +1,599 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chip8_core::instruction;
+use chip8_core::{parse_address, Break, Chip8Interpreter};
+use crossbeam_channel::{select, tick};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+/// Time between frames while the program runs
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+/// The only thread there is
+const THREAD_ID: i64 = 1;
+/// The disassembly listing, served by the `source` request
+const DISASSEMBLY_REFERENCE: i64 = 1;
+/// Address of line 1 of the disassembly listing, which has a line per
+/// two-byte word from there to the end of memory
+const DISASSEMBLY_START: u16 = 0x200;
+const REGISTERS_REFERENCE: i64 = 1;
+const DISPLAY_REFERENCE: i64 = 2;
+
+/// Read a message framed with a Content-Length header. None at the end of
+/// input.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// What the program is running towards
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Run {
+    Continue,
+    /// Stepping over a call, until the stack is back to this depth
+    Over(usize),
+    /// Stepping out of a subroutine, until the stack is below this depth
+    Out(usize),
+}
+
+/// A Debug Adapter Protocol server, so editors such as VS Code can set
+/// breakpoints, step and inspect the machine. The code is shown as a
+/// disassembly listing with a line per word from 0x200.
+#[derive(Debug, Default)]
+pub struct DapServer {
+    seq: i64,
+    running: Option<Run>,
+    stop_on_entry: bool,
+    /// Breakpoints set through the listing, replaced as a whole by each
+    /// setBreakpoints
+    line_breakpoints: Vec<u16>,
+    /// The same for setInstructionBreakpoints
+    instruction_breakpoints: Vec<u16>,
+    /// The same for setFunctionBreakpoints, which take symbol names
+    function_breakpoints: Vec<u16>,
+    done: bool,
+}
+
+impl DapServer {
+    pub fn new() -> DapServer {
+        DapServer::default()
+    }
+
+    /// Serve requests from stdin until the editor disconnects
+    pub fn run(&mut self, cpu: &mut Chip8Interpreter) -> io::Result<()> {
+        let (sender, requests) = crossbeam_channel::unbounded();
+        std::thread::spawn(move || {
+            let stdin = io::stdin();
+            let mut input = stdin.lock();
+            while let Ok(Some(message)) = read_message(&mut input) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        let ticker = tick(FRAME_TIME);
+        let mut output = io::stdout();
+        while !self.done {
+            let messages = select! {
+                recv(requests) -> request => match request {
+                    Ok(request) => self.handle(cpu, &request),
+                    Err(_) => return Ok(()),
+                },
+                recv(ticker) -> _ => match self.running {
+                    Some(_) => self.run_frame(cpu),
+                    None => vec![],
+                },
+            };
+            for message in messages.iter() {
+                write_message(&mut output, message)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn next_seq(&mut self) -> i64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Value {
+        json!({"seq": self.next_seq(), "type": "event", "event": event, "body": body})
+    }
+
+    fn response(&mut self, request: &Value, result: Result<Value, String>) -> Value {
+        let mut response = json!({
+            "seq": self.next_seq(),
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        response
+    }
+
+    fn stopped(&mut self, reason: &str, text: Option<String>) -> Value {
+        self.running = None;
+        let mut body = json!({"reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true});
+        if let Some(text) = text {
+            body["text"] = json!(text);
+        }
+        self.event("stopped", body)
+    }
+
+    /// Answer a request, followed by any events it causes
+    pub fn handle(&mut self, cpu: &mut Chip8Interpreter, request: &Value) -> Vec<Value> {
+        let args = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or("");
+        let mut events = vec![];
+        let result = match command {
+            "initialize" => {
+                events.push(self.event("initialized", json!({})));
+                Ok(json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsInstructionBreakpoints": true,
+                    "supportsFunctionBreakpoints": true,
+                    "supportsReadMemoryRequest": true,
+                    "supportsDisassembleRequest": true,
+                }))
+            }
+            "launch" | "attach" => {
+                self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                Ok(json!({}))
+            }
+            "configurationDone" => {
+                if self.stop_on_entry {
+                    events.push(self.stopped("entry", None));
+                } else {
+                    self.running = Some(Run::Continue);
+                }
+                Ok(json!({}))
+            }
+            "setBreakpoints" => {
+                let addrs: Vec<u16> = args["breakpoints"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                    .map(|line| line_address(line as i64))
+                    .collect();
+                let breakpoints = replace_breakpoints(cpu, &mut self.line_breakpoints, &addrs);
+                Ok(json!({ "breakpoints": breakpoints }))
+            }
+            "setInstructionBreakpoints" => {
+                let addrs: Vec<u16> = args["breakpoints"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|breakpoint| {
+                        let addr = parse_address(breakpoint["instructionReference"].as_str()?)?;
+                        Some((addr as i64 + breakpoint["offset"].as_i64().unwrap_or(0)) as u16)
+                    })
+                    .collect();
+                let breakpoints =
+                    replace_breakpoints(cpu, &mut self.instruction_breakpoints, &addrs);
+                Ok(json!({ "breakpoints": breakpoints }))
+            }
+            "setFunctionBreakpoints" => {
+                let names = args["breakpoints"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|breakpoint| breakpoint["name"].as_str());
+                let mut addrs = vec![];
+                let mut unknown = vec![];
+                for name in names {
+                    match cpu.symbols().resolve(name) {
+                        Some(addr) => addrs.push(addr),
+                        None => unknown.push(name),
+                    }
+                }
+                let mut breakpoints =
+                    replace_breakpoints(cpu, &mut self.function_breakpoints, &addrs);
+                breakpoints.extend(unknown.iter().map(
+                    |name| json!({"verified": false, "message": format!("no symbol '{}'", name)}),
+                ));
+                Ok(json!({ "breakpoints": breakpoints }))
+            }
+            "threads" => Ok(json!({"threads": [{"id": THREAD_ID, "name": "CHIP-8"}]})),
+            "stackTrace" => Ok(stack_trace(cpu)),
+            "scopes" => Ok(json!({"scopes": [
+                {"name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false},
+                {"name": "Display", "variablesReference": DISPLAY_REFERENCE, "expensive": false},
+            ]})),
+            "variables" => Ok(variables(cpu, args["variablesReference"].as_i64())),
+            "source" => Ok(json!({"content": listing(cpu)})),
+            "continue" => {
+                cpu.resume();
+                self.running = Some(Run::Continue);
+                Ok(json!({"allThreadsContinued": true}))
+            }
+            "next" | "stepIn" | "stepOut" => {
+                let depth = cpu.stack().len();
+                let run = match command {
+                    "next" => Some(Run::Over(depth)),
+                    "stepOut" => Some(Run::Out(depth)),
+                    _ => None,
+                };
+                cpu.resume();
+                match cpu.step() {
+                    Err(err) => events.push(self.stopped("exception", Some(err.to_string()))),
+                    Ok(_) => {
+                        self.running = run;
+                        if let Some(event) = self.check_stop(cpu) {
+                            events.push(event);
+                        } else if run.is_none() {
+                            events.push(self.stopped("step", None));
+                        }
+                    }
+                }
+                Ok(json!({}))
+            }
+            "pause" => {
+                events.push(self.stopped("pause", None));
+                Ok(json!({}))
+            }
+            "readMemory" => read_memory(cpu, args),
+            "disassemble" => disassemble(cpu, args),
+            "disconnect" => {
+                self.done = true;
+                Ok(json!({}))
+            }
+            _ => Err(format!("unsupported request '{}'", command)),
+        };
+        let mut messages = vec![self.response(request, result)];
+        messages.append(&mut events);
+        messages
+    }
+
+    /// A stopped event when the last step ended what the program was
+    /// running towards
+    fn check_stop(&mut self, cpu: &Chip8Interpreter) -> Option<Value> {
+        if let Some(reason) = cpu.paused() {
+            let kind = match reason {
+                Break::Breakpoint(_) => "breakpoint",
+                _ => "data breakpoint",
+            };
+            return Some(self.stopped(kind, Some(reason.to_string())));
+        }
+        if cpu.halted() {
+            return Some(self.stopped("pause", Some("The program has ended".to_string())));
+        }
+        let depth = cpu.stack().len();
+        match self.running {
+            Some(Run::Over(target)) if depth <= target => Some(self.stopped("step", None)),
+            Some(Run::Out(target)) if depth < target => Some(self.stopped("step", None)),
+            _ => None,
+        }
+    }
+
+    /// Run a frame's worth of instructions, one at a time to stop as soon
+    /// as a step or breakpoint is reached
+    pub fn run_frame(&mut self, cpu: &mut Chip8Interpreter) -> Vec<Value> {
+        let instructions = (cpu.clock_speed() / 60.) as usize;
+        for _ in 0..instructions {
+            if let Err(err) = cpu.step() {
+                return vec![self.stopped("exception", Some(err.to_string()))];
+            }
+            if let Some(event) = self.check_stop(cpu) {
+                return vec![event];
+            }
+        }
+        cpu.tick_timers();
+        vec![]
+    }
+}
+
+/// Line of the disassembly listing showing `addr`
+fn address_line(addr: u16) -> i64 {
+    (addr as i64 - DISASSEMBLY_START as i64).div_euclid(2) + 1
+}
+
+fn line_address(line: i64) -> u16 {
+    (DISASSEMBLY_START as i64 + (line - 1) * 2) as u16
+}
+
+fn disassembly_source() -> Value {
+    json!({"name": "disassembly", "sourceReference": DISASSEMBLY_REFERENCE})
+}
+
+/// Set breakpoints at `addrs` in place of the ones in `current`
+fn replace_breakpoints(
+    cpu: &mut Chip8Interpreter,
+    current: &mut Vec<u16>,
+    addrs: &[u16],
+) -> Vec<Value> {
+    for addr in current.drain(..) {
+        cpu.debugger_mut().clear_breakpoint(addr);
+    }
+    addrs
+        .iter()
+        .map(|&addr| {
+            let verified = (addr as usize) < cpu.memory().len() - 1;
+            if verified {
+                cpu.debugger_mut().set_breakpoint(addr);
+                current.push(addr);
+            }
+            json!({
+                "verified": verified,
+                "line": address_line(addr),
+                "source": disassembly_source(),
+                "instructionReference": format!("{:#05x}", addr),
+            })
+        })
+        .collect()
+}
+
+/// PC, then the call sites on the stack, innermost first, named after
+/// the symbol they are in when there is one
+fn stack_trace(cpu: &Chip8Interpreter) -> Value {
+    let calls = cpu.stack().iter().rev().map(|&ret| ret.wrapping_sub(2));
+    let frames: Vec<Value> = std::iter::once(cpu.pc())
+        .chain(calls)
+        .enumerate()
+        .map(|(id, addr)| {
+            let name = cpu.symbols().label(addr);
+            json!({
+                "id": id,
+                "name": name.unwrap_or_else(|| format!("{:#05x}", addr)),
+                "source": disassembly_source(),
+                "line": address_line(addr).max(1),
+                "column": 0,
+                "instructionPointerReference": format!("{:#05x}", addr),
+            })
+        })
+        .collect();
+    json!({"stackFrames": frames, "totalFrames": frames.len()})
+}
+
+fn variables(cpu: &Chip8Interpreter, reference: Option<i64>) -> Value {
+    let variable = |name: String, value: String| json!({"name": name, "value": value, "variablesReference": 0});
+    let variables: Vec<Value> = match reference {
+        Some(REGISTERS_REFERENCE) => (0..16)
+            .map(|x| variable(format!("V{:X}", x), format!("{:#04x}", cpu.v(x))))
+            .chain([
+                variable("I".to_string(), format!("{:#05x}", cpu.i())),
+                variable("PC".to_string(), format!("{:#05x}", cpu.pc())),
+                variable("SP".to_string(), cpu.stack().len().to_string()),
+                variable("DT".to_string(), cpu.delay_timer().to_string()),
+                variable("ST".to_string(), cpu.sound_timer().to_string()),
+            ])
+            .collect(),
+        Some(DISPLAY_REFERENCE) => cpu
+            .frame_buffer()
+            .rows()
+            .enumerate()
+            .map(|(y, row)| {
+                let pixels = row.iter().map(|&x| if x > 0 { '█' } else { ' ' });
+                variable(format!("{:02}", y), pixels.collect())
+            })
+            .collect(),
+        _ => vec![],
+    };
+    json!({ "variables": variables })
+}
+
+fn opcode_at(mem: &[u8], addr: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*mem.get(addr)?, *mem.get(addr + 1)?]))
+}
+
+/// The listing behind the `disassembly` source, a line per word, with
+/// the symbol names after the words they name
+fn listing(cpu: &Chip8Interpreter) -> String {
+    let mem = cpu.memory();
+    (DISASSEMBLY_START as usize..mem.len() - 1)
+        .step_by(2)
+        .map(|addr| {
+            let opcode = opcode_at(mem, addr).unwrap_or(0);
+            let name = match cpu.symbols().name(addr as u16) {
+                Some(name) => format!("  <{}>", name),
+                None => String::new(),
+            };
+            format!(
+                "{:#05x}  {:04X}  {}{}\n",
+                addr,
+                opcode,
+                instruction::mnemonic(opcode),
+                name
+            )
+        })
+        .collect()
+}
+
+fn memory_reference(args: &Value) -> Result<i64, String> {
+    let reference = args["memoryReference"].as_str().unwrap_or("");
+    let addr = parse_address(reference)
+        .ok_or_else(|| format!("invalid memory reference '{}'", reference))?;
+    Ok(addr as i64 + args["offset"].as_i64().unwrap_or(0))
+}
+
+fn read_memory(cpu: &Chip8Interpreter, args: &Value) -> Result<Value, String> {
+    let start = memory_reference(args)?;
+    let count = args["count"].as_i64().unwrap_or(0);
+    let mem = cpu.memory();
+    let end = (start + count).clamp(start, mem.len() as i64);
+    let bytes = mem.get(start as usize..end as usize).unwrap_or_default();
+    Ok(json!({
+        "address": format!("{:#05x}", start),
+        "data": BASE64.encode(bytes),
+        "unreadableBytes": count - bytes.len() as i64,
+    }))
+}
+
+fn disassemble(cpu: &Chip8Interpreter, args: &Value) -> Result<Value, String> {
+    let start = memory_reference(args)? + args["instructionOffset"].as_i64().unwrap_or(0) * 2;
+    let count = args["instructionCount"].as_i64().unwrap_or(0);
+    let mem = cpu.memory();
+    let instructions: Vec<Value> = (0..count)
+        .map(|index| {
+            let addr = start + index * 2;
+            let opcode = (addr >= 0)
+                .then_some(addr as usize)
+                .and_then(|addr| opcode_at(mem, addr));
+            let (bytes, instruction) = match opcode {
+                Some(opcode) => (format!("{:04X}", opcode), instruction::mnemonic(opcode)),
+                None => (String::new(), String::from("??")),
+            };
+            let mut instruction = json!({
+                "address": format!("{:#05x}", addr),
+                "instructionBytes": bytes,
+                "instruction": instruction,
+                "line": address_line(addr as u16),
+                "location": disassembly_source(),
+            });
+            if let Some(name) = cpu.symbols().name(addr as u16) {
+                instruction["symbol"] = json!(name);
+            }
+            instruction
+        })
+        .collect();
+    Ok(json!({ "instructions": instructions }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu() -> Chip8Interpreter {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        // V0 = 1; call 0x206; jump to self; V1 = 2; return
+        cpu.load_rom_bytes(&[0x60, 0x01, 0x22, 0x06, 0x12, 0x04, 0x61, 0x02, 0x00, 0xEE])
+            .unwrap();
+        cpu
+    }
+
+    fn request(
+        server: &mut DapServer,
+        cpu: &mut Chip8Interpreter,
+        command: &str,
+        args: Value,
+    ) -> Vec<Value> {
+        let request = json!({"seq": 1, "type": "request", "command": command, "arguments": args});
+        server.handle(cpu, &request)
+    }
+
+    #[test]
+    fn test_framing() {
+        let mut buffer = vec![];
+        write_message(&mut buffer, &json!({"seq": 1})).unwrap();
+        assert_eq!(buffer, b"Content-Length: 9\r\n\r\n{\"seq\":1}");
+        let mut input = &buffer[..];
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({"seq": 1})));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn test_symbols() {
+        let mut cpu = cpu();
+        cpu.set_symbols("206 double\n".parse().unwrap());
+        let mut server = DapServer::new();
+        let messages = request(
+            &mut server,
+            &mut cpu,
+            "setFunctionBreakpoints",
+            json!({"breakpoints": [{"name": "double"}, {"name": "nowhere"}]}),
+        );
+        let breakpoints = &messages[0]["body"]["breakpoints"];
+        assert_eq!(breakpoints[0]["line"], 4);
+        assert_eq!(breakpoints[1]["verified"], false);
+        request(&mut server, &mut cpu, "configurationDone", json!({}));
+        server.run_frame(&mut cpu);
+        cpu.resume();
+        cpu.step().unwrap();
+        let trace = request(&mut server, &mut cpu, "stackTrace", json!({}));
+        assert_eq!(trace[0]["body"]["stackFrames"][0]["name"], "double+2");
+        assert!(listing(&cpu).starts_with("0x200  6001  LD V0, 0x01\n"));
+        assert!(listing(&cpu).contains("0x206  6102  LD V1, 0x02  <double>\n"));
+    }
+
+    #[test]
+    fn test_session() {
+        let mut cpu = cpu();
+        let mut server = DapServer::new();
+        let messages = request(&mut server, &mut cpu, "initialize", json!({}));
+        assert_eq!(messages[0]["body"]["supportsDisassembleRequest"], true);
+        assert_eq!(messages[1]["event"], "initialized");
+        // Line 4 of the listing is 0x206
+        let messages = request(
+            &mut server,
+            &mut cpu,
+            "setBreakpoints",
+            json!({"source": disassembly_source(), "breakpoints": [{"line": 4}]}),
+        );
+        assert_eq!(messages[0]["body"]["breakpoints"][0]["verified"], true);
+        request(&mut server, &mut cpu, "launch", json!({}));
+        request(&mut server, &mut cpu, "configurationDone", json!({}));
+        let messages = server.run_frame(&mut cpu);
+        assert_eq!(messages[0]["event"], "stopped");
+        assert_eq!(messages[0]["body"]["reason"], "breakpoint");
+        let trace = request(&mut server, &mut cpu, "stackTrace", json!({}));
+        let frames = &trace[0]["body"]["stackFrames"];
+        assert_eq!(frames[0]["line"], 4);
+        assert_eq!(frames[1]["instructionPointerReference"], "0x202");
+        // Out of the subroutine
+        let messages = request(&mut server, &mut cpu, "stepOut", json!({}));
+        assert!(messages.len() == 1 && server.running.is_some());
+        let messages = server.run_frame(&mut cpu);
+        assert_eq!(messages[0]["body"]["reason"], "step");
+        assert_eq!(cpu.pc(), 0x204);
+        let variables = request(
+            &mut server,
+            &mut cpu,
+            "variables",
+            json!({"variablesReference": REGISTERS_REFERENCE}),
+        );
+        assert_eq!(variables[0]["body"]["variables"][1]["value"], "0x02");
+        let memory = request(
+            &mut server,
+            &mut cpu,
+            "readMemory",
+            json!({"memoryReference": "0x200", "count": 2}),
+        );
+        assert_eq!(memory[0]["body"]["data"], "YAE=");
+        let code = request(
+            &mut server,
+            &mut cpu,
+            "disassemble",
+            json!({"memoryReference": "0x204", "instructionOffset": -1, "instructionCount": 2}),
+        );
+        let instructions = &code[0]["body"]["instructions"];
+        assert_eq!(instructions[0]["address"], "0x202");
+        assert_eq!(instructions[1]["instruction"], "JP 0x204");
+        let messages = request(&mut server, &mut cpu, "evaluate", json!({}));
+        assert_eq!(messages[0]["success"], false);
+        request(&mut server, &mut cpu, "disconnect", json!({}));
+        assert!(server.done);
+    }
+}
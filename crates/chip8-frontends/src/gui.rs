@@ -0,0 +1,411 @@
+use crate::repl;
+use crate::video::{PIXEL_OFF, PIXEL_ON};
+use chip8_core::{parse_address, Chip8Interpreter, FlameNode};
+use chip8_core::{FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+use eframe::egui::{self, Color32, ColorImage, Key, TextureHandle, TextureOptions};
+use std::time::{Duration, Instant};
+
+const TITLE: &str = "Chip8 Debugger";
+/// One 60Hz frame
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+/// Frames run at most per repaint when the window falls behind
+const MAX_CATCH_UP_FRAMES: u32 = 4;
+/// Host keys for the hex keypad, in the usual QWERTY layout
+const KEYPAD_KEYS: [(Key, u8); 16] = [
+    (Key::Num1, 0x1),
+    (Key::Num2, 0x2),
+    (Key::Num3, 0x3),
+    (Key::Num4, 0xC),
+    (Key::Q, 0x4),
+    (Key::W, 0x5),
+    (Key::E, 0x6),
+    (Key::R, 0xD),
+    (Key::A, 0x7),
+    (Key::S, 0x8),
+    (Key::D, 0x9),
+    (Key::F, 0xE),
+    (Key::Z, 0xA),
+    (Key::X, 0x0),
+    (Key::C, 0xB),
+    (Key::V, 0xF),
+];
+/// Instructions in the code pane
+const CODE_LINES: u16 = 24;
+/// Bytes in the memory pane
+const MEMORY_VIEW_BYTES: usize = 256;
+/// Height of one call depth in the flame graph
+const FLAME_ROW_HEIGHT: f32 = 18.;
+
+/// A debugger window with the game display in the middle and panes for
+/// registers, stack, breakpoints, disassembly, memory and the call
+/// profile around it
+pub struct GuiDebugger {
+    cpu: Chip8Interpreter,
+    running: bool,
+    next_frame: Instant,
+    status: String,
+    display: Option<TextureHandle>,
+    /// Contents of the add breakpoint field
+    new_breakpoint: String,
+    /// Contents of the memory address field
+    memory_addr: String,
+}
+
+impl GuiDebugger {
+    pub fn new(cpu: Chip8Interpreter) -> GuiDebugger {
+        GuiDebugger {
+            cpu,
+            running: false,
+            next_frame: Instant::now(),
+            status: String::from("Paused"),
+            display: None,
+            new_breakpoint: String::new(),
+            memory_addr: String::from("200"),
+        }
+    }
+
+    /// Open the window and debug until it is closed
+    pub fn run(self) -> Result<(), String> {
+        eframe::run_native(
+            TITLE,
+            eframe::NativeOptions::default(),
+            Box::new(|_| Ok(Box::new(self))),
+        )
+        .map_err(|err| err.to_string())
+    }
+
+    fn run_frames(&mut self) {
+        let now = Instant::now();
+        for _ in 0..MAX_CATCH_UP_FRAMES {
+            if !self.running || self.next_frame > now {
+                break;
+            }
+            self.run_frame();
+            self.next_frame += FRAME_TIME;
+        }
+        self.next_frame = self.next_frame.max(now);
+    }
+
+    /// Run a frame, stopping on a pause, a fault or the end of the program
+    fn run_frame(&mut self) {
+        if let Err(err) = self.cpu.run_frame() {
+            self.running = false;
+            self.status = format!("Fault: {}", err);
+        } else if let Some(reason) = self.cpu.paused() {
+            self.running = false;
+            self.status = format!("Paused on {}", reason);
+        } else if self.cpu.halted() {
+            self.running = false;
+            self.status = String::from("The program has ended");
+        }
+    }
+
+    fn step(&mut self) {
+        self.running = false;
+        self.cpu.resume();
+        let explanation = self.cpu.explain();
+        self.status = match self.cpu.step() {
+            Ok(Some(_)) => explanation,
+            Ok(None) => String::from("The program has ended"),
+            Err(err) => format!("Fault: {}", err),
+        };
+    }
+
+    fn toggle_breakpoint(&mut self, addr: u16) {
+        let debugger = self.cpu.debugger_mut();
+        if !debugger.clear_breakpoint(addr) {
+            debugger.set_breakpoint(addr);
+        }
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let label = if self.running { "Pause" } else { "Run" };
+            if ui.button(label).clicked() {
+                self.running = !self.running;
+                if self.running {
+                    self.cpu.resume();
+                    self.next_frame = Instant::now();
+                    self.status = String::from("Running");
+                } else {
+                    self.status = String::from("Paused");
+                }
+            }
+            if ui.button("Step").clicked() {
+                self.step();
+            }
+            if ui.button("Frame").clicked() {
+                self.running = false;
+                self.cpu.resume();
+                self.run_frame();
+            }
+            if ui.button("Reset").clicked() {
+                self.cpu.reset();
+                self.status = String::from("Reset");
+            }
+            ui.separator();
+            ui.label(&self.status);
+        });
+    }
+
+    fn registers(&mut self, ui: &mut egui::Ui) {
+        let cpu = &self.cpu;
+        egui::Grid::new("registers").striped(true).show(ui, |ui| {
+            ui.monospace(format!("PC {:#05x}", cpu.pc()));
+            ui.monospace(format!("I  {:#05x}", cpu.i()));
+            ui.end_row();
+            ui.monospace(format!("DT {:>5}", cpu.delay_timer()));
+            ui.monospace(format!("ST {:>5}", cpu.sound_timer()));
+            ui.end_row();
+            for x in 0..8 {
+                ui.monospace(format!("V{:X} {:02x}", x, cpu.v(x)));
+                ui.monospace(format!("V{:X} {:02x}", x + 8, cpu.v(x + 8)));
+                ui.end_row();
+            }
+        });
+        ui.monospace(format!("cycle {}", cpu.cycles()));
+        ui.separator();
+        ui.heading("Stack");
+        for addr in cpu.stack().iter().rev() {
+            ui.monospace(format!("{:#05x}", addr));
+        }
+        ui.separator();
+        ui.heading("Breakpoints");
+        let breakpoints: Vec<u16> = cpu.debugger().breakpoints().collect();
+        for addr in breakpoints {
+            ui.horizontal(|ui| {
+                ui.monospace(format!("{:#05x}", addr));
+                if ui.small_button("x").clicked() {
+                    self.cpu.debugger_mut().clear_breakpoint(addr);
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_breakpoint).desired_width(48.));
+            if ui.button("Add").clicked() {
+                match parse_address(&self.new_breakpoint) {
+                    Some(addr) => {
+                        self.cpu.debugger_mut().set_breakpoint(addr);
+                        self.new_breakpoint.clear();
+                    }
+                    None => self.status = format!("Invalid address '{}'", self.new_breakpoint),
+                }
+            }
+        });
+    }
+
+    /// The code around PC, click a line to toggle a breakpoint there
+    fn code(&mut self, ui: &mut egui::Ui) {
+        let first = self.cpu.pc().saturating_sub(CODE_LINES / 3 * 2);
+        let end = self.cpu.memory().len() as u16 - 1;
+        for addr in (first..end).step_by(2).take(CODE_LINES as usize) {
+            let line = repl::disassemble(&self.cpu, addr, 1);
+            let text = egui::RichText::new(line.trim_end()).monospace();
+            let current = addr == self.cpu.pc();
+            if ui.selectable_label(current, text).clicked() {
+                self.toggle_breakpoint(addr);
+            }
+        }
+    }
+
+    fn memory(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Address");
+            ui.add(egui::TextEdit::singleline(&mut self.memory_addr).desired_width(64.));
+        });
+        match repl::memory(&self.cpu, &self.memory_addr, MEMORY_VIEW_BYTES) {
+            Ok(dump) => ui.monospace(dump),
+            Err(err) => ui.label(err),
+        };
+    }
+
+    fn call_profile(&mut self, ui: &mut egui::Ui) {
+        let Some(profile) = self.cpu.call_profile() else {
+            if ui.button("Start profiling calls").clicked() {
+                self.cpu.enable_call_profile();
+            }
+            return;
+        };
+        flame_graph(ui, &profile.tree());
+    }
+
+    fn update_keypad(&mut self, ctx: &egui::Context) {
+        // Typing an address isn't playing the game
+        let typing = ctx.wants_keyboard_input();
+        ctx.input(|input| {
+            for &(key, chip8_key) in KEYPAD_KEYS.iter() {
+                self.cpu.set_key(chip8_key, !typing && input.key_down(key));
+            }
+        });
+    }
+
+    fn draw_display(&mut self, ui: &mut egui::Ui) {
+        let image = display_image(&self.cpu);
+        let texture = match &mut self.display {
+            Some(texture) => {
+                texture.set(image, TextureOptions::NEAREST);
+                texture
+            }
+            None => self.display.insert(ui.ctx().load_texture(
+                "display",
+                image,
+                TextureOptions::NEAREST,
+            )),
+        };
+        let available = ui.available_size();
+        let scale = (available.x / FRAME_BUFFER_WIDTH as f32)
+            .min(available.y / FRAME_BUFFER_HEIGHT as f32)
+            .max(1.);
+        let size = egui::vec2(
+            FRAME_BUFFER_WIDTH as f32 * scale,
+            FRAME_BUFFER_HEIGHT as f32 * scale,
+        );
+        ui.image((texture.id(), size));
+    }
+}
+
+impl eframe::App for GuiDebugger {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.update_keypad(ctx);
+        if self.running {
+            self.run_frames();
+            ctx.request_repaint_after(FRAME_TIME);
+        }
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| self.controls(ui));
+        egui::SidePanel::left("registers").show(ctx, |ui| self.registers(ui));
+        egui::SidePanel::right("code").show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                self.code(ui);
+                ui.separator();
+                egui::CollapsingHeader::new("Calls").show(ui, |ui| self.call_profile(ui));
+            });
+        });
+        egui::TopBottomPanel::bottom("memory").show(ctx, |ui| self.memory(ui));
+        egui::CentralPanel::default().show(ctx, |ui| self.draw_display(ui));
+    }
+}
+
+fn display_image(cpu: &Chip8Interpreter) -> ColorImage {
+    let color = |pixel: u32| {
+        let [_, r, g, b] = pixel.to_be_bytes();
+        Color32::from_rgb(r, g, b)
+    };
+    let pixels = cpu
+        .frame_buffer()
+        .rows()
+        .flatten()
+        .map(|&pixel| color(if pixel != 0 { PIXEL_ON } else { PIXEL_OFF }))
+        .collect();
+    ColorImage {
+        size: [FRAME_BUFFER_WIDTH, FRAME_BUFFER_HEIGHT],
+        pixels,
+    }
+}
+
+/// A subroutine's bar in the flame graph: call depth, and where it starts
+/// and how wide it is as fractions of the whole width
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FlameBar {
+    addr: u16,
+    depth: usize,
+    start: f32,
+    width: f32,
+    total: u64,
+    own: u64,
+}
+
+/// Lay the call tree out with callers above callees, each as wide as the
+/// share of instructions it accounts for
+fn flame_bars(root: &FlameNode) -> Vec<FlameBar> {
+    fn lay_out(node: &FlameNode, depth: usize, start: f32, scale: f32, bars: &mut Vec<FlameBar>) {
+        bars.push(FlameBar {
+            addr: node.addr,
+            depth,
+            start,
+            width: node.total as f32 * scale,
+            total: node.total,
+            own: node.own,
+        });
+        let mut child_start = start;
+        for child in node.children.iter() {
+            lay_out(child, depth + 1, child_start, scale, bars);
+            child_start += child.total as f32 * scale;
+        }
+    }
+    let mut bars = vec![];
+    lay_out(root, 0, 0., 1. / root.total.max(1) as f32, &mut bars);
+    bars
+}
+
+/// The call tree as a flame graph, with the hovered subroutine's counts
+/// below it
+fn flame_graph(ui: &mut egui::Ui, root: &FlameNode) {
+    let bars = flame_bars(root);
+    let depth = bars.iter().map(|bar| bar.depth + 1).max().unwrap_or(1);
+    let size = egui::vec2(ui.available_width(), depth as f32 * FLAME_ROW_HEIGHT);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let origin = response.rect.min;
+    let mut hovered = None;
+    for bar in bars.iter() {
+        let rect = egui::Rect::from_min_size(
+            origin + egui::vec2(bar.start * size.x, bar.depth as f32 * FLAME_ROW_HEIGHT),
+            egui::vec2(bar.width * size.x, FLAME_ROW_HEIGHT - 1.),
+        );
+        // Warmer the deeper the call
+        let warmth = (bar.depth * 40).min(200) as u8;
+        painter.rect_filled(rect, 2., Color32::from_rgb(240, 200 - warmth, 60));
+        if rect.width() > 40. {
+            painter.text(
+                rect.left_center() + egui::vec2(3., 0.),
+                egui::Align2::LEFT_CENTER,
+                format!("{:#05x}", bar.addr),
+                egui::FontId::monospace(11.),
+                Color32::BLACK,
+            );
+        }
+        if response.hover_pos().is_some_and(|pos| rect.contains(pos)) {
+            hovered = Some(bar);
+        }
+    }
+    if let Some(bar) = hovered {
+        ui.monospace(format!(
+            "{:#05x}: {} instructions, {} own",
+            bar.addr, bar.total, bar.own
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::{CallProfile, Instruction};
+
+    #[test]
+    fn test_flame_bars() {
+        let mut profile = CallProfile::new();
+        // Three instructions in main, two in 0x300 and one in 0x400
+        for opcode in [0x6000, 0x2300, 0x6000, 0x00EE, 0x2400, 0x00EE] {
+            profile.record(&Instruction::from_raw_opcode(opcode).unwrap());
+        }
+        let bars = flame_bars(&profile.tree());
+        assert_eq!(bars.len(), 3);
+        assert_eq!((bars[0].addr, bars[0].depth, bars[0].width), (0x200, 0, 1.));
+        let callee = |addr| bars.iter().find(|bar| bar.addr == addr).unwrap();
+        assert_eq!(callee(0x300).depth, 1);
+        assert!((callee(0x300).width - 2. / 6.).abs() < 1e-6);
+        assert!((callee(0x400).start - callee(0x300).width).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_display_image() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        // Draw the font's 0 at 0,0
+        cpu.load_rom_bytes(&[0xF0, 0x29, 0xD0, 0x05]).unwrap();
+        cpu.step_n(2).unwrap();
+        let image = display_image(&cpu);
+        assert_eq!(image.size, [FRAME_BUFFER_WIDTH, FRAME_BUFFER_HEIGHT]);
+        assert_eq!(image.pixels[0], Color32::WHITE);
+        assert_eq!(image.pixels[FRAME_BUFFER_WIDTH + 1], Color32::BLACK);
+    }
+}
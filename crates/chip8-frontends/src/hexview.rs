@@ -0,0 +1,168 @@
+use chip8_core::Chip8Interpreter;
+
+/// Bytes per row of a dump
+pub const ROW_BYTES: usize = 16;
+/// Column of the first hex byte in a dump row, after "0x200  "
+pub const HEX_COLUMN: usize = 7;
+
+/// One row of a hex and ASCII dump of `mem` from `addr`, e.g.
+/// `0x200  00 e0 a2 2a ...  |...*...|`. Short rows keep the ASCII column
+/// aligned.
+pub fn dump_row(mem: &[u8], addr: usize, length: usize) -> String {
+    let bytes = &mem[addr..(addr + length.min(ROW_BYTES)).min(mem.len())];
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let ascii: String = bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x20..=0x7E => byte as char,
+            _ => '.',
+        })
+        .collect();
+    format!(
+        "{:#05x}  {:<width$}  |{}|",
+        addr,
+        hex.join(" "),
+        ascii,
+        width = ROW_BYTES * 3 - 1
+    )
+}
+
+/// `length` bytes of `mem` from `start`, a row per line
+pub fn dump(mem: &[u8], start: usize, length: usize) -> String {
+    let end = start.saturating_add(length).min(mem.len());
+    (start..end)
+        .step_by(ROW_BYTES)
+        .map(|row| dump_row(mem, row, end - row) + "\n")
+        .collect()
+}
+
+/// Write `bytes` to memory at `addr` in the running machine. Pokes are
+/// patches, so `undo` in the debugger reverts them.
+pub fn poke(cpu: &mut Chip8Interpreter, addr: u16, bytes: &[u8]) -> Result<(), String> {
+    cpu.patch(addr, bytes)
+}
+
+/// Bytes from hex pairs, with or without spaces between them
+pub fn parse_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let hex: String = text.split_whitespace().collect();
+    let invalid = || format!("expected hex bytes, got '{}'", text);
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        })
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)
+}
+
+/// A cursor over memory for editing it a hex digit at a time, the way
+/// hex editors do: two digits overwrite the byte under the cursor and
+/// move on to the next one
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HexEditor {
+    cursor: u16,
+    /// First address shown
+    top: u16,
+    /// High nibble typed for the byte under the cursor
+    pending: Option<u8>,
+}
+
+impl HexEditor {
+    pub fn new(addr: u16) -> HexEditor {
+        HexEditor {
+            cursor: addr,
+            top: addr - addr % ROW_BYTES as u16,
+            pending: None,
+        }
+    }
+
+    pub fn cursor(&self) -> u16 {
+        self.cursor
+    }
+
+    /// Move the cursor by `delta` bytes, staying inside memory and in
+    /// view of a window `rows` high
+    pub fn move_by(&mut self, cpu: &Chip8Interpreter, delta: i32, rows: usize) {
+        let last = cpu.memory().len() as i32 - 1;
+        self.cursor = (self.cursor as i32 + delta).clamp(0, last) as u16;
+        self.pending = None;
+        let row = self.cursor - self.cursor % ROW_BYTES as u16;
+        let height = (rows.max(1) * ROW_BYTES) as u16;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + height {
+            self.top = row + ROW_BYTES as u16 - height;
+        }
+    }
+
+    /// Type a hex digit at the cursor
+    pub fn type_digit(&mut self, cpu: &mut Chip8Interpreter, digit: u8, rows: usize) {
+        match self.pending.take() {
+            None => self.pending = Some(digit),
+            Some(high) => {
+                // The cursor is always inside memory
+                let _ = poke(cpu, self.cursor, &[high << 4 | digit]);
+                self.move_by(cpu, 1, rows);
+            }
+        }
+    }
+
+    /// `rows` rows of dump from the top of the view
+    pub fn lines(&self, cpu: &Chip8Interpreter, rows: usize) -> Vec<String> {
+        let mem = cpu.memory();
+        (self.top as usize..mem.len())
+            .step_by(ROW_BYTES)
+            .take(rows)
+            .map(|row| dump_row(mem, row, ROW_BYTES))
+            .collect()
+    }
+
+    /// Row and column of the cursor's hex digits in `lines`
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let offset = (self.cursor - self.top) as usize;
+        (offset / ROW_BYTES, HEX_COLUMN + offset % ROW_BYTES * 3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump() {
+        let mut mem = vec![0; 0x220];
+        mem[0x200..0x204].copy_from_slice(b"Hi!\x01");
+        assert_eq!(
+            dump(&mem, 0x200, 4),
+            format!("0x200  48 69 21 01{}  |Hi!.|\n", " ".repeat(36))
+        );
+        assert_eq!(dump(&mem, 0x200, 0x40).lines().count(), 2);
+        assert_eq!(dump(&mem, 0x210, usize::MAX).lines().count(), 1);
+        assert_eq!(parse_bytes("0a ff"), Ok(vec![0x0A, 0xFF]));
+        assert_eq!(parse_bytes("0aff"), Ok(vec![0x0A, 0xFF]));
+        assert!(parse_bytes("0af").is_err());
+        assert!(parse_bytes("zz").is_err());
+    }
+
+    #[test]
+    fn test_editor() {
+        let mut cpu = Chip8Interpreter::new();
+        let mut editor = HexEditor::new(0x2FF);
+        editor.type_digit(&mut cpu, 0xA, 4);
+        assert_eq!(cpu.memory()[0x2FF], 0);
+        editor.type_digit(&mut cpu, 0x5, 4);
+        assert_eq!(cpu.memory()[0x2FF], 0xA5);
+        assert_eq!(editor.cursor(), 0x300);
+        assert_eq!(editor.cursor_position(), (1, HEX_COLUMN));
+        // Scrolls to keep the cursor in view
+        editor.move_by(&cpu, 0x100, 4);
+        assert_eq!(editor.lines(&cpu, 4)[3], dump_row(cpu.memory(), 0x400, 16));
+        assert_eq!(cpu.undo_patch().map(|patch| patch.addr), Some(0x2FF));
+        editor.move_by(&cpu, -0x1000, 4);
+        assert_eq!(editor.cursor(), 0);
+    }
+}
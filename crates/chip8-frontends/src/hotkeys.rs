@@ -0,0 +1,262 @@
+use minifb::Key;
+
+/// Emulator functions that can be bound to a host key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Stop and resume the machine
+    Pause,
+    /// Run several instructions per tick while held
+    Turbo,
+    /// Write the machine state to the current save slot
+    SaveState,
+    /// Go back to the state in the current save slot
+    LoadState,
+    /// Pick the next save slot
+    NextSlot,
+    /// Pick the previous save slot
+    PreviousSlot,
+    /// Print the instruction at the program counter
+    Explain,
+    /// Switch the "game grabs all keys" mode on and off
+    ToggleGrab,
+    /// Write the display to the next free screenshot-N.png
+    Screenshot,
+    /// Remember the machine state as the point to retry from
+    PracticeMark,
+    /// Go back to the marked state
+    PracticeRetry,
+    /// Run the machine backwards while held
+    Rewind,
+}
+
+const ACTIONS: [(&str, Action); 12] = [
+    ("pause", Action::Pause),
+    ("turbo", Action::Turbo),
+    ("save-state", Action::SaveState),
+    ("load-state", Action::LoadState),
+    ("next-slot", Action::NextSlot),
+    ("previous-slot", Action::PreviousSlot),
+    ("explain", Action::Explain),
+    ("toggle-grab", Action::ToggleGrab),
+    ("screenshot", Action::Screenshot),
+    ("practice-mark", Action::PracticeMark),
+    ("practice-retry", Action::PracticeRetry),
+    ("rewind", Action::Rewind),
+];
+
+/// Keys that can be bound, matched by their name ignoring case
+const KEYS: [Key; 70] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::Up,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Escape,
+    Key::Space,
+    Key::Enter,
+    Key::Tab,
+    Key::Backspace,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Pause,
+    Key::ScrollLock,
+    Key::Backquote,
+    Key::Minus,
+    Key::Equal,
+    Key::LeftBracket,
+    Key::RightBracket,
+];
+
+/// Which host key triggers which emulator function. In grab mode every key
+/// goes to the game and only ToggleGrab is still handled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hotkeys {
+    bindings: Vec<(Action, Option<Key>)>,
+    pub grab_all: bool,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Hotkeys {
+            bindings: vec![
+                (Action::Pause, Some(Key::Escape)),
+                (Action::Turbo, Some(Key::Tab)),
+                (Action::SaveState, Some(Key::F5)),
+                (Action::LoadState, Some(Key::F8)),
+                (Action::NextSlot, Some(Key::F4)),
+                (Action::PreviousSlot, Some(Key::F3)),
+                (Action::Explain, Some(Key::F1)),
+                (Action::ToggleGrab, Some(Key::ScrollLock)),
+                (Action::Screenshot, Some(Key::F12)),
+                (Action::PracticeMark, Some(Key::F6)),
+                (Action::PracticeRetry, Some(Key::F7)),
+                (Action::Rewind, Some(Key::Backspace)),
+            ],
+            grab_all: false,
+        }
+    }
+}
+
+impl Hotkeys {
+    /// Apply a binding such as "pause=P", or "turbo=none" to unbind
+    pub fn bind(&mut self, spec: &str) -> Result<(), String> {
+        let (action_name, key_name) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected action=key, got {}", spec))?;
+        let action = ACTIONS
+            .iter()
+            .find(|(name, _)| *name == action_name)
+            .map(|&(_, action)| action)
+            .ok_or_else(|| format!("unknown action {}", action_name))?;
+        let key = if key_name.eq_ignore_ascii_case("none") {
+            None
+        } else {
+            Some(key_from_name(key_name).ok_or_else(|| format!("unknown key {}", key_name))?)
+        };
+        if let Some((other, _)) = self
+            .bindings
+            .iter()
+            .find(|&&(other, bound)| other != action && key.is_some() && bound == key)
+        {
+            return Err(format!("{} is already bound to {:?}", key_name, other));
+        }
+        for binding in self.bindings.iter_mut().filter(|(a, _)| *a == action) {
+            binding.1 = key;
+        }
+        Ok(())
+    }
+
+    /// Key bound to `action`, unless the game currently grabs it
+    pub fn key(&self, action: Action) -> Option<Key> {
+        if self.grab_all && action != Action::ToggleGrab {
+            return None;
+        }
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|&(_, key)| key)
+    }
+
+    /// Every binding as an "action=key" spec that `bind` accepts
+    pub fn specs(&self) -> Vec<String> {
+        ACTIONS
+            .iter()
+            .map(|&(name, action)| {
+                let key = self
+                    .bindings
+                    .iter()
+                    .find(|(a, _)| *a == action)
+                    .and_then(|&(_, key)| key);
+                match key {
+                    Some(key) => format!("{}={:?}", name, key),
+                    None => format!("{}=none", name),
+                }
+            })
+            .collect()
+    }
+
+    /// Whether `key` currently triggers an emulator function rather than
+    /// reaching the game
+    pub fn is_bound(&self, key: Key) -> bool {
+        ACTIONS
+            .iter()
+            .any(|&(_, action)| self.key(action) == Some(key))
+    }
+}
+
+pub(crate) fn key_from_name(name: &str) -> Option<Key> {
+    KEYS.iter().copied().find(|key| {
+        let key_name = format!("{:?}", key);
+        key_name.eq_ignore_ascii_case(name) || key_name == format!("Key{}", name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind() {
+        let mut hotkeys = Hotkeys::default();
+        assert_eq!(hotkeys.key(Action::Pause), Some(Key::Escape));
+        assert_eq!(hotkeys.key(Action::LoadState), Some(Key::F8));
+        hotkeys.bind("pause=p").unwrap();
+        assert_eq!(hotkeys.key(Action::Pause), Some(Key::P));
+        hotkeys.bind("turbo=none").unwrap();
+        assert_eq!(hotkeys.key(Action::Turbo), None);
+        hotkeys.bind("save-state=7").unwrap();
+        assert_eq!(hotkeys.key(Action::SaveState), Some(Key::Key7));
+        assert!(hotkeys.bind("explain=P").is_err());
+        assert!(hotkeys.bind("explain=Hyper").is_err());
+        assert!(hotkeys.bind("fast-forward=R").is_err());
+        assert!(hotkeys.bind("explain").is_err());
+        let mut rebound = Hotkeys::default();
+        for spec in hotkeys.specs() {
+            rebound.bind(&spec).unwrap();
+        }
+        assert_eq!(rebound, hotkeys);
+    }
+
+    #[test]
+    fn test_grab_all() {
+        let hotkeys = Hotkeys {
+            grab_all: true,
+            ..Hotkeys::default()
+        };
+        assert_eq!(hotkeys.key(Action::Pause), None);
+        assert_eq!(hotkeys.key(Action::ToggleGrab), Some(Key::ScrollLock));
+        assert!(!hotkeys.is_bound(Key::Escape));
+        assert!(hotkeys.is_bound(Key::ScrollLock));
+    }
+}
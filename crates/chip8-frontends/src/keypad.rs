@@ -0,0 +1,134 @@
+use crate::hotkeys::{key_from_name, Hotkeys};
+use chip8_core::Chip8Interpreter;
+use minifb::{Key, Window};
+
+/// Host keys for the hex keypad, in the usual layout that puts the COSMAC
+/// VIP's 4x4 grid on the left of a QWERTY keyboard:
+///
+/// ```text
+/// 1 2 3 C        1 2 3 4
+/// 4 5 6 D   ->   Q W E R
+/// 7 8 9 E        A S D F
+/// A 0 B F        Z X C V
+/// ```
+pub const KEYPAD_LAYOUT: [(Key, u8); 16] = [
+    (Key::Key1, 0x1),
+    (Key::Key2, 0x2),
+    (Key::Key3, 0x3),
+    (Key::Key4, 0xC),
+    (Key::Q, 0x4),
+    (Key::W, 0x5),
+    (Key::E, 0x6),
+    (Key::R, 0xD),
+    (Key::A, 0x7),
+    (Key::S, 0x8),
+    (Key::D, 0x9),
+    (Key::F, 0xE),
+    (Key::Z, 0xA),
+    (Key::X, 0x0),
+    (Key::C, 0xB),
+    (Key::V, 0xF),
+];
+
+/// Which host key presses each hex key, `KEYPAD_LAYOUT` unless remapped
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    /// Host key of each hex key, by hex key
+    keys: [Option<Key>; 16],
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keys = [None; 16];
+        for &(key, chip8_key) in KEYPAD_LAYOUT.iter() {
+            keys[chip8_key as usize] = Some(key);
+        }
+        Keymap { keys }
+    }
+}
+
+impl Keymap {
+    /// Apply a mapping such as "5=Up", or "5=none" to leave hex key 5
+    /// without a host key
+    pub fn bind(&mut self, spec: &str) -> Result<(), String> {
+        let (hex_name, key_name) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected hex=key, got {}", spec))?;
+        let chip8_key = match u8::from_str_radix(hex_name, 16) {
+            Ok(chip8_key) if hex_name.len() == 1 => chip8_key,
+            _ => return Err(format!("unknown keypad key {}", hex_name)),
+        };
+        let key = if key_name.eq_ignore_ascii_case("none") {
+            None
+        } else {
+            Some(key_from_name(key_name).ok_or_else(|| format!("unknown key {}", key_name))?)
+        };
+        if let Some(other) = (0..16)
+            .find(|&other| other != chip8_key as usize && key.is_some() && self.keys[other] == key)
+        {
+            return Err(format!("{} already presses {:X}", key_name, other));
+        }
+        self.keys[chip8_key as usize] = key;
+        Ok(())
+    }
+
+    /// Host key pressing hex key `chip8_key`
+    pub fn key(&self, chip8_key: u8) -> Option<Key> {
+        self.keys[chip8_key as usize]
+    }
+
+    /// Every mapping as a "hex=key" spec that `bind` accepts
+    pub fn specs(&self) -> Vec<String> {
+        self.keys
+            .iter()
+            .enumerate()
+            .map(|(chip8_key, key)| match key {
+                Some(key) => format!("{:X}={:?}", chip8_key, key),
+                None => format!("{:X}=none", chip8_key),
+            })
+            .collect()
+    }
+}
+
+/// Copy the host keyboard state to the interpreter's keypad. Keys bound to
+/// a hotkey stay with the hotkey.
+pub fn update(window: &Window, keymap: &Keymap, hotkeys: &Hotkeys, cpu: &mut Chip8Interpreter) {
+    for chip8_key in 0..16 {
+        let down = keymap
+            .key(chip8_key)
+            .is_some_and(|key| window.is_key_down(key) && !hotkeys.is_bound(key));
+        cpu.set_key(chip8_key, down);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_covers_keypad() {
+        let mut keys: Vec<u8> = KEYPAD_LAYOUT.iter().map(|&(_, key)| key).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..16).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_keymap() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.key(0x5), Some(Key::W));
+        assert_eq!(keymap.key(0xC), Some(Key::Key4));
+        keymap.bind("5=up").unwrap();
+        assert_eq!(keymap.key(0x5), Some(Key::Up));
+        keymap.bind("c=none").unwrap();
+        assert_eq!(keymap.key(0xC), None);
+        assert!(keymap.bind("8=Up").is_err());
+        assert!(keymap.bind("10=Down").is_err());
+        assert!(keymap.bind("G=Down").is_err());
+        assert!(keymap.bind("8=Hyper").is_err());
+        let mut rebound = Keymap::default();
+        for spec in keymap.specs() {
+            rebound.bind(&spec).unwrap();
+        }
+        assert_eq!(rebound, keymap);
+    }
+}
@@ -0,0 +1,56 @@
+//! Frontends that drive a `chip8_core` interpreter in real time
+
+mod audio;
+mod dap;
+#[cfg(feature = "egui")]
+mod gui;
+mod hexview;
+mod hotkeys;
+mod keypad;
+mod repl;
+mod screenshot;
+mod shared;
+mod slots;
+#[cfg(feature = "tui")]
+mod tui;
+mod video;
+mod watch;
+mod wav;
+mod window;
+
+pub use crate::audio::{supports_audio, Buzzer, Tone, Waveform};
+pub use crate::dap::DapServer;
+#[cfg(feature = "egui")]
+pub use crate::gui::GuiDebugger;
+pub use crate::hexview::HexEditor;
+pub use crate::hotkeys::{Action, Hotkeys};
+pub use crate::keypad::{Keymap, KEYPAD_LAYOUT};
+pub use crate::repl::Repl;
+pub use crate::screenshot::{save_heatmap_png, save_png, ScreenshotSequence};
+pub use crate::shared::SharedFrame;
+pub use crate::slots::{SaveSlots, SLOT_COUNT};
+#[cfg(feature = "tui")]
+pub use crate::tui::TerminalFrontend;
+pub use crate::video::{parse_color, render, ScaleFilter, SoundFlash, VideoOptions};
+pub use crate::wav::WavRecorder;
+pub use crate::window::{RunEnd, WindowFrontend};
+use chip8_core::plugin::Registry;
+
+/// Register the built-in scale filters as renderers
+pub fn register_builtins(registry: &mut Registry) -> Result<(), String> {
+    for (name, filter) in [
+        ("nearest", ScaleFilter::Nearest),
+        ("smooth", ScaleFilter::Smooth),
+    ] {
+        registry.register_renderer(
+            name,
+            Box::new(move || {
+                Box::new(VideoOptions {
+                    filter,
+                    ..VideoOptions::default()
+                })
+            }),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,439 @@
+use crate::hexview;
+use chip8_core::instruction;
+use chip8_core::{parse_snippet, Chip8Error, Chip8Interpreter};
+use chip8_core::{Snapshot, Watchpoint};
+use std::io::{self, BufRead, Write};
+
+/// Frames `continue` runs before giving control back, a minute of emulated
+/// time, so a ROM that never pauses doesn't lock up the prompt
+const CONTINUE_FRAMES: u32 = 60 * 60;
+/// Bytes `mem` shows without a length
+const DEFAULT_MEM_LENGTH: usize = 64;
+/// Instructions `disasm` lists without a count
+const DEFAULT_DISASM_COUNT: usize = 10;
+/// Executed instructions kept for `history`, unless the machine already
+/// keeps some
+const HISTORY_LENGTH: usize = 1024;
+/// Instructions `history` lists without a count
+const DEFAULT_HISTORY_COUNT: usize = 16;
+
+const HELP: &str = "\
+step [N]             run N instructions (1), showing the state after
+continue             run until a breakpoint, watchpoint, fault or the end
+regs                 registers, stack and the code around PC
+mem ADDR [LEN]       hex and ASCII dump of LEN bytes (64) at ADDR,
+                     BANK:ADDR for another memory bank
+poke ADDR BYTES      write hex bytes to memory, e.g. poke 300 0a ff
+disasm [ADDR] [N]    N instructions (10) from ADDR (PC)
+history [N]          the last N executed instructions (16) and the
+                     registers they changed
+break [ADDR]         set a breakpoint at ADDR, or list them
+delete ADDR          clear the breakpoint at ADDR
+watch [SPEC]         watch V3, 300 or 300-30F[:r|:w], or list watchpoints
+unwatch SPEC         remove a watchpoint
+patch ADDR OPCODES   overwrite code with hex opcodes, e.g. patch 2a4 6005
+undo                 revert the last patch or poke
+reset                go back to where the session started, keeping
+                     breakpoints
+symbols              list the symbol names
+quit                 leave the debugger
+An empty line repeats the last command. ADDR can also be a symbol name,
+e.g. break main_loop.
+";
+
+/// A gdb-like prompt over an interpreter: commands in, text out. Addresses
+/// are in hex, with or without 0x, or names from the machine's symbols.
+#[derive(Default)]
+pub struct Repl {
+    /// Repeated on an empty line
+    last: String,
+    /// The machine before the first command, for `reset`
+    start: Option<Snapshot>,
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+
+    /// Read commands from stdin until `quit` or the end of input
+    pub fn run(&mut self, cpu: &mut Chip8Interpreter) -> io::Result<()> {
+        if cpu.history().is_none() {
+            cpu.enable_history(HISTORY_LENGTH);
+        }
+        let stdin = io::stdin();
+        let mut stdout = io::stdout();
+        print!("{}", cpu);
+        loop {
+            write!(stdout, "(chip8) ")?;
+            stdout.flush()?;
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line)? == 0 {
+                println!();
+                return Ok(());
+            }
+            if matches!(line.trim(), "quit" | "q") {
+                return Ok(());
+            }
+            match self.execute(cpu, &line) {
+                Ok(text) => print!("{}", text),
+                Err(err) => println!("Err: {}", err),
+            }
+        }
+    }
+
+    /// Run one command line and return what it prints
+    pub fn execute(&mut self, cpu: &mut Chip8Interpreter, line: &str) -> Result<String, String> {
+        let line = match line.trim() {
+            "" => self.last.clone(),
+            line => line.to_string(),
+        };
+        self.last.clone_from(&line);
+        let start = self.start.get_or_insert_with(|| cpu.snapshot());
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] => Ok(String::new()),
+            ["help" | "h"] => Ok(HELP.to_string()),
+            ["step" | "s"] => step(cpu, 1),
+            ["step" | "s", count] => step(cpu, count.parse().map_err(|_| "step expects a count")?),
+            ["continue" | "c"] => resume(cpu),
+            ["regs" | "r"] => Ok(cpu.to_string()),
+            ["mem" | "m", addr] => memory(cpu, addr, DEFAULT_MEM_LENGTH),
+            ["mem" | "m", addr, length] => memory(cpu, addr, parse_number(length)?),
+            ["disasm" | "d"] => Ok(disassemble(cpu, cpu.pc(), DEFAULT_DISASM_COUNT)),
+            ["disasm" | "d", addr] => {
+                Ok(disassemble(cpu, address(cpu, addr)?, DEFAULT_DISASM_COUNT))
+            }
+            ["disasm" | "d", addr, count] => {
+                Ok(disassemble(cpu, address(cpu, addr)?, parse_number(count)?))
+            }
+            ["history"] => history(cpu, DEFAULT_HISTORY_COUNT),
+            ["history", count] => history(cpu, parse_number(count)?),
+            ["break" | "b"] => Ok(cpu
+                .debugger()
+                .breakpoints()
+                .map(|addr| format!("breakpoint at {}\n", describe(cpu, addr)))
+                .collect()),
+            ["break" | "b", text] => {
+                let addr = address(cpu, text)?;
+                match cpu.debugger_mut().set_breakpoint(addr) {
+                    true => Ok(String::new()),
+                    false => Err(format!("there already is a breakpoint at {}", text)),
+                }
+            }
+            ["delete", text] => {
+                let addr = address(cpu, text)?;
+                match cpu.debugger_mut().clear_breakpoint(addr) {
+                    true => Ok(String::new()),
+                    false => Err(format!("no breakpoint at {}", text)),
+                }
+            }
+            ["watch" | "w"] => Ok(cpu
+                .debugger()
+                .watchpoints()
+                .iter()
+                .map(|watchpoint| format!("watching {:?}\n", watchpoint))
+                .collect()),
+            ["watch" | "w", spec] => {
+                let watchpoint: Watchpoint = spec.parse()?;
+                cpu.debugger_mut().add_watchpoint(watchpoint);
+                Ok(String::new())
+            }
+            ["unwatch", spec] => match cpu.debugger_mut().remove_watchpoint(spec.parse()?) {
+                true => Ok(String::new()),
+                false => Err(format!("not watching {}", spec)),
+            },
+            ["patch", addr, ref opcodes @ ..] => {
+                let bytes = parse_snippet(&opcodes.join(" "))?;
+                let addr = address(cpu, addr)?;
+                cpu.patch(addr, &bytes)?;
+                Ok(disassemble(cpu, addr, bytes.len() / 2))
+            }
+            ["poke", addr, ref bytes @ ..] => {
+                let bytes = hexview::parse_bytes(&bytes.join(" "))?;
+                let start = address(cpu, addr)?;
+                hexview::poke(cpu, start, &bytes)?;
+                memory(cpu, addr, bytes.len())
+            }
+            ["undo"] => match cpu.undo_patch() {
+                Some(patch) => Ok(format!(
+                    "reverted {} bytes at {:#05x}\n",
+                    patch.original.len(),
+                    patch.addr
+                )),
+                None => Err("no patch to undo".to_string()),
+            },
+            ["symbols"] => Ok(cpu.symbols().to_string()),
+            ["reset"] => {
+                cpu.restore_snapshot(start);
+                Ok(cpu.to_string())
+            }
+            _ => Err(format!("unknown command '{}', try help", line)),
+        }
+    }
+}
+
+/// An address in the bank that is mapped in, in hex or by name
+fn address(cpu: &Chip8Interpreter, text: &str) -> Result<u16, String> {
+    cpu.symbols()
+        .resolve(text)
+        .ok_or_else(|| format!("invalid address '{}'", text))
+}
+
+/// "0x204", or "0x204 <main_loop+2>" when there is a symbol at or below it
+fn describe(cpu: &Chip8Interpreter, addr: u16) -> String {
+    match cpu.symbols().label(addr) {
+        Some(label) => format!("{:#05x} <{}>", addr, label),
+        None => format!("{:#05x}", addr),
+    }
+}
+
+/// "ADDR" for the mapped in bank, or "BANK:ADDR" with the bank in decimal
+fn banked_address(cpu: &Chip8Interpreter, text: &str) -> Result<(usize, u16), String> {
+    match text.split_once(':') {
+        Some((bank, addr)) => {
+            let bank = bank
+                .parse()
+                .ok()
+                .filter(|&bank| bank < cpu.bank_count())
+                .ok_or_else(|| format!("no memory bank '{}'", bank))?;
+            Ok((bank, address(cpu, addr)?))
+        }
+        None => Ok((cpu.bank(), address(cpu, text)?)),
+    }
+}
+
+/// A count in decimal, or hex with 0x
+fn parse_number(text: &str) -> Result<usize, String> {
+    match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+    .ok_or_else(|| format!("invalid number '{}'", text))
+}
+
+/// Step over a pause, then run `count` instructions
+fn step(cpu: &mut Chip8Interpreter, count: u64) -> Result<String, String> {
+    cpu.resume();
+    let mut text = String::new();
+    for _ in 0..count {
+        if cpu.halted() {
+            break;
+        }
+        text = format!("{}\n", cpu.explain());
+        cpu.step().map_err(|err| fault(cpu, err))?;
+        if cpu.paused().is_some() {
+            break;
+        }
+    }
+    Ok(text + &stopped(cpu))
+}
+
+/// Run frames until something stops the machine, or `CONTINUE_FRAMES` pass
+fn resume(cpu: &mut Chip8Interpreter) -> Result<String, String> {
+    cpu.resume();
+    for _ in 0..CONTINUE_FRAMES {
+        if cpu.halted() || cpu.paused().is_some() || cpu.is_spinning() {
+            return Ok(stopped(cpu));
+        }
+        cpu.run_frame().map_err(|err| fault(cpu, err))?;
+    }
+    Ok(format!(
+        "Still running after {} frames\n{}",
+        CONTINUE_FRAMES, cpu
+    ))
+}
+
+/// Why the machine is where it is, and its state
+fn stopped(cpu: &Chip8Interpreter) -> String {
+    let reason = if let Some(reason) = cpu.paused() {
+        format!("Paused on {}\n", reason)
+    } else if cpu.halted() {
+        "The program has ended\n".to_string()
+    } else if cpu.is_spinning() {
+        "The program is spinning on a jump to itself\n".to_string()
+    } else {
+        String::new()
+    };
+    format!("{}{}", reason, cpu)
+}
+
+fn fault(cpu: &Chip8Interpreter, err: Chip8Error) -> String {
+    let history = history(cpu, DEFAULT_HISTORY_COUNT).unwrap_or_default();
+    format!("{}\n{}{}", err, cpu, history)
+}
+
+fn history(cpu: &Chip8Interpreter, count: usize) -> Result<String, String> {
+    let history = cpu.history().ok_or("the machine keeps no history")?;
+    let lines: String = history
+        .last(count)
+        .map(|entry| format!("{}\n", entry))
+        .collect();
+    Ok(format!("last instructions:\n{}", lines))
+}
+
+/// Hex and ASCII dump starting at `addr`
+pub(crate) fn memory(cpu: &Chip8Interpreter, addr: &str, length: usize) -> Result<String, String> {
+    let (bank, start) = banked_address(cpu, addr)?;
+    let mem = cpu
+        .bank_memory(bank)
+        .ok_or_else(|| format!("no memory bank {}", bank))?;
+    Ok(hexview::dump(mem, start as usize, length))
+}
+
+/// `count` instructions from `addr`, marking PC with > and breakpoints
+/// with *, each named address preceded by a "name:" line
+pub(crate) fn disassemble(cpu: &Chip8Interpreter, addr: u16, count: usize) -> String {
+    let mem = cpu.memory();
+    let breakpoints: Vec<u16> = cpu.debugger().breakpoints().collect();
+    (addr as usize..mem.len() - 1)
+        .step_by(2)
+        .take(count)
+        .map(|addr| {
+            let opcode = u16::from_be_bytes([mem[addr], mem[addr + 1]]);
+            let pc = if addr == cpu.pc() as usize { '>' } else { ' ' };
+            let stop = if breakpoints.contains(&(addr as u16)) {
+                '*'
+            } else {
+                ' '
+            };
+            let label = match cpu.symbols().name(addr as u16) {
+                Some(name) => format!("{}:\n", name),
+                None => String::new(),
+            };
+            format!(
+                "{}{}{} {:#05x}  {:04X}  {}\n",
+                label,
+                pc,
+                stop,
+                addr,
+                opcode,
+                instruction::mnemonic(opcode)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu() -> Chip8Interpreter {
+        // V0 = 1; V1 = 2; I = 300; store V0..V1; jump to self
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        cpu.load_rom_bytes(&[0x60, 0x01, 0x61, 0x02, 0xA3, 0x00, 0xF1, 0x55, 0x12, 0x08])
+            .unwrap();
+        cpu
+    }
+
+    #[test]
+    fn test_step_and_break() {
+        let mut cpu = cpu();
+        let mut repl = Repl::new();
+        let text = repl.execute(&mut cpu, "step").unwrap();
+        assert!(text.starts_with("6XNN: set V0 = 1"), "{}", text);
+        assert_eq!(cpu.pc(), 0x202);
+        // An empty line repeats the step
+        repl.execute(&mut cpu, "").unwrap();
+        assert_eq!(cpu.v(1), 2);
+        repl.execute(&mut cpu, "break 0x206").unwrap();
+        assert!(repl.execute(&mut cpu, "break 206").is_err());
+        let text = repl.execute(&mut cpu, "continue").unwrap();
+        assert!(
+            text.starts_with("Paused on breakpoint at 0x206"),
+            "{}",
+            text
+        );
+        repl.execute(&mut cpu, "delete 206").unwrap();
+        let text = repl.execute(&mut cpu, "c").unwrap();
+        assert!(text.starts_with("The program is spinning"), "{}", text);
+        assert_eq!(
+            repl.execute(&mut cpu, "mem 300 2").unwrap(),
+            hexview::dump(cpu.memory(), 0x300, 2)
+        );
+        assert!(repl
+            .execute(&mut cpu, "mem 300 2")
+            .unwrap()
+            .starts_with("0x300  01 02 "));
+        repl.execute(&mut cpu, "poke 300 41 42").unwrap();
+        assert!(repl
+            .execute(&mut cpu, "mem 300 2")
+            .unwrap()
+            .ends_with("|AB|\n"));
+        repl.execute(&mut cpu, "undo").unwrap();
+        assert_eq!(cpu.memory()[0x300], 1);
+        assert!(repl.execute(&mut cpu, "poke 300").is_err());
+        assert!(repl.execute(&mut cpu, "history").is_err());
+        cpu.enable_history(8);
+        repl.execute(&mut cpu, "reset").unwrap();
+        repl.execute(&mut cpu, "step 2").unwrap();
+        let text = repl.execute(&mut cpu, "history 1").unwrap();
+        assert!(
+            text.ends_with("0x202  6102  LD V1, 0x02  V1 00->02\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn test_disasm_and_patch() {
+        let mut cpu = cpu();
+        let mut repl = Repl::new();
+        repl.execute(&mut cpu, "b 202").unwrap();
+        assert_eq!(
+            repl.execute(&mut cpu, "disasm 200 2").unwrap(),
+            ">  0x200  6001  LD V0, 0x01\n * 0x202  6102  LD V1, 0x02\n"
+        );
+        assert_eq!(
+            repl.execute(&mut cpu, "patch 200 6005").unwrap(),
+            ">  0x200  6005  LD V0, 0x05\n"
+        );
+        repl.execute(&mut cpu, "s").unwrap();
+        assert_eq!(cpu.v(0), 5);
+        assert!(repl.execute(&mut cpu, "undo").is_ok());
+        assert!(repl.execute(&mut cpu, "undo").is_err());
+        assert!(repl.execute(&mut cpu, "patch 200 600").is_err());
+        assert!(repl.execute(&mut cpu, "patch 200").is_err());
+    }
+
+    #[test]
+    fn test_symbols() {
+        let mut cpu = cpu();
+        cpu.set_symbols("200 start\n208 spin\n".parse().unwrap());
+        let mut repl = Repl::new();
+        repl.execute(&mut cpu, "break spin").unwrap();
+        assert_eq!(
+            repl.execute(&mut cpu, "break").unwrap(),
+            "breakpoint at 0x208 <spin>\n"
+        );
+        assert_eq!(
+            repl.execute(&mut cpu, "disasm start 2").unwrap(),
+            "start:\n>  0x200  6001  LD V0, 0x01\n   0x202  6102  LD V1, 0x02\n"
+        );
+        repl.execute(&mut cpu, "continue").unwrap();
+        assert_eq!(cpu.pc(), 0x208);
+        repl.execute(&mut cpu, "delete spin").unwrap();
+        assert!(repl.execute(&mut cpu, "break nowhere").is_err());
+        assert_eq!(
+            repl.execute(&mut cpu, "symbols").unwrap(),
+            "0x200 start\n0x208 spin\n"
+        );
+    }
+
+    #[test]
+    fn test_banked_memory() {
+        let mut cpu = cpu();
+        cpu.set_bank_count(2);
+        let mut rom = vec![0; 0xE00];
+        rom.extend_from_slice(&[0xAB, 0xCD]);
+        cpu.load_rom_bytes(&rom).unwrap();
+        let mut repl = Repl::new();
+        let text = repl.execute(&mut cpu, "mem 1:200 2").unwrap();
+        assert!(text.starts_with("0x200  ab cd "), "{}", text);
+        let text = repl.execute(&mut cpu, "mem 0:200 2").unwrap();
+        assert!(text.starts_with("0x200  00 00 "), "{}", text);
+        assert!(repl.execute(&mut cpu, "mem 2:200").is_err());
+        assert!(repl.execute(&mut cpu, "frobnicate").is_err());
+    }
+}
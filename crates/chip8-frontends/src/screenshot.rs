@@ -0,0 +1,175 @@
+use chip8_core::{Frame, FrameBuffer, Heatmap, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Size of a CHIP-8 pixel in screenshots
+const SCREENSHOT_SCALE: usize = 8;
+const PIXEL_ON: u8 = 0xFF;
+const PIXEL_OFF: u8 = 0x00;
+/// Addresses per row of a heatmap picture, 64 rows cover the 4K of memory
+const HEATMAP_COLUMNS: usize = 64;
+const HEATMAP_ROWS: usize = 64;
+/// Size of an address in heatmap pictures
+const HEATMAP_SCALE: usize = 4;
+
+/// Save the display as a black and white PNG
+pub fn save_png(frame_buffer: &FrameBuffer, path: &Path) -> io::Result<()> {
+    let width = FRAME_BUFFER_WIDTH * SCREENSHOT_SCALE;
+    let height = FRAME_BUFFER_HEIGHT * SCREENSHOT_SCALE;
+    let mut data = Vec::with_capacity(width * height);
+    for row in frame_buffer.rows() {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&pixel| {
+                let value = if pixel == 1 { PIXEL_ON } else { PIXEL_OFF };
+                std::iter::repeat_n(value, SCREENSHOT_SCALE)
+            })
+            .collect();
+        for _ in 0..SCREENSHOT_SCALE {
+            data.extend_from_slice(&line);
+        }
+    }
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(to_io_error)?;
+    writer.write_image_data(&data).map_err(to_io_error)
+}
+
+/// Save the memory heatmap as a PNG with a pixel per address, from the top
+/// left: red for writes, green for executes and blue for reads, brighter
+/// the more often, on a log scale
+pub fn save_heatmap_png(heatmap: &Heatmap, path: &Path) -> io::Result<()> {
+    let width = HEATMAP_COLUMNS * HEATMAP_SCALE;
+    let height = HEATMAP_ROWS * HEATMAP_SCALE;
+    let scale = (heatmap.max() as f64).ln_1p().max(1.);
+    let level = |count: u64| ((count as f64).ln_1p() / scale * 255.) as u8;
+    let mut data = Vec::with_capacity(width * height * 3);
+    for row in 0..HEATMAP_ROWS {
+        let line: Vec<u8> = (0..HEATMAP_COLUMNS)
+            .flat_map(|column| {
+                let addr = (row * HEATMAP_COLUMNS + column) as u16;
+                let rgb = [
+                    level(heatmap.writes(addr)),
+                    level(heatmap.executes(addr)),
+                    level(heatmap.reads(addr)),
+                ];
+                std::iter::repeat_n(rgb, HEATMAP_SCALE).flatten()
+            })
+            .collect();
+        for _ in 0..HEATMAP_SCALE {
+            data.extend_from_slice(&line);
+        }
+    }
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(to_io_error)?;
+    writer.write_image_data(&data).map_err(to_io_error)
+}
+
+fn to_io_error(err: png::EncodingError) -> io::Error {
+    match err {
+        png::EncodingError::IoError(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+/// Writes successive frames as numbered PNGs, e.g. for illustrating how a
+/// ROM draws its screen step by step
+pub struct ScreenshotSequence {
+    dir: PathBuf,
+    changed_only: bool,
+    last_hash: Option<u64>,
+}
+
+impl ScreenshotSequence {
+    /// With `changed_only`, frames that look like the previous one are
+    /// skipped. The numbers in the file names stay frame numbers.
+    pub fn new(dir: &Path, changed_only: bool) -> io::Result<ScreenshotSequence> {
+        std::fs::create_dir_all(dir)?;
+        Ok(ScreenshotSequence {
+            dir: dir.to_path_buf(),
+            changed_only,
+            last_hash: None,
+        })
+    }
+
+    /// Returns the file written, if any
+    pub fn capture(&mut self, frame: &Frame) -> io::Result<Option<PathBuf>> {
+        let hash = frame.framebuffer.hash();
+        if self.changed_only && self.last_hash == Some(hash) {
+            return Ok(None);
+        }
+        self.last_hash = Some(hash);
+        let path = self.dir.join(format!("frame-{:05}.png", frame.index));
+        save_png(&frame.framebuffer, &path)?;
+        Ok(Some(path))
+    }
+}
+
+/// First screenshot-N.png in the current directory that does not exist yet
+pub fn next_screenshot_path() -> PathBuf {
+    (1..)
+        .map(|n| PathBuf::from(format!("screenshot-{}.png", n)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(index: u64, lit: bool) -> Frame {
+        let mut framebuffer = FrameBuffer::new();
+        framebuffer[0][0] = lit as u32;
+        Frame {
+            index,
+            framebuffer,
+            sound_active: false,
+        }
+    }
+
+    #[test]
+    fn test_changed_only() {
+        let dir = std::env::temp_dir().join(format!("chip8-shots-{}", std::process::id()));
+        let mut sequence = ScreenshotSequence::new(&dir, true).unwrap();
+        let written: Vec<bool> = [frame(0, false), frame(1, false), frame(2, true)]
+            .iter()
+            .map(|frame| sequence.capture(frame).unwrap().is_some())
+            .collect();
+        assert_eq!(written, [true, false, true]);
+        let png = std::fs::read(dir.join("frame-00002.png")).unwrap();
+        assert_eq!(png[..8], [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+        assert!(!dir.join("frame-00001.png").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_heatmap_png() {
+        let dir = std::env::temp_dir().join(format!("chip8-heatmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut cpu = chip8_core::Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        cpu.enable_heatmap();
+        // Jump to self
+        cpu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        cpu.step_n(3).unwrap();
+        let path = dir.join("heatmap.png");
+        save_heatmap_png(cpu.heatmap().unwrap(), &path).unwrap();
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (256, 256));
+        // 0x200 is row 8, column 0, executed the most
+        let pixel = (8 * HEATMAP_SCALE * 256) * 3;
+        assert_eq!(data[pixel..pixel + 3], [0, 255, 0]);
+        assert_eq!(data[..3], [0, 0, 0]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+use chip8_core::{FrameBuffer, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+use memmap2::MmapMut;
+use std::fs::OpenOptions;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{fence, Ordering};
+
+/// Identifies the file layout to readers
+const MAGIC: &[u8; 4] = b"C8FB";
+const VERSION: u32 = 1;
+const SEQUENCE_OFFSET: usize = 16;
+const HEADER_SIZE: usize = 24;
+const PIXEL_ON: u8 = 0xFF;
+
+/// The display, published into a memory-mapped file (e.g. under /dev/shm)
+/// for other processes such as OBS plugins to composite from directly.
+///
+/// Layout, little endian: "C8FB", version u32, width u32, height u32, a
+/// sequence u64, then one byte per pixel (0 or 255) row by row, ready to
+/// upload as a single-channel texture. The sequence is odd while a frame
+/// is being written and goes up by two per frame, so readers can copy the
+/// pixels and retry if the sequence changed or was odd.
+pub struct SharedFrame {
+    map: MmapMut,
+    sequence: u64,
+    last_hash: Option<u64>,
+}
+
+impl SharedFrame {
+    pub fn create(path: &Path) -> io::Result<SharedFrame> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len((HEADER_SIZE + FRAME_BUFFER_WIDTH * FRAME_BUFFER_HEIGHT) as u64)?;
+        // SAFETY: the file was just created and sized by us; other
+        // processes are only expected to read it.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[0..4].copy_from_slice(MAGIC);
+        map[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        map[8..12].copy_from_slice(&(FRAME_BUFFER_WIDTH as u32).to_le_bytes());
+        map[12..16].copy_from_slice(&(FRAME_BUFFER_HEIGHT as u32).to_le_bytes());
+        Ok(SharedFrame {
+            map,
+            sequence: 0,
+            last_hash: None,
+        })
+    }
+
+    /// Write `frame_buffer` into the mapping, unless it is unchanged since
+    /// the last call
+    pub fn publish(&mut self, frame_buffer: &FrameBuffer) {
+        let hash = frame_buffer.hash();
+        if self.last_hash == Some(hash) {
+            return;
+        }
+        self.last_hash = Some(hash);
+        self.write_sequence(self.sequence + 1);
+        fence(Ordering::Release);
+        let pixels = self.map[HEADER_SIZE..].chunks_mut(FRAME_BUFFER_WIDTH);
+        for (row, line) in frame_buffer.rows().zip(pixels) {
+            for (&pixel, byte) in row.iter().zip(line.iter_mut()) {
+                *byte = if pixel == 1 { PIXEL_ON } else { 0 };
+            }
+        }
+        fence(Ordering::Release);
+        self.sequence += 2;
+        self.write_sequence(self.sequence);
+    }
+
+    fn write_sequence(&mut self, sequence: u64) {
+        self.map[SEQUENCE_OFFSET..HEADER_SIZE].copy_from_slice(&sequence.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish() {
+        let path = std::env::temp_dir().join(format!("chip8-frame-{}", std::process::id()));
+        let mut shared = SharedFrame::create(&path).unwrap();
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer[1][2] = 1;
+        shared.publish(&frame_buffer);
+        shared.publish(&frame_buffer);
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], MAGIC);
+        assert_eq!(bytes[8], 64);
+        assert_eq!(bytes[12], 32);
+        assert_eq!(bytes[SEQUENCE_OFFSET], 2);
+        assert_eq!(bytes[HEADER_SIZE + FRAME_BUFFER_WIDTH + 2], PIXEL_ON);
+        assert_eq!(bytes.iter().filter(|&&byte| byte == PIXEL_ON).count(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
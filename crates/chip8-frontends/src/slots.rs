@@ -0,0 +1,188 @@
+use chip8_core::{Chip8Interpreter, MachineState, RPL_FLAG_COUNT};
+use std::convert::TryFrom;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Number of save slots, picked with the slot hotkeys
+pub const SLOT_COUNT: u8 = 10;
+
+/// Numbered save state files kept next to the ROM, e.g. `pong.3.c8state`
+/// for slot 3 of `pong.ch8`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveSlots {
+    /// ROM path without its extension, which slot files are named after
+    base: PathBuf,
+    current: u8,
+}
+
+impl SaveSlots {
+    /// Slots for the ROM opened from `spec`, as given on the command line.
+    /// ROMs from a URL get their slots in the working directory, ROMs in
+    /// an archive next to it, named after the archive and the entry.
+    pub fn for_rom(spec: &str) -> SaveSlots {
+        let local = match spec.split_once("://") {
+            Some((_, url)) => url.rsplit('/').next().unwrap_or(url),
+            None => spec,
+        };
+        let (path, entry) = match local.split_once('#') {
+            Some((archive, entry)) => (archive, Some(entry)),
+            None => (local, None),
+        };
+        let mut base = Path::new(path).with_extension("");
+        if let Some(entry) = entry {
+            let entry = Path::new(entry).with_extension("");
+            let name = format!(
+                "{}.{}",
+                base.file_name().unwrap_or_default().to_string_lossy(),
+                entry.file_name().unwrap_or_default().to_string_lossy()
+            );
+            base.set_file_name(name);
+        }
+        if base.file_name().is_none() {
+            base.push("quicksave");
+        }
+        SaveSlots { base, current: 0 }
+    }
+
+    pub fn current(&self) -> u8 {
+        self.current
+    }
+
+    /// Pick the next slot, or the previous one with `step` -1, wrapping
+    /// around
+    pub fn select(&mut self, step: i8) -> u8 {
+        self.current = (self.current as i8 + step).rem_euclid(SLOT_COUNT as i8) as u8;
+        self.current
+    }
+
+    pub fn path(&self, slot: u8) -> PathBuf {
+        let mut name = self.base.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.c8state", slot));
+        self.base.with_file_name(name)
+    }
+
+    /// Where the ROM's SCHIP RPL user flags are kept, e.g. `pong.rpl`
+    pub fn flags_path(&self) -> PathBuf {
+        let mut name = self.base.file_name().unwrap_or_default().to_os_string();
+        name.push(".rpl");
+        self.base.with_file_name(name)
+    }
+
+    /// Keep the RPL user flags in `flags_path`, like the battery-backed
+    /// memory of the HP48: they are read from it now if it exists, and
+    /// written back whenever FX75 stores them
+    pub fn keep_flags(&self, cpu: &mut Chip8Interpreter) -> Result<(), String> {
+        let path = self.flags_path();
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                let flags = <[u8; RPL_FLAG_COUNT]>::try_from(bytes).map_err(|_| {
+                    format!("{}: expected {} flag bytes", path.display(), RPL_FLAG_COUNT)
+                })?;
+                cpu.set_rpl_flags(flags);
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        }
+        cpu.on_rpl_store(Box::new(move |flags| {
+            if let Err(err) = std::fs::write(&path, flags) {
+                eprintln!("Err: cannot write {}: {}", path.display(), err);
+            }
+        }));
+        Ok(())
+    }
+
+    /// Write the machine state to the current slot
+    pub fn save(&self, cpu: &Chip8Interpreter) -> Result<PathBuf, String> {
+        let path = self.path(self.current);
+        cpu.state()
+            .save(&path.to_string_lossy())
+            .map_err(|err| format!("cannot write {}: {}", path.display(), err))?;
+        Ok(path)
+    }
+
+    /// Put the machine back into the state in the current slot
+    pub fn load(&self, cpu: &mut Chip8Interpreter) -> Result<PathBuf, String> {
+        let path = self.path(self.current);
+        if !path.exists() {
+            return Err(format!("slot {} is empty", self.current));
+        }
+        cpu.load_state(&MachineState::load(&path.to_string_lossy())?)?;
+        Ok(path)
+    }
+}
+
+impl Default for SaveSlots {
+    fn default() -> Self {
+        SaveSlots::for_rom("quicksave")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_paths() {
+        let slots = SaveSlots::for_rom("roms/pong.ch8");
+        assert_eq!(slots.path(3), Path::new("roms/pong.3.c8state"));
+        let slots = SaveSlots::for_rom("https://example.com/games/tetris.ch8");
+        assert_eq!(slots.path(0), Path::new("tetris.0.c8state"));
+        let slots = SaveSlots::for_rom("pack.zip#games/brix.ch8");
+        assert_eq!(slots.path(1), Path::new("pack.brix.1.c8state"));
+        assert_eq!(slots.flags_path(), Path::new("pack.brix.rpl"));
+        assert_eq!(
+            SaveSlots::default().path(9),
+            Path::new("quicksave.9.c8state")
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let mut slots = SaveSlots::default();
+        assert_eq!(slots.select(-1), SLOT_COUNT - 1);
+        assert_eq!(slots.select(1), 0);
+        assert_eq!(slots.select(1), 1);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("chip8-slots-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut slots = SaveSlots::for_rom(&dir.join("game.ch8").to_string_lossy());
+        slots.select(2);
+        let mut cpu = Chip8Interpreter::new();
+        assert!(slots.load(&mut cpu).is_err());
+        cpu.set_v(3, 0x42);
+        assert_eq!(slots.save(&cpu).unwrap(), dir.join("game.2.c8state"));
+        cpu.set_v(3, 0);
+        slots.load(&mut cpu).unwrap();
+        assert_eq!(cpu.v(3), 0x42);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_keep_flags() {
+        let dir = std::env::temp_dir().join(format!("chip8-rpl-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let slots = SaveSlots::for_rom(&dir.join("game.ch8").to_string_lossy());
+        // V0 = 1; V1 = 2; store V0..V1 in the flags
+        let mut cpu = Chip8Interpreter::new();
+        slots.keep_flags(&mut cpu).unwrap();
+        cpu.load_rom_bytes(&[0x60, 0x01, 0x61, 0x02, 0xF1, 0x75])
+            .unwrap();
+        cpu.step_n(3).unwrap();
+        assert_eq!(
+            std::fs::read(slots.flags_path()).unwrap(),
+            [1, 2, 0, 0, 0, 0, 0, 0]
+        );
+        // Load V0 from the flags in the next session
+        let mut next = Chip8Interpreter::new();
+        slots.keep_flags(&mut next).unwrap();
+        next.load_rom_bytes(&[0xF0, 0x85]).unwrap();
+        next.step().unwrap();
+        assert_eq!((next.v(0), next.v(1)), (1, 0));
+        std::fs::write(slots.flags_path(), [0; 3]).unwrap();
+        assert!(slots.keep_flags(&mut next).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,368 @@
+use crate::hexview::{HexEditor, ROW_BYTES};
+use crate::keypad::KEYPAD_LAYOUT;
+use crate::repl;
+use chip8_core::{Chip8Interpreter, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::Stylize;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::time::{Duration, Instant};
+
+/// One 60Hz frame
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+/// Terminal keys for the hex keypad, in the order of `KEYPAD_LAYOUT`
+const TERMINAL_KEYPAD: &str = "1234qwerasdfzxcv";
+/// Frames a keypad key stays down after a key press. Terminals don't
+/// report releases, holding a key down keeps it pressed through auto
+/// repeat.
+const KEY_HOLD_FRAMES: u8 = 6;
+const HELP: &str =
+    "space run/pause  enter step  tab frame  b breakpoint  F2 memory  F5 reset  esc quit";
+const MEMORY_HELP: &str = "arrows/pgup/pgdn move  0-9 a-f edit  F2/esc back to the code";
+
+/// Runs an interpreter in a full-screen terminal UI with the display,
+/// registers, stack and code around PC, stepped from the keyboard. Works
+/// over SSH, no window system needed.
+#[derive(Debug, Default)]
+pub struct TerminalFrontend {
+    running: bool,
+    /// Frames left before each keypad key is released
+    held: [u8; 16],
+    status: String,
+    /// Set while the code pane shows the memory editor instead
+    memory: Option<HexEditor>,
+    /// Rows of memory the code pane had room for when last drawn
+    memory_rows: usize,
+}
+
+impl TerminalFrontend {
+    pub fn new() -> TerminalFrontend {
+        TerminalFrontend::default()
+    }
+
+    /// Take over the terminal until esc is pressed, paused on the first
+    /// instruction
+    pub fn run(&mut self, cpu: &mut Chip8Interpreter) -> io::Result<()> {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal, cpu);
+        ratatui::restore();
+        result
+    }
+
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        cpu: &mut Chip8Interpreter,
+    ) -> io::Result<()> {
+        let mut next_frame = Instant::now();
+        loop {
+            terminal.draw(|frame| self.draw(frame, cpu))?;
+            let timeout = next_frame.saturating_duration_since(Instant::now());
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Release && !self.handle_key(cpu, key.code) {
+                        return Ok(());
+                    }
+                }
+                continue;
+            }
+            next_frame = Instant::now() + FRAME_TIME;
+            if self.running {
+                self.run_frame(cpu);
+            }
+        }
+    }
+
+    /// Returns false to quit
+    fn handle_key(&mut self, cpu: &mut Chip8Interpreter, code: KeyCode) -> bool {
+        if let Some(editor) = &mut self.memory {
+            let rows = self.memory_rows;
+            match code {
+                KeyCode::Esc | KeyCode::F(2) => self.memory = None,
+                KeyCode::Left => editor.move_by(cpu, -1, rows),
+                KeyCode::Right => editor.move_by(cpu, 1, rows),
+                KeyCode::Up => editor.move_by(cpu, -(ROW_BYTES as i32), rows),
+                KeyCode::Down => editor.move_by(cpu, ROW_BYTES as i32, rows),
+                KeyCode::PageUp => editor.move_by(cpu, -((rows * ROW_BYTES) as i32), rows),
+                KeyCode::PageDown => editor.move_by(cpu, (rows * ROW_BYTES) as i32, rows),
+                KeyCode::Char(c) => {
+                    if let Some(digit) = c.to_digit(16) {
+                        editor.type_digit(cpu, digit as u8, rows);
+                    }
+                }
+                _ => {}
+            }
+            return true;
+        }
+        match code {
+            KeyCode::Esc => return false,
+            KeyCode::Char(' ') => {
+                self.running = !self.running;
+                if self.running {
+                    cpu.resume();
+                    self.status = "Running".to_string();
+                } else {
+                    self.status = "Paused".to_string();
+                }
+            }
+            KeyCode::Enter => {
+                self.running = false;
+                cpu.resume();
+                let explanation = cpu.explain();
+                self.status = match cpu.step() {
+                    Ok(Some(_)) => explanation,
+                    Ok(None) => "The program has ended".to_string(),
+                    Err(err) => format!("Fault: {}", err),
+                };
+            }
+            KeyCode::Tab => {
+                self.running = false;
+                cpu.resume();
+                self.run_frame(cpu);
+            }
+            KeyCode::Char('b') => {
+                let pc = cpu.pc();
+                let debugger = cpu.debugger_mut();
+                if !debugger.clear_breakpoint(pc) {
+                    debugger.set_breakpoint(pc);
+                }
+            }
+            KeyCode::F(2) => {
+                self.memory = Some(HexEditor::new(cpu.i()));
+            }
+            KeyCode::F(5) => {
+                cpu.reset();
+                self.status = "Reset".to_string();
+            }
+            KeyCode::Char(c) => {
+                if let Some(index) = TERMINAL_KEYPAD.find(c.to_ascii_lowercase()) {
+                    let key = KEYPAD_LAYOUT[index].1;
+                    self.held[key as usize] = KEY_HOLD_FRAMES;
+                    cpu.set_key(key, true);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Run a frame, stopping on a pause, a fault or the end of the program
+    fn run_frame(&mut self, cpu: &mut Chip8Interpreter) {
+        if let Err(err) = cpu.run_frame() {
+            self.running = false;
+            self.status = format!("Fault: {}", err);
+        } else if let Some(reason) = cpu.paused() {
+            self.running = false;
+            self.status = format!("Paused on {}", reason);
+        } else if cpu.halted() {
+            self.running = false;
+            self.status = "The program has ended".to_string();
+        }
+        for (key, frames) in self.held.iter_mut().enumerate() {
+            if *frames > 0 {
+                *frames -= 1;
+                if *frames == 0 {
+                    cpu.set_key(key as u8, false);
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame, cpu: &Chip8Interpreter) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [top, bottom] = Layout::vertical([
+            Constraint::Length(FRAME_BUFFER_HEIGHT as u16 / 2 + 2),
+            Constraint::Min(0),
+        ])
+        .areas(main);
+        let left = Constraint::Length(FRAME_BUFFER_WIDTH as u16 + 2);
+        let [display, registers] = Layout::horizontal([left, Constraint::Min(0)]).areas(top);
+        let [code, stack] = Layout::horizontal([left, Constraint::Min(0)]).areas(bottom);
+
+        frame.render_widget(
+            Paragraph::new(display_text(cpu)).block(Block::bordered().title("Display")),
+            display,
+        );
+        frame.render_widget(
+            Paragraph::new(registers_text(cpu)).block(Block::bordered().title("Registers")),
+            registers,
+        );
+        let lines = code.height.saturating_sub(2) as usize;
+        if let Some(editor) = &self.memory {
+            self.memory_rows = lines;
+            frame.render_widget(
+                Paragraph::new(memory_lines(editor, cpu, lines))
+                    .block(Block::bordered().title("Memory")),
+                code,
+            );
+        } else {
+            // A third of the listing before PC, the rest after
+            let first = cpu.pc().saturating_sub(lines as u16 / 3 * 2);
+            frame.render_widget(
+                Paragraph::new(repl::disassemble(cpu, first, lines))
+                    .block(Block::bordered().title("Code")),
+                code,
+            );
+        }
+        let stack_text: String = cpu
+            .stack()
+            .iter()
+            .rev()
+            .map(|addr| format!("{:#05x}\n", addr))
+            .collect();
+        frame.render_widget(
+            Paragraph::new(stack_text).block(Block::bordered().title("Stack")),
+            stack,
+        );
+        let status_text = match self.status.as_str() {
+            _ if self.memory.is_some() => MEMORY_HELP,
+            "" => HELP,
+            status => status,
+        };
+        frame.render_widget(Paragraph::new(status_text), status);
+    }
+}
+
+/// The editor's dump with the byte under the cursor highlighted
+fn memory_lines(editor: &HexEditor, cpu: &Chip8Interpreter, rows: usize) -> Vec<Line<'static>> {
+    let (cursor_row, column) = editor.cursor_position();
+    editor
+        .lines(cpu, rows)
+        .into_iter()
+        .enumerate()
+        .map(|(row, text)| {
+            if row != cursor_row {
+                return Line::from(text);
+            }
+            Line::from(vec![
+                Span::raw(text[..column].to_string()),
+                Span::raw(text[column..column + 2].to_string()).reversed(),
+                Span::raw(text[column + 2..].to_string()),
+            ])
+        })
+        .collect()
+}
+
+/// Two display rows per line of half blocks
+fn display_text(cpu: &Chip8Interpreter) -> String {
+    let rows: Vec<_> = cpu.frame_buffer().rows().collect();
+    let mut text = String::new();
+    for pair in rows.chunks(2) {
+        for x in 0..FRAME_BUFFER_WIDTH {
+            let lit = |row: Option<&&[u32; FRAME_BUFFER_WIDTH]>| row.is_some_and(|row| row[x] != 0);
+            text.push(match (lit(pair.first()), lit(pair.get(1))) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn registers_text(cpu: &Chip8Interpreter) -> String {
+    let mut text = format!(
+        "PC {:#05x}  I {:#05x}\nDT {:>3}    ST {:>3}\ncycle {}\n\n",
+        cpu.pc(),
+        cpu.i(),
+        cpu.delay_timer(),
+        cpu.sound_timer(),
+        cpu.cycles()
+    );
+    for x in 0..8 {
+        text += &format!(
+            "V{:X} {:02x}    V{:X} {:02x}\n",
+            x,
+            cpu.v(x),
+            x + 8,
+            cpu.v(x + 8)
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn test_draw() {
+        // V0 = 1; draw the font's 0 at V0,V0
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        cpu.load_rom_bytes(&[0x60, 0x01, 0xD0, 0x05]).unwrap();
+        let mut tui = TerminalFrontend::new();
+        tui.handle_key(&mut cpu, KeyCode::Enter);
+        tui.handle_key(&mut cpu, KeyCode::Enter);
+        let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+        terminal.draw(|frame| tui.draw(frame, &cpu)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("PC 0x204"), "{}", screen);
+        assert!(screen.contains("V0 01"));
+        assert!(screen.contains(">  0x204"));
+        assert!(screen.contains('▄'));
+        assert!(screen.contains("draw 5-byte sprite at (V0=1, V0=1)"));
+    }
+
+    #[test]
+    fn test_keypad_keys_are_released() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        // Jump to self
+        cpu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        let mut tui = TerminalFrontend::new();
+        tui.handle_key(&mut cpu, KeyCode::Char('v'));
+        assert!(cpu.keypad().is_pressed(0xF));
+        for _ in 0..KEY_HOLD_FRAMES {
+            tui.handle_key(&mut cpu, KeyCode::Tab);
+        }
+        assert!(!cpu.keypad().is_pressed(0xF));
+        assert!(tui.handle_key(&mut cpu, KeyCode::Char('b')));
+        assert_eq!(cpu.debugger().breakpoints().next(), Some(0x200));
+        assert!(!tui.handle_key(&mut cpu, KeyCode::Esc));
+    }
+
+    #[test]
+    fn test_memory_editor() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        // I = 300
+        cpu.load_rom_bytes(&[0xA3, 0x00]).unwrap();
+        cpu.step().unwrap();
+        let mut tui = TerminalFrontend::new();
+        tui.handle_key(&mut cpu, KeyCode::F(2));
+        // Keypad keys are hex digits while editing
+        for c in "c0ffee".chars() {
+            tui.handle_key(&mut cpu, KeyCode::Char(c));
+        }
+        assert_eq!(cpu.memory()[0x300..0x303], [0xC0, 0xFF, 0xEE]);
+        assert!(!cpu.keypad().is_pressed(0xC));
+        let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+        terminal.draw(|frame| tui.draw(frame, &cpu)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("0x300  c0 ff ee"), "{}", screen);
+        // Esc leaves the editor rather than quitting
+        assert!(tui.handle_key(&mut cpu, KeyCode::Esc));
+        assert!(tui.memory.is_none());
+    }
+}
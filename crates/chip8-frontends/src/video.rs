@@ -0,0 +1,347 @@
+use chip8_core::plugin::Renderer;
+use chip8_core::{FrameBuffer, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+
+pub const PIXEL_ON: u32 = 0xFFFFFF;
+pub const PIXEL_OFF: u32 = 0x000000;
+/// The glow is computed on a coarse grid of 8x8 pixel blocks, which is
+/// what blurs it
+const GLOW_BLOCK: usize = 8;
+const GLOW_BRIGHTNESS: f32 = 0.4;
+/// Color the display is blended towards while the buzzer sounds
+const FLASH_TINT: u32 = 0xFF8000;
+
+/// How the 64x32 display is scaled up to the window size
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScaleFilter {
+    /// Crisp integer scaling, every CHIP-8 pixel becomes an NxN block
+    Nearest,
+    /// Bilinear filtering, softer edges at large window sizes
+    Smooth,
+}
+
+impl ScaleFilter {
+    pub fn from_name(name: &str) -> Option<ScaleFilter> {
+        match name {
+            "nearest" => Some(ScaleFilter::Nearest),
+            "smooth" => Some(ScaleFilter::Smooth),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScaleFilter::Nearest => "nearest",
+            ScaleFilter::Smooth => "smooth",
+        }
+    }
+}
+
+/// How the display shows that the buzzer is sounding, for players who
+/// can't hear it
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum SoundFlash {
+    #[default]
+    Off,
+    /// Swap light and dark
+    Invert,
+    /// Blend the whole display towards amber
+    Tint,
+}
+
+impl SoundFlash {
+    pub fn from_name(name: &str) -> Option<SoundFlash> {
+        match name {
+            "off" => Some(SoundFlash::Off),
+            "invert" => Some(SoundFlash::Invert),
+            "tint" => Some(SoundFlash::Tint),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SoundFlash::Off => "off",
+            SoundFlash::Invert => "invert",
+            SoundFlash::Tint => "tint",
+        }
+    }
+
+    /// Apply the flash to a rendered frame
+    pub fn apply(self, buffer: &mut [u32]) {
+        for pixel in buffer.iter_mut() {
+            *pixel = match self {
+                SoundFlash::Off => *pixel,
+                SoundFlash::Invert => !*pixel & 0xFFFFFF,
+                SoundFlash::Tint => blend(*pixel, FLASH_TINT),
+            };
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VideoOptions {
+    pub filter: ScaleFilter,
+    /// Fill the letterbox bars with a dim, blurred extension of the display
+    /// instead of black
+    pub glow: bool,
+    /// Colors of lit and unlit pixels, as 0xRRGGBB
+    pub foreground: u32,
+    pub background: u32,
+    pub sound_flash: SoundFlash,
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        VideoOptions {
+            filter: ScaleFilter::Nearest,
+            glow: false,
+            foreground: PIXEL_ON,
+            background: PIXEL_OFF,
+            sound_flash: SoundFlash::Off,
+        }
+    }
+}
+
+/// Parse a "RRGGBB" color
+pub fn parse_color(text: &str) -> Option<u32> {
+    let hex = text.trim_start_matches('#');
+    u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)
+}
+
+impl Renderer for VideoOptions {
+    fn render(&mut self, frame_buffer: &FrameBuffer, width: usize, height: usize) -> Vec<u32> {
+        render(frame_buffer, width, height, self)
+    }
+}
+
+/// Render the frame buffer into an `out_width` x `out_height` window buffer,
+/// keeping the 2:1 aspect ratio and centering the display
+pub fn render(
+    frame_buffer: &FrameBuffer,
+    out_width: usize,
+    out_height: usize,
+    options: &VideoOptions,
+) -> Vec<u32> {
+    let filter = options.filter;
+    let mut out = vec![PIXEL_OFF; out_width * out_height];
+    let (area_width, area_height) = match filter {
+        ScaleFilter::Nearest => {
+            let factor = (out_width / FRAME_BUFFER_WIDTH)
+                .min(out_height / FRAME_BUFFER_HEIGHT)
+                .max(1);
+            (FRAME_BUFFER_WIDTH * factor, FRAME_BUFFER_HEIGHT * factor)
+        }
+        ScaleFilter::Smooth => {
+            let width = out_width.min(out_height * 2);
+            (width, width / 2)
+        }
+    };
+    let area_width = area_width.min(out_width);
+    let area_height = area_height.min(out_height);
+    let left = (out_width - area_width) / 2;
+    let top = (out_height - area_height) / 2;
+    if options.glow && (area_width < out_width || area_height < out_height) {
+        let blocks = glow_blocks(frame_buffer);
+        for y in 0..out_height {
+            for x in 0..out_width {
+                // Project the position onto the display area and sample there
+                let src_x = (x.clamp(left, left + area_width - 1) - left) as f32
+                    * FRAME_BUFFER_WIDTH as f32
+                    / area_width as f32;
+                let src_y = (y.clamp(top, top + area_height - 1) - top) as f32
+                    * FRAME_BUFFER_HEIGHT as f32
+                    / area_height as f32;
+                out[y * out_width + x] = glow(&blocks, src_x, src_y, options.foreground);
+            }
+        }
+    }
+    for y in 0..area_height {
+        for x in 0..area_width {
+            out[(top + y) * out_width + left + x] = match filter {
+                ScaleFilter::Nearest => {
+                    let src_x = x * FRAME_BUFFER_WIDTH / area_width;
+                    let src_y = y * FRAME_BUFFER_HEIGHT / area_height;
+                    color(frame_buffer[src_y][src_x], options)
+                }
+                ScaleFilter::Smooth => {
+                    bilinear(frame_buffer, x, y, area_width, area_height, options)
+                }
+            };
+        }
+    }
+    out
+}
+
+type GlowBlocks = [[f32; FRAME_BUFFER_WIDTH / GLOW_BLOCK]; FRAME_BUFFER_HEIGHT / GLOW_BLOCK];
+
+/// Average brightness of each block of the display
+fn glow_blocks(frame_buffer: &FrameBuffer) -> GlowBlocks {
+    let mut blocks = [[0.; FRAME_BUFFER_WIDTH / GLOW_BLOCK]; FRAME_BUFFER_HEIGHT / GLOW_BLOCK];
+    for (y, row) in frame_buffer.rows().enumerate() {
+        for (x, &pixel) in row.iter().enumerate() {
+            if pixel == 1 {
+                blocks[y / GLOW_BLOCK][x / GLOW_BLOCK] += 1. / (GLOW_BLOCK * GLOW_BLOCK) as f32;
+            }
+        }
+    }
+    blocks
+}
+
+/// Bilinearly sample the block grid at a frame buffer position
+fn glow(blocks: &GlowBlocks, src_x: f32, src_y: f32, foreground: u32) -> u32 {
+    let columns = FRAME_BUFFER_WIDTH / GLOW_BLOCK;
+    let rows = FRAME_BUFFER_HEIGHT / GLOW_BLOCK;
+    let bx = (src_x / GLOW_BLOCK as f32 - 0.5).clamp(0., (columns - 1) as f32);
+    let by = (src_y / GLOW_BLOCK as f32 - 0.5).clamp(0., (rows - 1) as f32);
+    let (x0, y0) = (bx as usize, by as usize);
+    let (x1, y1) = ((x0 + 1).min(columns - 1), (y0 + 1).min(rows - 1));
+    let (fx, fy) = (bx - x0 as f32, by - y0 as f32);
+    let top = blocks[y0][x0] * (1. - fx) + blocks[y0][x1] * fx;
+    let bottom = blocks[y1][x0] * (1. - fx) + blocks[y1][x1] * fx;
+    let brightness = (top * (1. - fy) + bottom * fy) * GLOW_BRIGHTNESS;
+    let mut rgb = 0;
+    for shift in [0, 8, 16] {
+        let channel = ((foreground >> shift) & 0xFF) as f32 * brightness;
+        rgb |= (channel.round() as u32).min(0xFF) << shift;
+    }
+    rgb
+}
+
+fn color(pixel: u32, options: &VideoOptions) -> u32 {
+    if pixel == 1 {
+        options.foreground
+    } else {
+        options.background
+    }
+}
+
+/// Sample the frame buffer between pixel centers and blend the four neighbours
+fn bilinear(
+    frame_buffer: &FrameBuffer,
+    x: usize,
+    y: usize,
+    area_width: usize,
+    area_height: usize,
+    options: &VideoOptions,
+) -> u32 {
+    let src_x = ((x as f32 + 0.5) * FRAME_BUFFER_WIDTH as f32 / area_width as f32 - 0.5)
+        .clamp(0., (FRAME_BUFFER_WIDTH - 1) as f32);
+    let src_y = ((y as f32 + 0.5) * FRAME_BUFFER_HEIGHT as f32 / area_height as f32 - 0.5)
+        .clamp(0., (FRAME_BUFFER_HEIGHT - 1) as f32);
+    let (x0, y0) = (src_x as usize, src_y as usize);
+    let x1 = (x0 + 1).min(FRAME_BUFFER_WIDTH - 1);
+    let y1 = (y0 + 1).min(FRAME_BUFFER_HEIGHT - 1);
+    let (fx, fy) = (src_x - x0 as f32, src_y - y0 as f32);
+    let mut rgb = 0;
+    for shift in [0, 8, 16] {
+        let channel = |px: u32| ((color(px, options) >> shift) & 0xFF) as f32;
+        let top = channel(frame_buffer[y0][x0]) * (1. - fx) + channel(frame_buffer[y0][x1]) * fx;
+        let bottom = channel(frame_buffer[y1][x0]) * (1. - fx) + channel(frame_buffer[y1][x1]) * fx;
+        let value = (top * (1. - fy) + bottom * fy).round() as u32;
+        rgb |= value.min(0xFF) << shift;
+    }
+    rgb
+}
+
+/// The frame halfway between two rendered frames, shown in between them on
+/// displays faster than 60Hz so that pixels flickering on and off fade
+/// instead
+pub fn interpolate(from: &[u32], to: &[u32]) -> Vec<u32> {
+    from.iter().zip(to).map(|(&a, &b)| blend(a, b)).collect()
+}
+
+/// Per-channel average of two colors
+fn blend(a: u32, b: u32) -> u32 {
+    let mut rgb = 0;
+    for shift in [0, 8, 16] {
+        let channel = (((a >> shift) & 0xFF) + ((b >> shift) & 0xFF)) / 2;
+        rgb |= channel << shift;
+    }
+    rgb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_nearest() {
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer[0][0] = 1;
+        // 3x scale with a 10 pixel border left and right
+        let options = VideoOptions::default();
+        let out = render(&frame_buffer, 212, 96, &options);
+        assert_eq!(out[10], PIXEL_ON);
+        assert_eq!(out[2 * 212 + 12], PIXEL_ON);
+        assert_eq!(out[13], PIXEL_OFF);
+        assert_eq!(out[9], PIXEL_OFF);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let from = [PIXEL_ON, PIXEL_OFF, 0x204060];
+        let to = [PIXEL_OFF, PIXEL_OFF, 0x406080];
+        assert_eq!(interpolate(&from, &to), [0x7F7F7F, PIXEL_OFF, 0x305070]);
+    }
+
+    #[test]
+    fn test_render_smooth() {
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer[0][0] = 1;
+        let options = VideoOptions {
+            filter: ScaleFilter::Smooth,
+            ..VideoOptions::default()
+        };
+        let out = render(&frame_buffer, 256, 128, &options);
+        assert_eq!(out[0], PIXEL_ON);
+        // Blended edge between the lit pixel and its dark neighbour
+        let edge = out[4] & 0xFF;
+        assert!(edge > 0 && edge < 0xFF);
+        assert_eq!(out[20], PIXEL_OFF);
+    }
+
+    #[test]
+    fn test_render_glow() {
+        let mut frame_buffer = FrameBuffer::new();
+        for row in frame_buffer.rows_mut() {
+            row[0] = 1;
+        }
+        let options = VideoOptions {
+            glow: true,
+            ..VideoOptions::default()
+        };
+        // Display is 128x64 with 36 pixel bars left and right
+        let out = render(&frame_buffer, 200, 64, &options);
+        let left_bar = out[10] & 0xFF;
+        let right_bar = out[190] & 0xFF;
+        assert!(left_bar > 0 && left_bar < 0xFF);
+        assert_eq!(right_bar, 0);
+        assert_eq!(out[36], PIXEL_ON);
+    }
+
+    #[test]
+    fn test_palette() {
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer[0][0] = 1;
+        let options = VideoOptions {
+            foreground: parse_color("FFB000").unwrap(),
+            background: parse_color("#202020").unwrap(),
+            ..VideoOptions::default()
+        };
+        let out = render(&frame_buffer, 64, 32, &options);
+        assert_eq!((out[0], out[1]), (0xFFB000, 0x202020));
+        assert_eq!(parse_color("FFF"), None);
+    }
+
+    #[test]
+    fn test_sound_flash() {
+        let mut buffer = [PIXEL_ON, PIXEL_OFF];
+        SoundFlash::Invert.apply(&mut buffer);
+        assert_eq!(buffer, [PIXEL_OFF, PIXEL_ON]);
+        SoundFlash::Tint.apply(&mut buffer);
+        assert_eq!(buffer, [0x7F4000, 0xFFBF7F]);
+        SoundFlash::Off.apply(&mut buffer);
+        assert_eq!(buffer, [0x7F4000, 0xFFBF7F]);
+        assert_eq!(SoundFlash::from_name("tint"), Some(SoundFlash::Tint));
+    }
+}
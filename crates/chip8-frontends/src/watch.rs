@@ -0,0 +1,37 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The most recently modified .ch8 file in `dir`, with its modification time
+pub fn newest_rom(dir: &Path) -> Option<(PathBuf, SystemTime)> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some(OsStr::new("ch8")))
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+            Some((path, modified))
+        })
+        .max_by_key(|(_, modified)| *modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_newest_rom() {
+        let dir = std::env::temp_dir().join(format!("chip8emu-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(newest_rom(&dir), None);
+        std::fs::write(dir.join("old.ch8"), [0x12, 0x00]).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a rom").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(dir.join("new.ch8"), [0x12, 0x00]).unwrap();
+        let (path, _) = newest_rom(&dir).unwrap();
+        assert_eq!(path, dir.join("new.ch8"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
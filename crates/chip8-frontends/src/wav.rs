@@ -0,0 +1,169 @@
+//! Recording the buzzer to a WAV file, rendered in emulated time so the
+//! same ROM and input always give the same file
+
+use crate::audio::{Oscillator, Tone};
+use chip8_core::{Audio, AudioPattern};
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const SAMPLE_RATE: u32 = 44100;
+/// Size of the RIFF and format headers before the samples
+const HEADER_SIZE: u32 = 44;
+
+/// Writes everything the buzzer plays to a 16-bit mono WAV file, passing
+/// the beeps on to another backend so the session can still be heard.
+/// The header is kept up to date after every tick, so the file is valid
+/// however the session ends.
+pub struct WavRecorder<W: Write + Seek = File> {
+    out: W,
+    inner: Box<dyn Audio>,
+    oscillator: Oscillator,
+    sounding: bool,
+    /// Samples written so far
+    length: u32,
+    /// Fraction of a sample carried over between ticks
+    remainder: f64,
+    /// Set once a write fails, after which recording stops
+    failed: bool,
+}
+
+impl WavRecorder<File> {
+    pub fn create(path: &Path, tone: Tone, inner: Box<dyn Audio>) -> io::Result<WavRecorder> {
+        WavRecorder::new(File::create(path)?, tone, inner)
+    }
+}
+
+impl<W: Write + Seek> WavRecorder<W> {
+    pub fn new(mut out: W, tone: Tone, inner: Box<dyn Audio>) -> io::Result<WavRecorder<W>> {
+        out.write_all(&header(0))?;
+        Ok(WavRecorder {
+            out,
+            inner,
+            oscillator: Oscillator::new(tone, SAMPLE_RATE as f32),
+            sounding: false,
+            length: 0,
+            remainder: 0.,
+            failed: false,
+        })
+    }
+
+    /// Samples written so far
+    pub fn len(&self) -> u32 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn write_samples(&mut self, count: usize) -> io::Result<()> {
+        let mut data = Vec::with_capacity(count * 2);
+        for _ in 0..count {
+            let value = if self.sounding {
+                self.oscillator.next_sample()
+            } else {
+                0.
+            };
+            data.extend_from_slice(&((value * i16::MAX as f32) as i16).to_le_bytes());
+        }
+        self.out.write_all(&data)?;
+        self.length += count as u32;
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header(self.length))?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()
+    }
+}
+
+impl<W: Write + Seek> Audio for WavRecorder<W> {
+    fn start_beep(&mut self) {
+        self.sounding = true;
+        self.inner.start_beep();
+    }
+
+    fn stop_beep(&mut self) {
+        self.sounding = false;
+        self.inner.stop_beep();
+    }
+
+    fn set_pattern(&mut self, pattern: AudioPattern, rate: f32) {
+        self.oscillator.set_pattern(pattern, rate);
+        self.inner.set_pattern(pattern, rate);
+    }
+
+    fn advance(&mut self, seconds: f32) {
+        self.inner.advance(seconds);
+        if self.failed {
+            return;
+        }
+        let samples = self.remainder + seconds as f64 * SAMPLE_RATE as f64;
+        self.remainder = samples.fract();
+        if let Err(err) = self.write_samples(samples as usize) {
+            eprintln!("Err: cannot record audio: {}", err);
+            self.failed = true;
+        }
+    }
+}
+
+/// RIFF header for `length` samples of 16-bit mono PCM
+fn header(length: u32) -> [u8; HEADER_SIZE as usize] {
+    let data_size = length * 2;
+    let mut header = [0; HEADER_SIZE as usize];
+    let fields: [&[u8]; 13] = [
+        b"RIFF",
+        &(HEADER_SIZE - 8 + data_size).to_le_bytes(),
+        b"WAVE",
+        b"fmt ",
+        &16u32.to_le_bytes(),
+        // PCM, one channel
+        &1u16.to_le_bytes(),
+        &1u16.to_le_bytes(),
+        &SAMPLE_RATE.to_le_bytes(),
+        // Bytes per second and per sample, bits per sample
+        &(SAMPLE_RATE * 2).to_le_bytes(),
+        &2u16.to_le_bytes(),
+        &16u16.to_le_bytes(),
+        b"data",
+        &data_size.to_le_bytes(),
+    ];
+    let mut offset = 0;
+    for field in fields {
+        header[offset..offset + field.len()].copy_from_slice(field);
+        offset += field.len();
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::NoAudio;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_recording() {
+        let out = Cursor::new(vec![]);
+        let mut recorder = WavRecorder::new(out, Tone::default(), Box::new(NoAudio)).unwrap();
+        // A silent tick, then a beeping one, at 60Hz
+        recorder.advance(1. / 60.);
+        recorder.start_beep();
+        recorder.advance(1. / 60.);
+        assert_eq!(recorder.len(), 1470);
+        let data = recorder.into_inner().into_inner();
+        assert_eq!(data.len(), HEADER_SIZE as usize + 1470 * 2);
+        assert_eq!(&data[..4], b"RIFF");
+        assert_eq!(data[4..8], (36u32 + 2940).to_le_bytes());
+        assert_eq!(data[40..44], 2940u32.to_le_bytes());
+        let samples: Vec<i16> = data[44..]
+            .chunks(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        assert!(samples[..735].iter().all(|&sample| sample == 0));
+        assert!(samples[735..].iter().all(|&sample| sample != 0));
+    }
+}
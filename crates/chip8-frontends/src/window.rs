@@ -0,0 +1,475 @@
+use crate::hotkeys::{Action, Hotkeys};
+use crate::keypad::{self, Keymap};
+use crate::repl::Repl;
+use crate::screenshot::{next_screenshot_path, save_png};
+use crate::shared::SharedFrame;
+use crate::slots::SaveSlots;
+use crate::video::{self, SoundFlash, VideoOptions, PIXEL_OFF};
+use crate::watch;
+use chip8_core::plugin::Renderer;
+use chip8_core::rom::{FileRom, RomError, RomSource};
+use chip8_core::{Chip8Error, Chip8Interpreter, MachineState};
+use chip8_core::{FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+use crossbeam_channel::{select, tick};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const TITLE: &str = "Chip8 Emulator";
+/// Keys handled while the machine is frozen on a fault
+const FAULT_RESET_KEY: Key = Key::R;
+const FAULT_DUMP_KEY: Key = Key::D;
+const FAULT_DEBUG_KEY: Key = Key::B;
+/// Colors of the display while frozen on a fault
+const FAULT_PIXEL_ON: u32 = 0xFF5050;
+const FAULT_PIXEL_OFF: u32 = 0x300000;
+/// Initial window size in host pixels per CHIP-8 pixel
+const WINDOW_SCALE: usize = 10;
+/// How often watch mode looks for a new ROM build
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// Instructions per CPU tick while the turbo key is held
+const TURBO_FACTOR: u32 = 4;
+/// Frames run per CPU tick while fast booting, with the timers kept in step
+const FAST_BOOT_FRAMES: u32 = 4;
+/// Where D writes the state on a fault unless told otherwise
+const QUICKSAVE_PATH: &str = "quicksave.c8state";
+
+/// Why `WindowFrontend::run` returned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunEnd {
+    /// The program reached its end
+    Halted,
+    /// The window was closed while the program was running
+    Closed,
+}
+
+/// Runs an interpreter in real time in a minifb window
+pub struct WindowFrontend {
+    window: Window,
+    renderer: Box<dyn Renderer>,
+    hotkeys: Hotkeys,
+    keymap: Keymap,
+    paused: bool,
+    /// Run at full speed until the program first reads the keypad
+    fast_boot: bool,
+    /// Keep showing the last frame after the program ends, until the
+    /// window is closed
+    keep_open: bool,
+    /// Set when the machine faulted, it stays frozen until reset
+    fault: Option<Chip8Error>,
+    quicksave_path: String,
+    save_slots: SaveSlots,
+    shared_frame: Option<SharedFrame>,
+    /// Color of unlit pixels in the renderer's output, to tell lit ones
+    /// apart when tinting the display on a fault
+    background: u32,
+    sound_flash: SoundFlash,
+    /// Last frame rendered from the frame buffer, kept when interpolating
+    /// so a changed frame can be faded in
+    previous: Option<Vec<u32>>,
+    /// State marked with the practice hotkey, to retry from
+    practice_point: Option<MachineState>,
+    /// Display region (x, y, width, height) and its hash when the game is
+    /// lost, which retries from the practice point automatically
+    game_over: Option<((usize, usize, usize, usize), u64)>,
+}
+
+impl WindowFrontend {
+    pub fn open() -> Result<WindowFrontend, minifb::Error> {
+        let mut window = Window::new(
+            TITLE,
+            FRAME_BUFFER_WIDTH * WINDOW_SCALE,
+            FRAME_BUFFER_HEIGHT * WINDOW_SCALE,
+            WindowOptions {
+                resize: true,
+                ..WindowOptions::default()
+            },
+        )?;
+        // Limit to max ~60 fps update rate
+        window.limit_update_rate(Some(Duration::from_micros(16600)));
+        Ok(WindowFrontend {
+            window,
+            renderer: Box::new(VideoOptions::default()),
+            hotkeys: Hotkeys::default(),
+            keymap: Keymap::default(),
+            paused: false,
+            fast_boot: false,
+            keep_open: false,
+            fault: None,
+            quicksave_path: String::from(QUICKSAVE_PATH),
+            save_slots: SaveSlots::default(),
+            shared_frame: None,
+            background: PIXEL_OFF,
+            sound_flash: SoundFlash::Off,
+            previous: None,
+            practice_point: None,
+            game_over: None,
+        })
+    }
+
+    /// Also publish every new frame for other processes to composite
+    pub fn set_shared_frame(&mut self, shared_frame: SharedFrame) {
+        self.shared_frame = Some(shared_frame);
+    }
+
+    pub fn set_video_options(&mut self, options: VideoOptions) {
+        self.renderer = Box::new(options);
+        self.background = options.background;
+        self.sound_flash = options.sound_flash;
+    }
+
+    /// Draw with a plugin renderer instead of the built-in scaling
+    pub fn set_renderer(&mut self, renderer: Box<dyn Renderer>) {
+        self.renderer = renderer;
+        self.background = PIXEL_OFF;
+    }
+
+    pub fn set_hotkeys(&mut self, hotkeys: Hotkeys) {
+        self.hotkeys = hotkeys;
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// Skip through intros: until the program first polls the keypad, run
+    /// whole frames per CPU tick, timers included, instead of a single
+    /// instruction
+    pub fn set_fast_boot(&mut self, fast_boot: bool) {
+        self.fast_boot = fast_boot;
+    }
+
+    /// Leave the window open on the final screen when the program ends,
+    /// instead of returning from `run` straight away
+    pub fn set_keep_open(&mut self, keep_open: bool) {
+        self.keep_open = keep_open;
+    }
+
+    /// Present at `refresh_hz`, e.g. 120 or 144 for a fast monitor, showing
+    /// a frame halfway between the old and new one whenever the display
+    /// changes. This softens XOR flicker without touching emulation.
+    pub fn set_interpolation(&mut self, refresh_hz: f64) {
+        self.window
+            .limit_update_rate(Some(Duration::from_secs_f64(1. / refresh_hz)));
+        self.previous = Some(vec![]);
+    }
+
+    /// Treat the display showing a picture with `hash` in `region` (x, y,
+    /// width, height) as game over, and go back to the practice point when
+    /// one is marked. The hash is what headless runs print for a region.
+    pub fn set_game_over_screen(&mut self, region: (usize, usize, usize, usize), hash: u64) {
+        self.game_over = Some((region, hash));
+    }
+
+    /// Where the save and load state hotkeys keep their slots, normally
+    /// `SaveSlots::for_rom` for the ROM being run
+    pub fn set_save_slots(&mut self, save_slots: SaveSlots) {
+        self.save_slots = save_slots;
+    }
+
+    /// File written when D is pressed on a fault
+    pub fn set_quicksave_path(&mut self, path: &str) {
+        self.quicksave_path = path.to_string();
+    }
+
+    /// Load `rom` and run it in real time until it ends or the window is
+    /// closed. On a fault the machine freezes with the details shown, until
+    /// it is reset with R or rewound; D dumps the machine state. What to do next is up
+    /// to the caller.
+    pub fn run(
+        &mut self,
+        cpu: &mut Chip8Interpreter,
+        rom: &dyn RomSource,
+    ) -> Result<RunEnd, RomError> {
+        cpu.load_rom_from(rom)?;
+        let timer_ticker = tick(cpu.timer_period());
+        let cpu_timer = tick(Duration::from_secs_f64(1.0 / cpu.clock_speed()));
+        loop {
+            select! {
+                    recv(timer_ticker) -> _ => self.tick_timers(cpu),
+                    recv(cpu_timer) -> _ => {
+                        if self.fault.is_some() {
+                            self.handle_fault_keys(cpu, rom);
+                            self.present(cpu);
+                        } else if let Err(err) = self.handle_cpu_tick(cpu) {
+                            self.enter_fault(cpu, err);
+                        }
+                    },
+            }
+            let open = self.window.is_open();
+            if cpu.halted() && !(open && self.keep_open) {
+                finish(cpu);
+                return Ok(RunEnd::Halted);
+            }
+            if !open {
+                finish(cpu);
+                return Ok(RunEnd::Closed);
+            }
+        }
+    }
+
+    /// Like `run`, but keeps running the most recently modified .ch8 in
+    /// `dir`, reloading whenever a newer build shows up. Faults and the end
+    /// of the program pause the machine until then.
+    pub fn run_watch(&mut self, cpu: &mut Chip8Interpreter, dir: &Path) {
+        let timer_ticker = tick(cpu.timer_period());
+        let cpu_timer = tick(Duration::from_secs_f64(1.0 / cpu.clock_speed()));
+        let watch_ticker = tick(WATCH_INTERVAL);
+        let mut loaded = None;
+        let mut running = false;
+        loop {
+            select! {
+                    recv(timer_ticker) -> _ => self.tick_timers(cpu),
+                    recv(cpu_timer) -> _ => {
+                        if !running {
+                            self.present(cpu);
+                        } else if let Err(err) = self.handle_cpu_tick(cpu) {
+                            self.enter_fault(cpu, err);
+                            eprintln!("Waiting for a new build");
+                            running = false;
+                        }
+                    },
+                    recv(watch_ticker) -> _ => {
+                        if !self.window.is_open() {
+                            finish(cpu);
+                            return;
+                        }
+                        let newest = watch::newest_rom(dir);
+                        if let Some((path, _)) = newest.as_ref().filter(|_| newest != loaded) {
+                            cpu.reset();
+                            match cpu.load_rom_from(&FileRom::new(path)) {
+                                Ok(()) => {
+                                    println!("Loaded {}", path.display());
+                                    loaded = newest;
+                                    running = true;
+                                    self.fault = None;
+                                    self.window.set_title(TITLE);
+                                }
+                                // Probably still being written, retry next time
+                                Err(err) => eprintln!("Err: {}", err),
+                            }
+                        }
+                    },
+            }
+        }
+    }
+
+    fn tick_timers(&mut self, cpu: &mut Chip8Interpreter) {
+        // Snapshots are taken about once a frame, so going back one per
+        // timer tick rewinds at roughly the speed the game ran
+        if self.hotkey_down(Action::Rewind) {
+            if cpu.rewind() && self.fault.take().is_some() {
+                self.window.set_title(TITLE);
+            }
+            return;
+        }
+        if !self.paused && self.fault.is_none() {
+            cpu.tick_timers_wall_clock();
+        }
+    }
+
+    fn enter_fault(&mut self, cpu: &Chip8Interpreter, fault: Chip8Error) {
+        eprintln!("Err: {}", fault);
+        eprint!("{}", cpu);
+        if let Some(history) = cpu.history() {
+            eprint!("last instructions:\n{}", history);
+        }
+        match cpu.write_journal(&fault) {
+            Ok(Some(path)) => eprintln!("Wrote a crash dump to {}", path),
+            Ok(None) => {}
+            Err(io_err) => eprintln!("Err: cannot write journal: {}", io_err),
+        }
+        self.window.set_title(&format!(
+            "Fault: {} - R to reset, D to dump state, B to debug in the terminal",
+            fault
+        ));
+        self.fault = Some(fault);
+    }
+
+    fn handle_fault_keys(&mut self, cpu: &mut Chip8Interpreter, rom: &dyn RomSource) {
+        if self.window.is_key_pressed(FAULT_DUMP_KEY, KeyRepeat::No) {
+            self.save_state(cpu);
+        }
+        if self.window.is_key_pressed(FAULT_DEBUG_KEY, KeyRepeat::No) {
+            // The window stops updating until the prompt is left
+            println!("Debugging the faulted machine, quit to go back to the window");
+            if let Err(err) = Repl::new().run(cpu) {
+                eprintln!("Err: {}", err);
+            }
+        }
+        if self.window.is_key_pressed(FAULT_RESET_KEY, KeyRepeat::No) {
+            cpu.reset();
+            match cpu.load_rom_from(rom) {
+                Ok(()) => {
+                    self.fault = None;
+                    self.window.set_title(TITLE);
+                }
+                Err(err) => eprintln!("Err: {}", err),
+            }
+        }
+    }
+
+    fn save_state(&self, cpu: &Chip8Interpreter) {
+        match cpu.state().save(&self.quicksave_path) {
+            Ok(()) => println!("Saved state to {}", self.quicksave_path),
+            Err(err) => eprintln!("Err: cannot write {}: {}", self.quicksave_path, err),
+        }
+    }
+
+    fn handle_cpu_tick(&mut self, cpu: &mut Chip8Interpreter) -> Result<(), Chip8Error> {
+        if self.hotkey_pressed(Action::ToggleGrab) {
+            self.hotkeys.grab_all = !self.hotkeys.grab_all;
+            println!(
+                "Game grabs all keys: {}",
+                if self.hotkeys.grab_all { "on" } else { "off" }
+            );
+        }
+        if self.hotkey_pressed(Action::Pause) {
+            self.paused = !self.paused;
+            if !self.paused {
+                cpu.resume();
+            }
+        }
+        if self.hotkey_pressed(Action::SaveState) {
+            match self.save_slots.save(cpu) {
+                Ok(path) => println!("Saved state to {}", path.display()),
+                Err(err) => eprintln!("Err: {}", err),
+            }
+        }
+        if self.hotkey_pressed(Action::LoadState) {
+            match self.save_slots.load(cpu) {
+                Ok(path) => println!("Loaded state from {}", path.display()),
+                Err(err) => eprintln!("Err: {}", err),
+            }
+        }
+        for (action, step) in [(Action::NextSlot, 1), (Action::PreviousSlot, -1)] {
+            if self.hotkey_pressed(action) {
+                println!("Save slot {}", self.save_slots.select(step));
+            }
+        }
+        if self.hotkey_pressed(Action::PracticeMark) {
+            self.practice_point = Some(cpu.state());
+            println!("Marked the practice point at {:#05X}", cpu.pc());
+        }
+        if self.hotkey_pressed(Action::PracticeRetry) {
+            self.retry(cpu);
+        }
+        keypad::update(&self.window, &self.keymap, &self.hotkeys, cpu);
+        if !self.paused && !self.hotkey_down(Action::Rewind) {
+            let steps = if self.hotkey_down(Action::Turbo) {
+                TURBO_FACTOR
+            } else {
+                1
+            };
+            let start = Instant::now();
+            if self.fast_boot && !cpu.input_polled() {
+                for _ in 0..FAST_BOOT_FRAMES {
+                    if cpu.input_polled() || cpu.halted() {
+                        break;
+                    }
+                    cpu.run_frame()?;
+                }
+            } else {
+                for _ in 0..steps {
+                    cpu.step()?;
+                }
+            }
+            if let Some(timing) = cpu.timing_mut() {
+                timing.record_batch(start.elapsed());
+            }
+            if let Some(reason) = cpu.paused() {
+                self.paused = true;
+                println!("Paused on {}, unpause to continue", reason);
+            }
+            if let Some(((x, y, width, height), hash)) = self.game_over {
+                if self.practice_point.is_some()
+                    && cpu.frame_buffer().region(x, y, width, height).hash() == hash
+                {
+                    println!("Game over");
+                    self.retry(cpu);
+                }
+            }
+        }
+        if self.hotkey_pressed(Action::Explain) {
+            println!("{:#05X} {}", cpu.pc(), cpu.explain());
+        }
+        if self.hotkey_pressed(Action::Screenshot) {
+            let path = next_screenshot_path();
+            match save_png(cpu.frame_buffer(), &path) {
+                Ok(()) => println!("Saved screenshot to {}", path.display()),
+                Err(err) => eprintln!("Err: cannot write {}: {}", path.display(), err),
+            }
+        }
+        self.present(cpu);
+        Ok(())
+    }
+
+    fn retry(&self, cpu: &mut Chip8Interpreter) {
+        match &self.practice_point {
+            Some(state) => {
+                cpu.restore(state);
+                println!("Back to the practice point");
+            }
+            None => println!("No practice point marked yet"),
+        }
+    }
+
+    fn hotkey_pressed(&self, action: Action) -> bool {
+        match self.hotkeys.key(action) {
+            Some(key) => self.window.is_key_pressed(key, KeyRepeat::No),
+            None => false,
+        }
+    }
+
+    fn hotkey_down(&self, action: Action) -> bool {
+        match self.hotkeys.key(action) {
+            Some(key) => self.window.is_key_down(key),
+            None => false,
+        }
+    }
+
+    /// Draw the frame buffer to the window
+    fn present(&mut self, cpu: &mut Chip8Interpreter) {
+        if let Some(shared_frame) = &mut self.shared_frame {
+            shared_frame.publish(cpu.frame_buffer());
+        }
+        if self.window.is_open() {
+            let (width, height) = self.window.get_size();
+            let mut buffer = self.renderer.render(cpu.frame_buffer(), width, height);
+            if let Some(previous) = &mut self.previous {
+                if previous.len() == buffer.len() && *previous != buffer {
+                    let halfway = video::interpolate(previous, &buffer);
+                    *previous = std::mem::replace(&mut buffer, halfway);
+                } else {
+                    previous.clone_from(&buffer);
+                }
+            }
+            if self.fault.is_some() {
+                for pixel in buffer.iter_mut() {
+                    *pixel = if *pixel & 0xFFFFFF != self.background {
+                        FAULT_PIXEL_ON
+                    } else {
+                        FAULT_PIXEL_OFF
+                    };
+                }
+            } else if cpu.sound_timer() > 0 {
+                self.sound_flash.apply(&mut buffer);
+            }
+            self.window
+                .update_with_buffer(&buffer, width, height)
+                .unwrap();
+            if let Some(timing) = cpu.timing_mut() {
+                timing.record_present(Instant::now());
+            }
+        }
+    }
+}
+
+/// Write out whatever was being measured during the run
+fn finish(cpu: &Chip8Interpreter) {
+    if let Some(timing) = cpu.timing() {
+        if let Err(err) = timing.save() {
+            eprintln!("Err: cannot write {}: {}", timing.path(), err);
+        }
+    }
+}
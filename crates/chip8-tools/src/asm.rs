@@ -0,0 +1,637 @@
+//! Assembles Cowgod-style mnemonics into a ROM, one instruction per line,
+//! e.g. "LD V3, 0x1F" or "DRW V0, V1, 5". Anything after a ; is a comment.
+//! It reads what `Instruction`'s `Display` writes, so disassembled code
+//! assembles back to the same bytes.
+//!
+//! A line can start with a `label:`, which names the address of what
+//! follows it, and `NAME EQU 5` names a constant. Both can be used
+//! wherever a number goes, before or after they are defined. `.byte 1, 2`
+//! emits bytes, and `.sprite ####...., #..#....` emits a byte per row of
+//! pixels, # or 1 for a lit one, as `disasm` lists sprites.
+//!
+//! Errors say which line, column and token they are about, and suggest
+//! the instruction or name a misspelled one most likely meant.
+
+use crate::octo;
+use chip8_core::instruction::Opcode;
+use chip8_core::{Symbols, PROGRAM_START};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Every instruction name the assembler knows
+const MNEMONICS: [&str; 23] = [
+    "ADD", "AND", "AUDIO", "CALL", "CLS", "DRW", "END", "JP", "LD", "OR", "PITCH", "RET", "RND",
+    "SE", "SHL", "SHR", "SKNP", "SKP", "SNE", "SUB", "SUBN", "SYS", "XOR",
+];
+
+const DIRECTIVES: [&str; 2] = [".byte", ".sprite"];
+
+/// An instruction operand
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+enum Operand {
+    /// V0 to VF
+    V(u8),
+    /// 0x2A4, #2A4 or 676
+    Number(u16),
+    I,
+    /// [I], memory at I
+    IndirectI,
+    DT,
+    ST,
+    K,
+    /// The font sprite for a digit, in LD F, Vx
+    F,
+    /// Decimal digits, in LD B, Vx
+    B,
+    /// The RPL user flags, in LD R, Vx and LD Vx, R
+    R,
+}
+
+fn parse_number(text: &str) -> Option<u16> {
+    if let Some(hex) = text.strip_prefix("0X").or_else(|| text.strip_prefix('#')) {
+        return u16::from_str_radix(hex, 16).ok();
+    }
+    text.parse().ok()
+}
+
+/// Letters, digits and underscores, not starting with a digit
+pub(crate) fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether `name` is already an operand, like VA or DT
+fn is_reserved(name: &str) -> bool {
+    !matches!(
+        parse_operand(name, &HashMap::new()),
+        Err(_) | Ok(Operand::Number(_))
+    )
+}
+
+/// A number, or the value of a label or constant
+fn parse_value(text: &str, names: &HashMap<&str, u16>) -> Result<u16, String> {
+    if let Some(&value) = names.get(text) {
+        return Ok(value);
+    }
+    match parse_number(&text.to_ascii_uppercase()) {
+        Some(value) => Ok(value),
+        None if is_name(text) => Err(format!("undefined name '{}'", text)),
+        None => Err(format!("invalid number '{}'", text)),
+    }
+}
+
+fn parse_operand(text: &str, names: &HashMap<&str, u16>) -> Result<Operand, String> {
+    if let Some(&value) = names.get(text) {
+        return Ok(Operand::Number(value));
+    }
+    let upper = text.to_ascii_uppercase();
+    let operand = match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::DT,
+        "ST" => Operand::ST,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        register if register.len() == 2 && register.starts_with('V') => {
+            match u8::from_str_radix(&register[1..], 16) {
+                Ok(x) => Operand::V(x),
+                Err(_) => return Err(format!("invalid register '{}'", text)),
+            }
+        }
+        _ => match parse_value(text, names) {
+            Ok(value) => Operand::Number(value),
+            Err(_) if !is_name(text) => return Err(format!("invalid operand '{}'", text)),
+            Err(err) => return Err(err),
+        },
+    };
+    Ok(operand)
+}
+
+/// A number that has to fit in `max`, e.g. 0xFFF for an address
+fn fits(value: u16, max: u16, what: &str) -> Result<u16, String> {
+    if value > max {
+        return Err(format!(
+            "{} {:#X} is out of range, at most {:#X}",
+            what, value, max
+        ));
+    }
+    Ok(value)
+}
+
+/// The opcode word for one instruction
+fn encode(mnemonic: &str, operands: &[Operand]) -> Result<u16, String> {
+    use Operand::*;
+    let addr = |value| fits(value, 0xFFF, "address");
+    let byte = |value| fits(value, 0xFF, "byte").map(|value| value as u8);
+    let nibble = |value| fits(value, 0xF, "nibble").map(|value| value as u8);
+    let x_kk = |family: u8, x: u8, kk: u16| -> Result<u16, String> {
+        Ok(Opcode::with_x_kk(family, x, byte(kk)?).raw)
+    };
+    let x_y = |n, x, y| Opcode::with_x_y_n(0x8, x, y, n).raw;
+    let fx = |x, kk| Opcode::with_x_kk(0xF, x, kk).raw;
+    let name = mnemonic.to_ascii_uppercase();
+    let opcode = match (name.as_str(), operands) {
+        ("END", []) => 0x0000,
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SYS", &[Number(nnn)]) => Opcode::with_nnn(0x0, addr(nnn)?).raw,
+        ("JP", &[Number(nnn)]) => Opcode::with_nnn(0x1, addr(nnn)?).raw,
+        ("JP", &[V(0), Number(nnn)]) => Opcode::with_nnn(0xB, addr(nnn)?).raw,
+        ("CALL", &[Number(nnn)]) => Opcode::with_nnn(0x2, addr(nnn)?).raw,
+        ("SE", &[V(x), Number(kk)]) => x_kk(0x3, x, kk)?,
+        ("SNE", &[V(x), Number(kk)]) => x_kk(0x4, x, kk)?,
+        ("SE", &[V(x), V(y)]) => Opcode::with_x_y_n(0x5, x, y, 0).raw,
+        ("SNE", &[V(x), V(y)]) => Opcode::with_x_y_n(0x9, x, y, 0).raw,
+        ("LD", &[V(x), Number(kk)]) => x_kk(0x6, x, kk)?,
+        ("ADD", &[V(x), Number(kk)]) => x_kk(0x7, x, kk)?,
+        ("LD", &[V(x), V(y)]) => x_y(0x0, x, y),
+        ("OR", &[V(x), V(y)]) => x_y(0x1, x, y),
+        ("AND", &[V(x), V(y)]) => x_y(0x2, x, y),
+        ("XOR", &[V(x), V(y)]) => x_y(0x3, x, y),
+        ("ADD", &[V(x), V(y)]) => x_y(0x4, x, y),
+        ("SUB", &[V(x), V(y)]) => x_y(0x5, x, y),
+        ("SHR", &[V(x)]) => x_y(0x6, x, x),
+        ("SHR", &[V(x), V(y)]) => x_y(0x6, x, y),
+        ("SUBN", &[V(x), V(y)]) => x_y(0x7, x, y),
+        ("SHL", &[V(x)]) => x_y(0xE, x, x),
+        ("SHL", &[V(x), V(y)]) => x_y(0xE, x, y),
+        ("LD", &[I, Number(nnn)]) => Opcode::with_nnn(0xA, addr(nnn)?).raw,
+        ("RND", &[V(x), Number(kk)]) => x_kk(0xC, x, kk)?,
+        ("DRW", &[V(x), V(y), Number(n)]) => Opcode::with_x_y_n(0xD, x, y, nibble(n)?).raw,
+        ("SKP", &[V(x)]) => Opcode::with_x_kk(0xE, x, 0x9E).raw,
+        ("SKNP", &[V(x)]) => Opcode::with_x_kk(0xE, x, 0xA1).raw,
+        ("LD", &[V(x), DT]) => fx(x, 0x07),
+        ("LD", &[V(x), K]) => fx(x, 0x0A),
+        ("LD", &[DT, V(x)]) => fx(x, 0x15),
+        ("LD", &[ST, V(x)]) => fx(x, 0x18),
+        ("ADD", &[I, V(x)]) => fx(x, 0x1E),
+        ("LD", &[F, V(x)]) => fx(x, 0x29),
+        ("LD", &[B, V(x)]) => fx(x, 0x33),
+        ("LD", &[IndirectI, V(x)]) => fx(x, 0x55),
+        ("LD", &[V(x), IndirectI]) => fx(x, 0x65),
+        ("AUDIO", []) => 0xF002,
+        ("PITCH", &[V(x)]) => fx(x, 0x3A),
+        ("LD", &[R, V(x)]) => fx(x, 0x75),
+        ("LD", &[V(x), R]) => fx(x, 0x85),
+        _ => return Err(format!("invalid operands for {}", mnemonic)),
+    };
+    Ok(opcode)
+}
+
+/// What a line emits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Statement<'a> {
+    Instruction(&'a str, &'a str),
+    /// .byte and its comma separated values
+    Bytes(&'a str),
+    /// .sprite and its comma separated rows
+    Sprite(&'a str),
+}
+
+impl Statement<'_> {
+    fn size(&self) -> u16 {
+        match self {
+            Statement::Instruction(..) => 2,
+            Statement::Bytes(list) | Statement::Sprite(list) => list.split(',').count() as u16,
+        }
+    }
+}
+
+/// A sprite row such as "####....", most significant bit first
+fn parse_row(row: &str) -> Result<u8, String> {
+    if row.is_empty() || row.len() > 8 {
+        return Err(format!(
+            "invalid sprite row '{}', expected 1 to 8 pixels",
+            row
+        ));
+    }
+    row.chars()
+        .enumerate()
+        .try_fold(0, |byte, (bit, pixel)| match pixel {
+            '#' | '1' => Ok(byte | 0x80 >> bit),
+            '.' | '0' => Ok(byte),
+            _ => Err(format!("invalid pixel '{}' in sprite row '{}'", pixel, row)),
+        })
+}
+
+/// An error and the token it is about, before it is placed in the source
+pub(crate) struct Fault<'a> {
+    token: &'a str,
+    message: String,
+    hint: Option<String>,
+}
+
+impl<'a> Fault<'a> {
+    pub(crate) fn new(token: &'a str, message: String) -> Fault<'a> {
+        Fault {
+            token,
+            message,
+            hint: None,
+        }
+    }
+
+    pub(crate) fn hint(self, hint: Option<String>) -> Fault<'a> {
+        Fault { hint, ..self }
+    }
+}
+
+/// The fewest characters to insert, delete or replace to turn `a` into `b`
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &y) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(x != y));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// "did you mean" the candidate closest to `word`, ignoring case, when it
+/// is close enough to be a typo
+pub(crate) fn suggest<'a>(
+    word: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let word = word.to_ascii_uppercase();
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(&word, &candidate.to_ascii_uppercase()), candidate))
+        .filter(|&(cost, _)| cost <= 2 && cost < word.len())
+        .min_by_key(|&(cost, _)| cost)
+        .map(|(_, candidate)| format!("did you mean '{}'?", candidate))
+}
+
+/// A value or operand that failed, suggesting a defined name when it looks
+/// like a misspelling of one
+fn name_fault<'a>(token: &'a str, message: String, names: &HashMap<&str, u16>) -> Fault<'a> {
+    let mut known: Vec<&str> = names.keys().copied().collect();
+    known.sort_unstable();
+    let hint = if is_name(token) {
+        suggest(token, known)
+    } else {
+        None
+    };
+    Fault::new(token, message).hint(hint)
+}
+
+/// The bytes `statement` emits, once every name is known
+fn emit<'a>(statement: Statement<'a>, names: &HashMap<&str, u16>) -> Result<Vec<u8>, Fault<'a>> {
+    match statement {
+        Statement::Instruction(mnemonic, operands) => {
+            if !MNEMONICS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(mnemonic))
+            {
+                let message = format!("unknown instruction '{}'", mnemonic);
+                let hint = suggest(mnemonic, MNEMONICS.iter().copied());
+                return Err(Fault::new(mnemonic, message).hint(hint));
+            }
+            let parsed = match operands {
+                "" => vec![],
+                operands => operands
+                    .split(',')
+                    .map(str::trim)
+                    .map(|operand| {
+                        parse_operand(operand, names).map_err(|err| name_fault(operand, err, names))
+                    })
+                    .collect::<Result<_, _>>()?,
+            };
+            let token = if operands.is_empty() {
+                mnemonic
+            } else {
+                operands
+            };
+            let opcode = encode(mnemonic, &parsed).map_err(|err| Fault::new(token, err))?;
+            Ok(opcode.to_be_bytes().to_vec())
+        }
+        Statement::Bytes(list) => list
+            .split(',')
+            .map(str::trim)
+            .map(|value| {
+                let byte =
+                    parse_value(value, names).map_err(|err| name_fault(value, err, names))?;
+                fits(byte, 0xFF, "byte")
+                    .map(|byte| byte as u8)
+                    .map_err(|err| Fault::new(value, err))
+            })
+            .collect(),
+        Statement::Sprite(list) => list
+            .split(',')
+            .map(str::trim)
+            .map(|row| parse_row(row).map_err(|err| Fault::new(row, err)))
+            .collect(),
+    }
+}
+
+/// Give `name` a value, once
+fn define<'a>(
+    names: &mut HashMap<&'a str, u16>,
+    name: &'a str,
+    value: u16,
+) -> Result<(), Fault<'a>> {
+    if !is_name(name) || is_reserved(name) {
+        return Err(Fault::new(name, format!("invalid name '{}'", name)));
+    }
+    if names.insert(name, value).is_some() {
+        return Err(Fault::new(name, format!("'{}' is already defined", name)));
+    }
+    Ok(())
+}
+
+/// An assembly error, pointing at the token in the source that caused it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Counted from 1, as editors do
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub message: String,
+    /// A "did you mean" for what looks like a typo
+    pub hint: Option<String>,
+    /// The line the token is on
+    pub source: String,
+}
+
+impl Diagnostic {
+    /// `fault` on the line at `index`, its token being a slice of `line`
+    fn new(index: usize, line: &str, fault: Fault) -> Diagnostic {
+        let offset = (fault.token.as_ptr() as usize)
+            .saturating_sub(line.as_ptr() as usize)
+            .min(line.len());
+        Diagnostic {
+            line: index + 1,
+            column: line[..offset].chars().count() + 1,
+            token: fault.token.to_string(),
+            message: fault.message,
+            hint: fault.hint,
+            source: line.to_string(),
+        }
+    }
+
+    /// `fault` anywhere in `source`, its token being a slice of it
+    pub(crate) fn locate(source: &str, fault: Fault) -> Diagnostic {
+        let offset = (fault.token.as_ptr() as usize)
+            .saturating_sub(source.as_ptr() as usize)
+            .min(source.len());
+        let start = source[..offset]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |newline| offset + newline);
+        let index = source[..offset].matches('\n').count();
+        Diagnostic::new(index, source[start..end].trim_end_matches('\r'), fault)
+    }
+}
+
+/// The location and message, then the source line with the token
+/// underlined and the hint below it
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )?;
+        // Tabs stay tabs so the carets line up however they are shown
+        let indent: String = self
+            .source
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { c } else { ' ' })
+            .collect();
+        let carets = "^".repeat(self.token.chars().count().max(1));
+        write!(f, "\n  {}\n  {}{}", self.source, indent, carets)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// An assembled ROM and the addresses of its labels
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    pub rom: Vec<u8>,
+    /// For the debuggers and `disasm` to show the labels by name
+    pub symbols: Symbols,
+}
+
+/// The program for `source`, or the first error in it. Addresses are
+/// laid out first, so labels can be used before they are defined.
+pub fn assemble(source: &str) -> Result<Program, Diagnostic> {
+    let mut names: HashMap<&str, u16> = HashMap::new();
+    let mut symbols = Symbols::new();
+    let mut statements = vec![];
+    let mut addr = PROGRAM_START;
+    for (index, line) in source.lines().enumerate() {
+        let at = |fault: Fault| Diagnostic::new(index, line, fault);
+        let mut code = line.split(';').next().unwrap_or_default().trim();
+        if let Some((label, rest)) = code.split_once(':') {
+            let label = label.trim();
+            define(&mut names, label, addr).map_err(at)?;
+            symbols.insert(addr, label);
+            code = rest.trim();
+        }
+        if code.is_empty() {
+            continue;
+        }
+        // The empty rest is sliced from the line so errors can point at it
+        let (first, rest) = code
+            .split_once(char::is_whitespace)
+            .unwrap_or((code, &code[code.len()..]));
+        let rest = rest.trim();
+        let statement = match first.to_ascii_lowercase().as_str() {
+            ".byte" => Statement::Bytes(rest),
+            ".sprite" => Statement::Sprite(rest),
+            directive if directive.starts_with('.') => {
+                let message = format!("unknown directive '{}'", first);
+                let hint = suggest(first, DIRECTIVES.iter().copied());
+                return Err(at(Fault::new(first, message).hint(hint)));
+            }
+            _ => match rest.split_once(char::is_whitespace) {
+                Some((equ, value)) if equ.eq_ignore_ascii_case("EQU") => {
+                    let value = value.trim();
+                    let number = parse_value(value, &names)
+                        .map_err(|err| at(name_fault(value, err, &names)))?;
+                    define(&mut names, first, number).map_err(at)?;
+                    continue;
+                }
+                _ => Statement::Instruction(first, rest),
+            },
+        };
+        statements.push((index, line, statement));
+        addr = addr
+            .checked_add(statement.size())
+            .filter(|&end| end <= 0x1000)
+            .ok_or_else(|| {
+                at(Fault::new(
+                    code,
+                    "the program doesn't fit in memory".to_string(),
+                ))
+            })?;
+    }
+    let mut rom = vec![];
+    for (index, line, statement) in statements {
+        rom.extend(emit(statement, &names).map_err(|fault| Diagnostic::new(index, line, fault))?);
+    }
+    Ok(Program { rom, symbols })
+}
+
+/// `chip8emu asm SOURCE -o OUT.ch8 [--symbols OUT.sym]`, reading Octo
+/// for a .8o source
+pub fn run(source_path: &str, rom_path: &str, symbols_path: Option<&str>) -> Result<(), String> {
+    let source = std::fs::read_to_string(source_path)
+        .map_err(|err| format!("cannot read {}: {}", source_path, err))?;
+    let program = if source_path.ends_with(".8o") {
+        octo::assemble(&source)
+    } else {
+        assemble(&source)
+    };
+    let program = program.map_err(|err| format!("{}: {}", source_path, err))?;
+    std::fs::write(rom_path, &program.rom)
+        .map_err(|err| format!("cannot write {}: {}", rom_path, err))?;
+    if let Some(path) = symbols_path {
+        std::fs::write(path, program.symbols.to_string())
+            .map_err(|err| format!("cannot write {}: {}", path, err))?;
+    }
+    println!("{} bytes written to {}", program.rom.len(), rom_path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::instruction;
+
+    #[test]
+    fn test_assemble() {
+        let source = "\
+            ; draw a digit
+            ld v3, #1F
+            LD F, V3
+            DRW V0, V1, 5   ; at the top left
+            SHR V2
+            JP V0, 768
+        ";
+        assert_eq!(
+            assemble(source).unwrap().rom,
+            [0x63, 0x1F, 0xF3, 0x29, 0xD0, 0x15, 0x82, 0x26, 0xB3, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_labels_and_data() {
+        let source = "\
+            ROWS EQU 3
+            start:  LD I, digit     ; defined further down
+                    DRW V0, V1, ROWS
+            spin:   JP spin
+            digit:
+                    .sprite ####...., #..#...., ####....
+                    .byte ROWS, 0xFF
+        ";
+        let program = assemble(source).unwrap();
+        assert_eq!(
+            program.rom,
+            [0xA2, 0x06, 0xD0, 0x13, 0x12, 0x04, 0xF0, 0x90, 0xF0, 0x03, 0xFF]
+        );
+        assert_eq!(
+            program.symbols.to_string(),
+            "0x200 start\n0x204 spin\n0x206 digit\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Every opcode the disassembler can print assembles back to itself
+        for raw in 0..=0xFFFF {
+            if let Ok(inst) = instruction::decode(raw) {
+                let rom = assemble(&inst.to_string()).map(|program| program.rom);
+                assert_eq!(rom, Ok(raw.to_be_bytes().to_vec()));
+            }
+        }
+    }
+
+    /// Where the error in `source` is and what it says
+    fn error(source: &str) -> (usize, usize, String) {
+        let err = assemble(source).unwrap_err();
+        (err.line, err.column, err.message)
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error("CLS\nLD V0, 0x100"),
+            (2, 4, "byte 0x100 is out of range, at most 0xFF".to_string())
+        );
+        assert_eq!(
+            error("DRW V0, V1"),
+            (1, 5, "invalid operands for DRW".to_string())
+        );
+        assert_eq!(
+            error("LD VG, 1"),
+            (1, 4, "invalid register 'VG'".to_string())
+        );
+        assert_eq!(
+            error("  JMP 0x200"),
+            (1, 3, "unknown instruction 'JMP'".to_string())
+        );
+        assert_eq!(
+            error("JP nowhere"),
+            (1, 4, "undefined name 'nowhere'".to_string())
+        );
+        assert_eq!(
+            error("a: CLS\na: RET"),
+            (2, 1, "'a' is already defined".to_string())
+        );
+        assert_eq!(error("DT EQU 1"), (1, 1, "invalid name 'DT'".to_string()));
+        assert_eq!(
+            error(".byte 1, 300"),
+            (
+                1,
+                10,
+                "byte 0x12C is out of range, at most 0xFF".to_string()
+            )
+        );
+        assert_eq!(
+            error(".sprite ##x"),
+            (1, 9, "invalid pixel 'x' in sprite row '##x'".to_string())
+        );
+        assert_eq!(
+            error(".word 1"),
+            (1, 1, "unknown directive '.word'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let err = assemble("start: CLS\n\tJMP strat").unwrap_err();
+        assert_eq!(err.token, "JMP");
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 2: unknown instruction 'JMP'\n  \tJMP strat\n  \t^^^\n  did you mean 'JP'?"
+        );
+        let hint = |source| assemble(source).unwrap_err().hint;
+        assert_eq!(
+            hint("start: CLS\nJP strat").as_deref(),
+            Some("did you mean 'start'?")
+        );
+        assert_eq!(hint(".bytes 1").as_deref(), Some("did you mean '.byte'?"));
+        assert_eq!(
+            hint("DRAW V0, V1, 5").as_deref(),
+            Some("did you mean 'DRW'?")
+        );
+        assert_eq!(hint("XYZZY"), None);
+    }
+}
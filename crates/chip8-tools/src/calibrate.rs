@@ -0,0 +1,142 @@
+use chip8_core::rom::{BytesRom, RomError, RomSource};
+use chip8_core::{Chip8Error, Chip8Interpreter, Quirks};
+use chip8_core::{FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+
+/// Frames each preset is run for, five seconds of play
+const TRIAL_FRAMES: u64 = 300;
+/// More than this share of lit pixels looks like a filled, broken screen
+const MAX_LIT_SHARE: f64 = 0.9;
+
+/// How a ROM behaved under one quirks preset
+#[derive(Debug, PartialEq)]
+pub struct Trial {
+    pub preset: &'static str,
+    pub quirks: Quirks,
+    /// Frames completed before the trial ended
+    pub frames: u64,
+    pub fault: Option<Chip8Error>,
+    /// The display ended up blank or almost entirely lit
+    pub degenerate_display: bool,
+}
+
+impl Trial {
+    /// Higher is more plausible. Faults outweigh everything, unknown
+    /// opcodes most of all since they usually mean execution went astray.
+    fn score(&self) -> i64 {
+        let fault_penalty = match self.fault {
+            Some(Chip8Error::Decode { .. }) => 3000,
+            Some(_) => 2000,
+            None => 0,
+        };
+        let display_penalty = if self.degenerate_display { 100 } else { 0 };
+        self.frames as i64 - fault_penalty - display_penalty
+    }
+}
+
+/// Run `rom` briefly under every preset
+pub fn trials(rom: &dyn RomSource) -> Result<Vec<Trial>, RomError> {
+    let data = rom.read()?;
+    let rom = BytesRom {
+        name: rom.name(),
+        data: &data,
+    };
+    Quirks::PRESETS
+        .iter()
+        .map(|&(preset, quirks)| {
+            let mut cpu = Chip8Interpreter::new();
+            cpu.set_quirks(quirks);
+            cpu.set_echo_display(false);
+            cpu.load_rom_from(&rom)?;
+            let mut frames = 0;
+            let mut fault = None;
+            for frame in cpu.frames().take(TRIAL_FRAMES as usize) {
+                match frame {
+                    Ok(_) => frames += 1,
+                    Err(err) => fault = Some(err),
+                }
+            }
+            let lit = cpu
+                .frame_buffer()
+                .rows()
+                .flatten()
+                .filter(|&&pixel| pixel == 1)
+                .count();
+            let lit_share = lit as f64 / (FRAME_BUFFER_WIDTH * FRAME_BUFFER_HEIGHT) as f64;
+            Ok(Trial {
+                preset,
+                quirks,
+                frames,
+                fault,
+                degenerate_display: lit == 0 || lit_share > MAX_LIT_SHARE,
+            })
+        })
+        .collect()
+}
+
+/// The most plausible trial, the earliest preset on a tie
+pub fn best(trials: &[Trial]) -> &Trial {
+    trials
+        .iter()
+        .rev()
+        .max_by_key(|trial| trial.score())
+        .expect("there is at least one preset")
+}
+
+/// Table of all trials, for telling the user why a preset was picked
+pub fn report(trials: &[Trial]) -> String {
+    let mut out = format!("{:<12} {:>6}  {:<8} fault\n", "preset", "frames", "display");
+    for trial in trials {
+        out += &format!(
+            "{:<12} {:>6}  {:<8} {}\n",
+            trial.preset,
+            trial.frames,
+            if trial.degenerate_display {
+                "blank"
+            } else {
+                "ok"
+            },
+            match &trial.fault {
+                Some(err) => err.to_string(),
+                None => String::from("-"),
+            }
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_preset() {
+        // V0 = 0x82 shifted by one bit, which is 0 under the COSMAC VIP
+        // shift quirk (it shifts V1 instead). Then the "0" glyph is drawn at (0, 0) and at
+        // (V0, 0): with the quirk the two cancel out and the screen is blank.
+        let rom = BytesRom {
+            name: String::from("shift"),
+            data: &[
+                0x60, 0x82, 0x80, 0x1E, 0xA0, 0x00, 0xD2, 0x25, 0xD0, 0x25, 0x12, 0x0A,
+            ],
+        };
+        let trials = trials(&rom).unwrap();
+        assert_eq!(trials.len(), Quirks::PRESETS.len());
+        assert!(trials.iter().all(|trial| trial.fault.is_none()));
+        assert!(trials.iter().all(|trial| trial.frames == TRIAL_FRAMES));
+        assert!(trials[0].degenerate_display);
+        assert!(!trials[1].degenerate_display);
+        assert_eq!(best(&trials).preset, "chip-48");
+    }
+
+    #[test]
+    fn test_fault_scores_lowest() {
+        let rom = BytesRom {
+            name: String::from("bad"),
+            data: &[0xFF, 0xFF],
+        };
+        let trials = trials(&rom).unwrap();
+        assert!(matches!(trials[0].fault, Some(Chip8Error::Decode { .. })));
+        assert_eq!(trials[0].frames, 0);
+        assert!(trials[0].score() < 0);
+    }
+}
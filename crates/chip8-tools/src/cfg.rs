@@ -0,0 +1,195 @@
+//! Maps a ROM's control flow without running it: jumps, calls and skips
+//! are followed from the entry point and the reachable instructions are
+//! split into basic blocks, written out as a Graphviz DOT graph.
+
+use crate::flow;
+use chip8_core::instruction;
+use chip8_core::PROGRAM_START;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+pub use crate::flow::{Edge, EdgeKind};
+
+fn attributes(kind: EdgeKind) -> &'static str {
+    match kind {
+        EdgeKind::Next => "",
+        EdgeKind::Jump => " [label=\"jump\"]",
+        EdgeKind::Call => " [label=\"call\"]",
+        EdgeKind::Return => " [label=\"return\" style=dashed]",
+        EdgeKind::Skip => " [label=\"skip\"]",
+    }
+}
+
+/// Instructions that run one after the other, entered only at the first
+/// and left only after the last
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub start: u16,
+    /// Address after the last instruction
+    pub end: u16,
+    /// Where control goes after the last instruction. Returns, the end of
+    /// the program and computed jumps (BNNN) have none.
+    pub edges: Vec<Edge>,
+}
+
+/// The basic blocks reachable from the entry point, by start address
+pub fn analyze(rom: &[u8]) -> BTreeMap<u16, Block> {
+    let mut reachable: BTreeMap<u16, Vec<Edge>> = BTreeMap::new();
+    let mut leaders = BTreeSet::from([PROGRAM_START]);
+    flow::walk(rom, (PROGRAM_START, ()), |(addr, ()), _, edges| {
+        if !matches!(
+            edges[..],
+            [Edge {
+                kind: EdgeKind::Next,
+                ..
+            }]
+        ) {
+            leaders.extend(edges.iter().map(|edge| edge.to));
+        }
+        reachable.insert(addr, edges.to_vec());
+        edges.iter().map(|edge| (edge.to, ())).collect()
+    });
+    leaders
+        .iter()
+        .filter(|addr| reachable.contains_key(addr))
+        .map(|&start| {
+            let mut addr = start;
+            // Carry on while control only falls through to an instruction
+            // nothing else jumps to
+            while let [Edge {
+                to,
+                kind: EdgeKind::Next,
+            }] = reachable[&addr][..]
+            {
+                if !reachable.contains_key(&to) || leaders.contains(&to) {
+                    break;
+                }
+                addr = to;
+            }
+            let edges = reachable[&addr]
+                .iter()
+                .copied()
+                .filter(|edge| flow::word(rom, edge.to).is_some())
+                .collect();
+            let block = Block {
+                start,
+                end: addr + 2,
+                edges,
+            };
+            (start, block)
+        })
+        .collect()
+}
+
+fn node(addr: u16) -> String {
+    format!("b{:03x}", addr)
+}
+
+/// The blocks as a DOT digraph, a box per block listing its instructions
+pub fn to_dot(rom: &[u8], blocks: &BTreeMap<u16, Block>) -> String {
+    let mut dot = String::from("digraph cfg {\n    node [shape=box fontname=\"monospace\"];\n");
+    for block in blocks.values() {
+        let mut label = String::new();
+        for addr in (block.start..block.end).step_by(2) {
+            let offset = (addr - PROGRAM_START) as usize;
+            let opcode = u16::from_be_bytes([rom[offset], rom[offset + 1]]);
+            let mnemonic = instruction::mnemonic(opcode);
+            let _ = write!(label, "{:#05x}  {:04X}  {}\\l", addr, opcode, mnemonic);
+        }
+        let style = if block.start == PROGRAM_START {
+            " style=bold"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            dot,
+            "    {} [label=\"{}\"{}];",
+            node(block.start),
+            label,
+            style
+        );
+    }
+    for block in blocks.values() {
+        for edge in block.edges.iter() {
+            let _ = writeln!(
+                dot,
+                "    {} -> {}{};",
+                node(block.start),
+                node(edge.to),
+                attributes(edge.kind)
+            );
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// `chip8emu cfg ROM [OUT.dot]`, the graph goes to stdout without a path
+pub fn run(rom_path: &str, dot_path: Option<&str>) -> Result<(), String> {
+    let rom =
+        std::fs::read(rom_path).map_err(|err| format!("cannot read {}: {}", rom_path, err))?;
+    let blocks = analyze(&rom);
+    let dot = to_dot(&rom, &blocks);
+    match dot_path {
+        Some(path) => {
+            std::fs::write(path, dot).map_err(|err| format!("cannot write {}: {}", path, err))?;
+            let edges: usize = blocks.values().map(|block| block.edges.len()).sum();
+            println!("{} blocks, {} edges", blocks.len(), edges);
+        }
+        None => print!("{}", dot),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A loop around a skip, calling a subroutine after it
+    const ROM: [u8; 14] = [
+        0x60, 0x00, // 0x200: V0 = 0
+        0x70, 0x01, // 0x202: V0 += 1
+        0x30, 0x05, // 0x204: skip if V0 == 5
+        0x12, 0x02, // 0x206: jump back
+        0x22, 0x0C, // 0x208: call 0x20C
+        0x12, 0x0A, // 0x20A: spin
+        0x00, 0xEE, // 0x20C: return
+    ];
+
+    #[test]
+    fn test_analyze() {
+        let blocks = analyze(&ROM);
+        let starts: Vec<u16> = blocks.keys().copied().collect();
+        assert_eq!(starts, [0x200, 0x202, 0x206, 0x208, 0x20A, 0x20C]);
+        assert_eq!(blocks[&0x200].end, 0x202);
+        assert_eq!(blocks[&0x202].end, 0x206);
+        assert_eq!(
+            blocks[&0x202].edges,
+            [
+                Edge {
+                    to: 0x206,
+                    kind: EdgeKind::Next
+                },
+                Edge {
+                    to: 0x208,
+                    kind: EdgeKind::Skip
+                }
+            ]
+        );
+        assert_eq!(blocks[&0x208].edges.len(), 2);
+        assert!(blocks[&0x20C].edges.is_empty());
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = to_dot(&ROM, &analyze(&ROM));
+        assert!(dot.starts_with("digraph cfg {"));
+        assert!(dot.contains(
+            "    b202 [label=\"0x202  7001  ADD V0, 0x01\\l0x204  3005  SE V0, 0x05\\l\"];"
+        ));
+        assert!(dot.contains("    b200 [label=\"0x200  6000  LD V0, 0x00\\l\" style=bold];"));
+        assert!(dot.contains("    b206 -> b202 [label=\"jump\"];"));
+        assert!(dot.contains("    b208 -> b20a [label=\"return\" style=dashed];"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
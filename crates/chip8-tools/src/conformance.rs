@@ -0,0 +1,141 @@
+use chip8_core::rom::{BytesRom, RomSource};
+use chip8_core::{Chip8Interpreter, Quirks, INSTRUCTIONS_PER_FRAME};
+
+/// Upper bound on instructions per ROM before we give up waiting for it to finish
+const MAX_CYCLES: u32 = 100_000;
+
+pub(crate) struct TestRom {
+    pub(crate) name: &'static str,
+    pub(crate) data: &'static [u8],
+    pub(crate) expect: Expectation,
+}
+
+/// How to tell whether a test ROM passed, besides not faulting
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Expectation {
+    /// Address of the ROM's failure handler, reaching it means a test failed
+    pub(crate) fail_addr: Option<u16>,
+    /// Expected display hash once the ROM is done
+    pub(crate) display_hash: Option<u64>,
+}
+
+/// Display hash of the finished IBM logo
+pub(crate) const IBM_LOGO_HASH: u64 = 0x4007_26de_07b4_63f0;
+
+pub(crate) const SUITE: [TestRom; 2] = [
+    TestRom {
+        name: "IBM logo",
+        data: include_bytes!("../roms/ibmrom.ch8"),
+        expect: Expectation {
+            fail_addr: None,
+            display_hash: Some(IBM_LOGO_HASH),
+        },
+    },
+    TestRom {
+        name: "BC_test",
+        data: include_bytes!("../roms/bc_test.ch8"),
+        expect: Expectation {
+            fail_addr: Some(0x310),
+            display_hash: None,
+        },
+    },
+];
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Outcome {
+    Pass,
+    Fail(String),
+}
+
+fn run_test(rom: &TestRom, quirks: Quirks) -> Outcome {
+    let source = BytesRom {
+        name: rom.name.to_string(),
+        data: rom.data,
+    };
+    run_rom(&source, rom.expect, quirks)
+}
+
+/// Run a test ROM until it finishes, faults or runs out of cycles
+pub(crate) fn run_rom(source: &dyn RomSource, expect: Expectation, quirks: Quirks) -> Outcome {
+    let mut cpu = Chip8Interpreter::new();
+    cpu.set_quirks(quirks);
+    cpu.set_echo_display(false);
+    if let Err(err) = cpu.load_rom_from(source) {
+        return Outcome::Fail(err.to_string());
+    }
+    for cycle in 0..MAX_CYCLES {
+        if Some(cpu.pc()) == expect.fail_addr {
+            return Outcome::Fail(format!("reached failure handler at {:#05x}", cpu.pc()));
+        }
+        if cpu.halted() || cpu.is_spinning() {
+            let hash = cpu.frame_buffer().hash();
+            return match expect.display_hash {
+                Some(expected) if hash != expected => {
+                    Outcome::Fail(format!("unexpected display, hash {:016x}", hash))
+                }
+                _ => Outcome::Pass,
+            };
+        }
+        if let Err(err) = cpu.step() {
+            return Outcome::Fail(err.to_string());
+        }
+        if cycle % INSTRUCTIONS_PER_FRAME == 0 {
+            cpu.tick_timers();
+        }
+    }
+    Outcome::Fail(format!("did not finish within {} cycles", MAX_CYCLES))
+}
+
+/// Run every bundled test ROM under every quirks preset and print a scorecard
+pub fn run() {
+    let mut passed = [0; Quirks::PRESETS.len()];
+    let mut failures = vec![];
+    print!("{:<12}", "ROM");
+    for (preset, _) in Quirks::PRESETS.iter() {
+        print!("{:>12}", preset);
+    }
+    println!();
+    for rom in SUITE.iter() {
+        print!("{:<12}", rom.name);
+        for (idx, (preset, quirks)) in Quirks::PRESETS.iter().enumerate() {
+            match run_test(rom, *quirks) {
+                Outcome::Pass => {
+                    passed[idx] += 1;
+                    print!("{:>12}", "pass");
+                }
+                Outcome::Fail(reason) => {
+                    print!("{:>12}", "FAIL");
+                    failures.push(format!("{} [{}]: {}", rom.name, preset, reason));
+                }
+            }
+        }
+        println!();
+    }
+    print!("{:<12}", "score");
+    for count in passed.iter() {
+        print!("{:>12}", format!("{}/{}", count, SUITE.len()));
+    }
+    println!();
+    if !failures.is_empty() {
+        println!();
+        for failure in failures {
+            println!("{}", failure);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ibm_logo_passes() {
+        assert_eq!(run_test(&SUITE[0], Quirks::COSMAC_VIP), Outcome::Pass);
+    }
+
+    #[test]
+    fn test_bc_test_passes() {
+        // BC_test expects the CHIP-48 shift and load/store behaviour
+        assert_eq!(run_test(&SUITE[1], Quirks::CHIP_48), Outcome::Pass);
+    }
+}
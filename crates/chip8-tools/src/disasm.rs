@@ -0,0 +1,173 @@
+//! Lists a whole ROM as code and data without running it. What the
+//! sprite analysis reaches from the entry point is listed as
+//! instructions, the bytes it draws as sprites, and everything else as
+//! data, since it is only reached through computed jumps if at all.
+
+use crate::sprites::{self, Sprite};
+use chip8_core::instruction;
+use chip8_core::{Symbols, PROGRAM_START};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// What a byte of the ROM most likely is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Region {
+    Code,
+    Sprite,
+    Data,
+}
+
+/// The listing of `rom`: a line per instruction, sprite row or data word
+/// with its address and bytes, headed by a comment wherever the kind of
+/// region changes. Named addresses get a "name:" line before them.
+pub fn disassemble(rom: &[u8], symbols: &Symbols) -> String {
+    let (code, sprites) = sprites::analyze(rom);
+    let end = PROGRAM_START as usize + rom.len();
+    let byte = |addr: usize| rom[addr - PROGRAM_START as usize];
+    let mut out = String::new();
+    let mut region = None;
+    let mut addr = PROGRAM_START as usize;
+    while addr < end {
+        let sprite = sprites.iter().find(|sprite| sprite.addr as usize == addr);
+        let kind = region_at(&code, &sprites, addr as u16);
+        if let Some(name) = symbols.name(addr as u16) {
+            let _ = writeln!(out, "{}:", name);
+        }
+        if let Some(sprite) = sprite.filter(|_| kind == Region::Sprite) {
+            let _ = writeln!(out, "; {}, {} rows", sprite.label(), sprite.height);
+        } else if region != Some(kind) {
+            let _ = match kind {
+                Region::Code => writeln!(out, "; code"),
+                Region::Sprite => writeln!(out, "; sprite data"),
+                Region::Data => writeln!(out, "; data, not reached from the entry point"),
+            };
+        }
+        region = Some(kind);
+        match kind {
+            Region::Code if addr + 1 < end => {
+                let opcode = u16::from_be_bytes([byte(addr), byte(addr + 1)]);
+                let mnemonic = instruction::mnemonic(opcode);
+                let _ = writeln!(out, "{:#05x}  {:04X}  {}", addr, opcode, mnemonic);
+                addr += 2;
+            }
+            Region::Sprite => {
+                let _ = writeln!(
+                    out,
+                    "{:#05x}  {:02X}    DB 0x{:02X}  ; {}",
+                    addr,
+                    byte(addr),
+                    byte(addr),
+                    pixels(byte(addr))
+                );
+                addr += 1;
+            }
+            // Data goes in words where it can, a byte where the next one
+            // starts something else
+            _ => {
+                let word = addr + 1 < end
+                    && region_at(&code, &sprites, addr as u16 + 1) == Region::Data
+                    && symbols.name(addr as u16 + 1).is_none();
+                if word {
+                    let opcode = u16::from_be_bytes([byte(addr), byte(addr + 1)]);
+                    let _ = write!(
+                        out,
+                        "{:#05x}  {:04X}  DB 0x{:02X}, 0x{:02X}",
+                        addr,
+                        opcode,
+                        byte(addr),
+                        byte(addr + 1)
+                    );
+                    if let Ok(inst) = instruction::decode(opcode) {
+                        let _ = write!(out, "  ; {}", inst);
+                    }
+                    out.push('\n');
+                    addr += 2;
+                } else {
+                    let _ = writeln!(
+                        out,
+                        "{:#05x}  {:02X}    DB 0x{:02X}",
+                        addr,
+                        byte(addr),
+                        byte(addr)
+                    );
+                    addr += 1;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Code wins over sprites, for programs that draw their own instructions
+fn region_at(code: &BTreeSet<u16>, sprites: &[Sprite], addr: u16) -> Region {
+    if code.contains(&addr) {
+        return Region::Code;
+    }
+    let drawn = sprites
+        .iter()
+        .any(|sprite| (sprite.addr..sprite.addr + sprite.height as u16).contains(&addr));
+    if drawn && !code.contains(&addr.wrapping_sub(1)) {
+        Region::Sprite
+    } else {
+        Region::Data
+    }
+}
+
+/// A sprite row as it is drawn, "#" for a lit pixel
+fn pixels(row: u8) -> String {
+    (0..8)
+        .map(|bit| if row & (0x80 >> bit) != 0 { '#' } else { '.' })
+        .collect()
+}
+
+/// `chip8emu disasm ROM [--symbols FILE]`
+pub fn run(rom_path: &str, symbols_path: Option<&str>) -> Result<(), String> {
+    let rom =
+        std::fs::read(rom_path).map_err(|err| format!("cannot read {}: {}", rom_path, err))?;
+    let symbols = match symbols_path {
+        Some(path) => Symbols::load(path)?,
+        None => Symbols::new(),
+    };
+    print!("{}", disassemble(&rom, &symbols));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draws a sprite, spins, and has a word nothing reaches
+    const ROM: [u8; 13] = [
+        0xA2, 0x08, // 0x200: I = 0x208
+        0xD0, 0x13, // 0x202: draw 3 rows
+        0x12, 0x04, // 0x204: spin
+        0x60, 0x01, // 0x206: unreached
+        0xF0, 0x90, 0xF0, // 0x208: sprite
+        0xFF, 0xFF, // 0x20B: data
+    ];
+
+    #[test]
+    fn test_disassemble() {
+        let symbols: Symbols = "204 spin\n".parse().unwrap();
+        let listing = disassemble(&ROM, &symbols);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "; code",
+                "0x200  A208  LD I, 0x208",
+                "0x202  D013  DRW V0, V1, 3",
+                "spin:",
+                "0x204  1204  JP 0x204",
+                "; data, not reached from the entry point",
+                "0x206  6001  DB 0x60, 0x01  ; LD V0, 0x01",
+                "; sprite_208, 3 rows",
+                "0x208  F0    DB 0xF0  ; ####....",
+                "0x209  90    DB 0x90  ; #..#....",
+                "0x20a  F0    DB 0xF0  ; ####....",
+                "; data, not reached from the entry point",
+                "0x20b  FFFF  DB 0xFF, 0xFF",
+            ]
+        );
+    }
+}
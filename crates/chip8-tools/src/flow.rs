@@ -0,0 +1,146 @@
+//! Follows a ROM's control flow from the entry point without running it,
+//! for the analyses that need to know which bytes are reachable code.
+
+use chip8_core::instruction::{self, Instruction};
+use chip8_core::PROGRAM_START;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// End of the 4K address space. Whatever an oversized ROM has past it
+/// can't be reached by a jump, so it is never read as code.
+const MEMORY_END: u16 = 0x1000;
+
+/// How control gets from one instruction to another
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Falling through to the next instruction
+    Next,
+    Jump,
+    Call,
+    /// Where a call comes back to
+    Return,
+    /// A skip instruction taking its skip
+    Skip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub to: u16,
+    pub kind: EdgeKind,
+}
+
+/// The opcode at `addr`, if the ROM has a whole instruction there
+pub fn word(rom: &[u8], addr: u16) -> Option<u16> {
+    if addr.checked_add(2)? > MEMORY_END {
+        return None;
+    }
+    let offset = addr.checked_sub(PROGRAM_START)? as usize;
+    match rom.get(offset..offset + 2)? {
+        &[high, low] => Some(u16::from_be_bytes([high, low])),
+        _ => None,
+    }
+}
+
+/// Where the instruction `opcode` at `addr` can go next. Returns, the end
+/// of the program, computed jumps (BNNN) and invalid opcodes have nowhere.
+pub fn edges(addr: u16, opcode: u16) -> Vec<Edge> {
+    let edge = |to: Option<u16>, kind| to.map(|to| Edge { to, kind });
+    let next = addr.checked_add(2);
+    let skipped = next.and_then(|next| next.checked_add(2));
+    let edges = match instruction::decode(opcode) {
+        Ok(Instruction::End(_)) | Ok(Instruction::I00EE(_)) | Ok(Instruction::IBNNN(_)) => vec![],
+        // Can't tell what an invalid opcode would do
+        Err(_) => vec![],
+        Ok(Instruction::I1NNN(op)) => vec![edge(Some(op.nnn), EdgeKind::Jump)],
+        Ok(Instruction::I2NNN(op)) => {
+            vec![
+                edge(Some(op.nnn), EdgeKind::Call),
+                edge(next, EdgeKind::Return),
+            ]
+        }
+        Ok(Instruction::I3XNN(_))
+        | Ok(Instruction::I4XNN(_))
+        | Ok(Instruction::I5XY0(_))
+        | Ok(Instruction::I9XY0(_))
+        | Ok(Instruction::IEX9E(_))
+        | Ok(Instruction::IEXA1(_)) => {
+            vec![edge(next, EdgeKind::Next), edge(skipped, EdgeKind::Skip)]
+        }
+        Ok(_) => vec![edge(next, EdgeKind::Next)],
+    };
+    edges.into_iter().flatten().collect()
+}
+
+/// Visits every state reachable from `start` once. A state is an address
+/// and whatever the caller tracks along with it, e.g. what I holds there.
+/// `visit` gets each state with its opcode and the edges out of it, and
+/// returns the states those edges lead to.
+pub fn walk<T, F>(rom: &[u8], start: (u16, T), mut visit: F)
+where
+    T: Copy + Eq + Hash,
+    F: FnMut((u16, T), u16, &[Edge]) -> Vec<(u16, T)>,
+{
+    let mut seen = HashSet::new();
+    let mut queue = vec![start];
+    while let Some(state) = queue.pop() {
+        let opcode = match word(rom, state.0) {
+            Some(opcode) => opcode,
+            None => continue,
+        };
+        if !seen.insert(state) {
+            continue;
+        }
+        let edges = edges(state.0, opcode);
+        queue.extend(visit(state, opcode, &edges));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word() {
+        let rom = [0x12, 0x34, 0x56];
+        assert_eq!(word(&rom, 0x200), Some(0x1234));
+        assert_eq!(word(&rom, 0x202), None);
+        assert_eq!(word(&rom, 0x1FE), None);
+        let rom = vec![0; 0x10000];
+        assert_eq!(word(&rom, 0xFFE), Some(0));
+        assert_eq!(word(&rom, 0x1000), None);
+        assert_eq!(word(&rom, 0xFFFF), None);
+    }
+
+    #[test]
+    fn test_edges() {
+        assert_eq!(
+            edges(0x204, 0x3005),
+            [
+                Edge {
+                    to: 0x206,
+                    kind: EdgeKind::Next
+                },
+                Edge {
+                    to: 0x208,
+                    kind: EdgeKind::Skip
+                }
+            ]
+        );
+        assert!(edges(0xFFFE, 0x3005).is_empty());
+        assert_eq!(edges(0xFFFC, 0x3005).len(), 1);
+        assert!(edges(0x200, 0x00EE).is_empty());
+    }
+
+    #[test]
+    fn test_walk() {
+        // Code running to the end of memory, and on past it
+        let rom = [0x60, 0x00].repeat(0x7F00);
+        let mut count = 0;
+        walk(&rom, (PROGRAM_START, ()), |(addr, ()), _, edges| {
+            count += 1;
+            assert!(addr < MEMORY_END);
+            edges.iter().map(|edge| (edge.to, ())).collect()
+        });
+        assert_eq!(count, (MEMORY_END - PROGRAM_START) as usize / 2);
+    }
+}
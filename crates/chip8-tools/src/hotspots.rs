@@ -0,0 +1,62 @@
+use chip8_core::plugin::{AnalysisPass, Registry};
+use std::collections::HashMap;
+
+/// How many of the busiest addresses the report lists
+const REPORT_LENGTH: usize = 10;
+
+/// Counts how often each address executes, to find the loops a ROM
+/// spends its time in
+#[derive(Default)]
+pub struct Hotspots {
+    counts: HashMap<u16, u64>,
+}
+
+impl AnalysisPass for Hotspots {
+    fn name(&self) -> &str {
+        "hotspots"
+    }
+
+    fn observe(&mut self, pc: u16, _opcode: u16) {
+        *self.counts.entry(pc).or_insert(0) += 1;
+    }
+
+    fn report(&self) -> String {
+        let total: u64 = self.counts.values().sum();
+        let mut busiest: Vec<(u16, u64)> = self.counts.iter().map(|(&pc, &n)| (pc, n)).collect();
+        busiest.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let mut out = String::from("address    count  share\n");
+        for (pc, count) in busiest.into_iter().take(REPORT_LENGTH) {
+            out += &format!(
+                "{:#05x} {:>10} {:>5.1}%\n",
+                pc,
+                count,
+                count as f64 * 100. / total as f64
+            );
+        }
+        out
+    }
+}
+
+pub fn register(registry: &mut Registry) -> Result<(), String> {
+    registry.register_analysis("hotspots", Box::new(|| Box::new(Hotspots::default())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let mut hotspots = Hotspots::default();
+        for pc in [0x200, 0x202, 0x202, 0x202] {
+            hotspots.observe(pc, 0);
+        }
+        let rows: Vec<Vec<String>> = hotspots
+            .report()
+            .lines()
+            .skip(1)
+            .map(|line| line.split_whitespace().map(String::from).collect())
+            .collect();
+        assert_eq!(rows, [["0x202", "3", "75.0%"], ["0x200", "1", "25.0%"]]);
+    }
+}
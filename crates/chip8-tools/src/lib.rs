@@ -0,0 +1,20 @@
+//! Command line tools built on `chip8_core`
+
+pub mod asm;
+pub mod calibrate;
+pub mod cfg;
+pub mod conformance;
+pub mod disasm;
+pub mod flow;
+pub mod hotspots;
+pub mod octo;
+pub mod selftest;
+pub mod sprites;
+pub mod statediff;
+
+use chip8_core::plugin::Registry;
+
+/// Register the analysis passes that ship with the tools
+pub fn register_builtins(registry: &mut Registry) -> Result<(), String> {
+    hotspots::register(registry)
+}
@@ -0,0 +1,748 @@
+//! Assembles a subset of Octo, the high level assembly language most
+//! CHIP-8 programs are published in. `asm` reads sources ending in .8o
+//! with it. Words are separated by whitespace and # starts a comment.
+//!
+//! - `: name` defines a label and a bare `name` calls it. The program
+//!   starts at `: main`, with a `jump main` put first unless nothing comes
+//!   before it. Names are letters, digits and underscores.
+//! - `:alias name v3` names a register, `:const name 5` a number, and
+//!   `:call name` and `:byte 5` do what they say. A bare number or
+//!   constant emits a byte, so sprites are written as `0xF0 0x90 0xF0`.
+//! - `v0 := 5`, `v0 := v1`, `v0 := random 0xFF`, `v0 := delay`,
+//!   `v0 := key`, `v0 += 5` and `+= -= =- |= &= ^= >>= <<=` between
+//!   registers.
+//! - `i := label`, `i := hex v0`, `i += v0`, `delay := v0`,
+//!   `buzzer := v0`, `pitch := v0`, `bcd v0`, `save v3`, `load v3`,
+//!   `saveflags v3`, `loadflags v3`, `sprite v0 v1 5`, `clear`, `audio`,
+//!   `return` or `;`, `jump label` and `jump0 label`.
+//! - `if v0 == 5 then` before a single instruction, or
+//!   `if ... begin ... else ... end` around several, testing `==` or `!=`
+//!   against a number or register, or `key` or `-key`.
+//! - `loop ... again`, leaving early with a `while` condition.
+//!
+//! Macros, `:calc`, `:org`, `:next`, `:unpack`, the <, >, <= and >=
+//! comparisons and the SUPER-CHIP instructions are not supported, and
+//! using one says so.
+
+use crate::asm::{is_name, suggest, Diagnostic, Fault, Program};
+use chip8_core::instruction::Opcode;
+use chip8_core::{Symbols, PROGRAM_START};
+use std::collections::HashMap;
+
+const DIRECTIVES: [&str; 5] = [":", ":alias", ":byte", ":call", ":const"];
+
+/// Words with a meaning of their own, which can't be names
+const KEYWORDS: [&str; 26] = [
+    "again",
+    "audio",
+    "bcd",
+    "begin",
+    "buzzer",
+    "clear",
+    "delay",
+    "else",
+    "end",
+    "hex",
+    "i",
+    "if",
+    "jump",
+    "jump0",
+    "key",
+    "load",
+    "loadflags",
+    "loop",
+    "pitch",
+    "random",
+    "return",
+    "save",
+    "saveflags",
+    "sprite",
+    "then",
+    "while",
+];
+
+/// Octo features this subset leaves out, so using one says that rather
+/// than calling a label of that name
+const UNSUPPORTED: [&str; 20] = [
+    ":assert",
+    ":breakpoint",
+    ":calc",
+    ":macro",
+    ":monitor",
+    ":next",
+    ":org",
+    ":pointer",
+    ":stringmode",
+    ":unpack",
+    "bighex",
+    "exit",
+    "hires",
+    "long",
+    "lores",
+    "plane",
+    "scroll-down",
+    "scroll-left",
+    "scroll-right",
+    "scroll-up",
+];
+
+/// V0 to VF by their own names, v0 to vf
+fn vx(token: &str) -> Option<u8> {
+    let digit = token
+        .strip_prefix(|c: char| c.eq_ignore_ascii_case(&'v'))
+        .filter(|digit| digit.len() == 1)?;
+    u8::from_str_radix(digit, 16).ok()
+}
+
+fn fx(x: u8, kk: u8) -> u16 {
+    Opcode::with_x_kk(0xF, x, kk).raw
+}
+
+/// What `if` and `while` test: Vx against a byte, against Vy, or whether
+/// the key in Vx is held
+#[derive(Clone, Copy, Debug)]
+enum Test {
+    Byte(u8),
+    Register(u8),
+    Key,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Condition {
+    x: u8,
+    test: Test,
+    /// == and key rather than != and -key
+    positive: bool,
+}
+
+impl Condition {
+    /// The instruction that skips the next one when the condition is
+    /// `holds`
+    fn skip(&self, holds: bool) -> u16 {
+        let equal = self.positive == holds;
+        match self.test {
+            Test::Byte(kk) => Opcode::with_x_kk(if equal { 0x3 } else { 0x4 }, self.x, kk).raw,
+            Test::Register(y) => {
+                Opcode::with_x_y_n(if equal { 0x5 } else { 0x9 }, self.x, y, 0).raw
+            }
+            Test::Key => Opcode::with_x_kk(0xE, self.x, if equal { 0x9E } else { 0xA1 }).raw,
+        }
+    }
+}
+
+/// A block waiting for the word that closes it
+#[derive(Clone, Debug)]
+enum Block {
+    /// Where `loop` is, and the jumps out of it each `while` left to patch
+    Loop(u16, Vec<usize>),
+    /// The jump past the block to patch, and whether `else` was seen
+    If(usize, bool),
+}
+
+#[derive(Default)]
+struct Compiler<'a> {
+    tokens: Vec<&'a str>,
+    next: usize,
+    base: u16,
+    rom: Vec<u8>,
+    labels: HashMap<&'a str, u16>,
+    constants: HashMap<&'a str, i32>,
+    aliases: HashMap<&'a str, u8>,
+    /// Words waiting for the address of a label defined further down
+    fixups: Vec<(usize, &'a str)>,
+    /// Open blocks, innermost last, with the word that opened them
+    blocks: Vec<(Block, &'a str)>,
+    symbols: Symbols,
+}
+
+impl<'a> Compiler<'a> {
+    fn addr(&self) -> u16 {
+        self.base.wrapping_add(self.rom.len() as u16)
+    }
+
+    fn word(&mut self, word: u16) {
+        self.rom.extend_from_slice(&word.to_be_bytes());
+    }
+
+    /// Point the jump, call or I load at `offset` in the ROM at `addr`
+    fn patch(&mut self, offset: usize, addr: u16) {
+        let word = u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]]);
+        let word = (word & 0xF000) | addr;
+        self.rom[offset..offset + 2].copy_from_slice(&word.to_be_bytes());
+    }
+
+    fn token(&mut self) -> Result<&'a str, Fault<'a>> {
+        let token = self.tokens.get(self.next).copied().ok_or_else(|| {
+            let last = self.tokens.last().copied().unwrap_or_default();
+            Fault::new(
+                &last[last.len()..],
+                "unexpected end of the program".to_string(),
+            )
+        })?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), Fault<'a>> {
+        let token = self.token()?;
+        if token != word {
+            return Err(Fault::new(
+                token,
+                format!("expected '{}', found '{}'", word, token),
+            ));
+        }
+        Ok(())
+    }
+
+    fn is_register(&self, token: &str) -> bool {
+        self.aliases.contains_key(token) || vx(token).is_some()
+    }
+
+    fn register(&self, token: &'a str) -> Result<u8, Fault<'a>> {
+        if let Some(&x) = self.aliases.get(token) {
+            return Ok(x);
+        }
+        vx(token)
+            .ok_or_else(|| Fault::new(token, format!("expected a register, found '{}'", token)))
+    }
+
+    fn next_register(&mut self) -> Result<u8, Fault<'a>> {
+        let token = self.token()?;
+        self.register(token)
+    }
+
+    /// A name for a label, register or constant
+    fn name(&self, token: &'a str) -> Result<&'a str, Fault<'a>> {
+        if !is_name(token) || vx(token).is_some() || KEYWORDS.contains(&token) {
+            return Err(Fault::new(token, format!("invalid name '{}'", token)));
+        }
+        Ok(token)
+    }
+
+    fn undefined(&self, token: &'a str) -> Fault<'a> {
+        let mut known: Vec<&str> = self
+            .labels
+            .keys()
+            .chain(self.constants.keys())
+            .copied()
+            .collect();
+        known.sort_unstable();
+        Fault::new(token, format!("undefined name '{}'", token)).hint(suggest(token, known))
+    }
+
+    /// A number in decimal, 0x hex or 0b binary, a constant or a label
+    /// defined further up
+    fn value(&self, token: &'a str) -> Result<i32, Fault<'a>> {
+        if let Some(&value) = self.constants.get(token) {
+            return Ok(value);
+        }
+        if let Some(&addr) = self.labels.get(token) {
+            return Ok(addr as i32);
+        }
+        let (sign, digits) = match token.strip_prefix('-') {
+            Some(digits) => (-1, digits),
+            None => (1, token),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i32::from_str_radix(hex, 16)
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i32::from_str_radix(binary, 2)
+        } else {
+            digits.parse()
+        };
+        match value {
+            Ok(value) => Ok(sign * value),
+            Err(_) if is_name(token) => Err(self.undefined(token)),
+            Err(_) => Err(Fault::new(token, format!("invalid number '{}'", token))),
+        }
+    }
+
+    /// Negative bytes are two's complement, so `v0 += -1` counts down
+    fn byte(&self, token: &'a str) -> Result<u8, Fault<'a>> {
+        match self.value(token)? {
+            value @ -128..=255 => Ok(value as u8),
+            value => Err(Fault::new(
+                token,
+                format!("byte {} is out of range, -128 to 255", value),
+            )),
+        }
+    }
+
+    fn nibble(&self, token: &'a str) -> Result<u8, Fault<'a>> {
+        match self.value(token)? {
+            value @ 0..=0xF => Ok(value as u8),
+            value => Err(Fault::new(
+                token,
+                format!("nibble {} is out of range, 0 to 15", value),
+            )),
+        }
+    }
+
+    /// An instruction taking an address, such as a jump, which can be to
+    /// a label further down
+    fn address(&mut self, family: u8, token: &'a str) -> Result<(), Fault<'a>> {
+        let forward = is_name(token)
+            && !self.labels.contains_key(token)
+            && !self.constants.contains_key(token);
+        let nnn = if forward {
+            self.fixups.push((self.rom.len(), token));
+            0
+        } else {
+            match self.value(token)? {
+                value @ 0..=0xFFF => value as u16,
+                value => {
+                    return Err(Fault::new(
+                        token,
+                        format!("address {:#X} is out of range, at most 0xFFF", value),
+                    ))
+                }
+            }
+        };
+        self.word(Opcode::with_nnn(family, nnn).raw);
+        Ok(())
+    }
+
+    /// Vx, then == or != and a byte or register, or key or -key
+    fn condition(&mut self) -> Result<Condition, Fault<'a>> {
+        let x = self.next_register()?;
+        let op = self.token()?;
+        let test = match op {
+            "key" | "-key" => Test::Key,
+            "==" | "!=" => {
+                let operand = self.token()?;
+                if self.is_register(operand) {
+                    Test::Register(self.register(operand)?)
+                } else {
+                    Test::Byte(self.byte(operand)?)
+                }
+            }
+            _ => {
+                return Err(Fault::new(
+                    op,
+                    format!("unknown test '{}', expected ==, !=, key or -key", op),
+                ))
+            }
+        };
+        Ok(Condition {
+            x,
+            test,
+            positive: op == "==" || op == "key",
+        })
+    }
+
+    /// An assignment to Vx or arithmetic on it
+    fn assignment(&mut self, x: u8) -> Result<(), Fault<'a>> {
+        let op = self.token()?;
+        let operand = self.token()?;
+        let word = match op {
+            ":=" => match operand {
+                "random" => {
+                    let mask = self.token()?;
+                    Opcode::with_x_kk(0xC, x, self.byte(mask)?).raw
+                }
+                "delay" => fx(x, 0x07),
+                "key" => fx(x, 0x0A),
+                _ if self.is_register(operand) => {
+                    Opcode::with_x_y_n(0x8, x, self.register(operand)?, 0x0).raw
+                }
+                _ => Opcode::with_x_kk(0x6, x, self.byte(operand)?).raw,
+            },
+            "+=" if !self.is_register(operand) => {
+                Opcode::with_x_kk(0x7, x, self.byte(operand)?).raw
+            }
+            _ => {
+                let n = match op {
+                    "|=" => 0x1,
+                    "&=" => 0x2,
+                    "^=" => 0x3,
+                    "+=" => 0x4,
+                    "-=" => 0x5,
+                    ">>=" => 0x6,
+                    "=-" => 0x7,
+                    "<<=" => 0xE,
+                    _ => return Err(Fault::new(op, format!("unknown operator '{}'", op))),
+                };
+                Opcode::with_x_y_n(0x8, x, self.register(operand)?, n).raw
+            }
+        };
+        self.word(word);
+        Ok(())
+    }
+
+    /// Compile the statement starting at the next token
+    fn statement(&mut self) -> Result<(), Fault<'a>> {
+        let token = self.token()?;
+        match token {
+            ":" => {
+                let name = self.token()?;
+                let name = self.name(name)?;
+                let addr = self.addr();
+                if self.labels.insert(name, addr).is_some() {
+                    return Err(Fault::new(name, format!("'{}' is already defined", name)));
+                }
+                self.symbols.insert(addr, name);
+            }
+            ":alias" => {
+                let name = self.token()?;
+                let name = self.name(name)?;
+                let x = self.next_register()?;
+                self.aliases.insert(name, x);
+            }
+            ":const" => {
+                let name = self.token()?;
+                let name = self.name(name)?;
+                let value = self.token()?;
+                let value = self.value(value)?;
+                self.constants.insert(name, value);
+            }
+            ":call" => {
+                let target = self.token()?;
+                self.address(0x2, target)?;
+            }
+            ":byte" => {
+                let value = self.token()?;
+                let byte = self.byte(value)?;
+                self.rom.push(byte);
+            }
+            "clear" => self.word(0x00E0),
+            "return" | ";" => self.word(0x00EE),
+            "audio" => self.word(0xF002),
+            "jump" | "jump0" => {
+                let target = self.token()?;
+                self.address(if token == "jump" { 0x1 } else { 0xB }, target)?;
+            }
+            "bcd" | "save" | "load" | "saveflags" | "loadflags" => {
+                let x = self.next_register()?;
+                let kk = match token {
+                    "bcd" => 0x33,
+                    "save" => 0x55,
+                    "load" => 0x65,
+                    "saveflags" => 0x75,
+                    _ => 0x85,
+                };
+                self.word(fx(x, kk));
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.next_register()?;
+                let kk = match token {
+                    "delay" => 0x15,
+                    "buzzer" => 0x18,
+                    _ => 0x3A,
+                };
+                self.word(fx(x, kk));
+            }
+            "sprite" => {
+                let x = self.next_register()?;
+                let y = self.next_register()?;
+                let n = self.token()?;
+                let n = self.nibble(n)?;
+                self.word(Opcode::with_x_y_n(0xD, x, y, n).raw);
+            }
+            "i" => {
+                let op = self.token()?;
+                match op {
+                    ":=" => match self.token()? {
+                        "hex" => {
+                            let x = self.next_register()?;
+                            self.word(fx(x, 0x29));
+                        }
+                        target => self.address(0xA, target)?,
+                    },
+                    "+=" => {
+                        let x = self.next_register()?;
+                        self.word(fx(x, 0x1E));
+                    }
+                    _ => return Err(Fault::new(op, format!("unknown operator '{}' for i", op))),
+                }
+            }
+            "if" => {
+                let condition = self.condition()?;
+                let word = self.token()?;
+                match word {
+                    "then" => {
+                        self.word(condition.skip(false));
+                        let start = self.rom.len();
+                        let next = self.tokens.get(self.next).copied().unwrap_or(word);
+                        self.statement()?;
+                        if self.rom.len() - start != 2 {
+                            return Err(Fault::new(
+                                next,
+                                "'then' must be followed by a single instruction".to_string(),
+                            ));
+                        }
+                    }
+                    "begin" => {
+                        self.word(condition.skip(true));
+                        self.blocks.push((Block::If(self.rom.len(), false), token));
+                        self.word(0x1000);
+                    }
+                    _ => {
+                        return Err(Fault::new(
+                            word,
+                            format!("expected 'then' or 'begin', found '{}'", word),
+                        ))
+                    }
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some((Block::If(jump, false), start)) => {
+                    let end = self.rom.len();
+                    self.word(0x1000);
+                    let addr = self.addr();
+                    self.patch(jump, addr);
+                    self.blocks.push((Block::If(end, true), start));
+                }
+                _ => {
+                    let message = "'else' without 'if ... begin'".to_string();
+                    return Err(Fault::new(token, message));
+                }
+            },
+            "end" => match self.blocks.pop() {
+                Some((Block::If(jump, _), _)) => {
+                    let addr = self.addr();
+                    self.patch(jump, addr);
+                }
+                _ => {
+                    let message = "'end' without 'if ... begin'".to_string();
+                    return Err(Fault::new(token, message));
+                }
+            },
+            "loop" => {
+                let start = self.addr();
+                self.blocks.push((Block::Loop(start, vec![]), token));
+            }
+            // Jumps out of the loop unless the condition holds
+            "while" => {
+                let condition = self.condition()?;
+                self.word(condition.skip(true));
+                let jump = self.rom.len();
+                self.word(0x1000);
+                let innermost = self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find_map(|(block, _)| match block {
+                        Block::Loop(_, breaks) => Some(breaks),
+                        Block::If(..) => None,
+                    });
+                match innermost {
+                    Some(breaks) => breaks.push(jump),
+                    None => return Err(Fault::new(token, "'while' outside of a loop".to_string())),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some((Block::Loop(start, breaks), _)) => {
+                    self.word(Opcode::with_nnn(0x1, start).raw);
+                    let addr = self.addr();
+                    for jump in breaks {
+                        self.patch(jump, addr);
+                    }
+                }
+                _ => return Err(Fault::new(token, "'again' without 'loop'".to_string())),
+            },
+            _ if self.is_register(token) => {
+                let x = self.register(token)?;
+                self.assignment(x)?;
+            }
+            _ if UNSUPPORTED.contains(&token) => {
+                return Err(Fault::new(token, format!("'{}' is not supported", token)))
+            }
+            _ if token.starts_with(':') => {
+                let message = format!("unknown directive '{}'", token);
+                let hint = suggest(token, DIRECTIVES.iter().copied());
+                return Err(Fault::new(token, message).hint(hint));
+            }
+            _ if self.constants.contains_key(token)
+                || token.starts_with(|c: char| c.is_ascii_digit() || c == '-') =>
+            {
+                let byte = self.byte(token)?;
+                self.rom.push(byte);
+            }
+            _ if is_name(token) && !KEYWORDS.contains(&token) => self.address(0x2, token)?,
+            _ => return Err(Fault::new(token, format!("unexpected '{}'", token))),
+        }
+        Ok(())
+    }
+}
+
+/// The program for `source`, starting with a jump to main or not
+fn compile(source: &str, jump_to_main: bool) -> Result<Program, Fault<'_>> {
+    let tokens = source
+        .lines()
+        .flat_map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+        })
+        .collect();
+    let mut compiler = Compiler {
+        tokens,
+        base: PROGRAM_START,
+        ..Compiler::default()
+    };
+    if jump_to_main {
+        compiler.word(0x1000);
+    }
+    while compiler.next < compiler.tokens.len() {
+        compiler.statement()?;
+    }
+    if let Some((block, token)) = compiler.blocks.pop() {
+        let message = match block {
+            Block::Loop(..) => "'loop' without 'again'",
+            Block::If(..) => "'if ... begin' without 'end'",
+        };
+        return Err(Fault::new(token, message.to_string()));
+    }
+    let main = compiler.labels.get("main").copied().ok_or_else(|| {
+        Fault::new(
+            &source[..0],
+            "there is no ': main' label to start at".to_string(),
+        )
+    })?;
+    if jump_to_main {
+        compiler.patch(0, main);
+    }
+    for (offset, name) in std::mem::take(&mut compiler.fixups) {
+        match compiler.labels.get(name) {
+            Some(&addr) => compiler.patch(offset, addr),
+            None => return Err(compiler.undefined(name)),
+        }
+    }
+    if PROGRAM_START as usize + compiler.rom.len() > 0x1000 {
+        let last = compiler.tokens.last().copied().unwrap_or_default();
+        return Err(Fault::new(
+            last,
+            "the program doesn't fit in memory".to_string(),
+        ));
+    }
+    Ok(Program {
+        rom: compiler.rom,
+        symbols: compiler.symbols,
+    })
+}
+
+/// The program for Octo `source`, or the first error in it
+pub fn assemble(source: &str) -> Result<Program, Diagnostic> {
+    let locate = |fault| Diagnostic::locate(source, fault);
+    let program = compile(source, true).map_err(locate)?;
+    // Nothing comes before main, so the jump to it can go
+    if program.symbols.address("main") == Some(PROGRAM_START + 2) {
+        return compile(source, false).map_err(locate);
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            # count down, drawing a digit each time
+            :alias counter v3
+            :const ROWS 3
+            : digit
+                0xF0 0x90 0xF0 0x00
+            : main
+                clear
+                counter := 10
+                i := digit
+                loop
+                    sprite v0 v1 ROWS
+                    counter += -1
+                    while counter != 0
+                again
+                if v0 == 1 then v1 := key
+            : spin
+                jump spin
+        ";
+        let program = assemble(source).unwrap();
+        assert_eq!(
+            program.rom,
+            [
+                0x12, 0x06, 0xF0, 0x90, 0xF0, 0x00, 0x00, 0xE0, 0x63, 0x0A, 0xA2, 0x02, 0xD0, 0x13,
+                0x73, 0xFF, 0x43, 0x00, 0x12, 0x16, 0x12, 0x0C, 0x40, 0x01, 0xF1, 0x0A, 0x12, 0x1A,
+            ]
+        );
+        assert_eq!(
+            program.symbols.to_string(),
+            "0x202 digit\n0x206 main\n0x21a spin\n"
+        );
+    }
+
+    #[test]
+    fn test_blocks_and_calls() {
+        // main comes first, so there is no jump to it
+        let source = "
+            : main
+                if v0 != v1 begin
+                    v2 := v1
+                else
+                    v2 ^= v2
+                end
+                i += v2
+                show
+            : show
+                bcd v2 ;
+        ";
+        assert_eq!(
+            assemble(source).unwrap().rom,
+            [
+                0x90, 0x10, 0x12, 0x08, 0x82, 0x10, 0x12, 0x0A, 0x82, 0x23, 0xF2, 0x1E, 0x22, 0x0E,
+                0xF2, 0x33, 0x00, 0xEE,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shifts() {
+        // 8XY6 is SHR and 8XYE is SHL
+        assert_eq!(
+            assemble(": main v1 >>= v2 v1 <<= v2").unwrap().rom,
+            [0x81, 0x26, 0x81, 0x2E]
+        );
+    }
+
+    /// Where the error in `source` is and what it says
+    fn error(source: &str) -> (usize, usize, String) {
+        let err = assemble(source).unwrap_err();
+        (err.line, err.column, err.message)
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error("v0 := 1"),
+            (1, 1, "there is no ': main' label to start at".to_string())
+        );
+        assert_eq!(
+            error(": main\n  loop v0 += 1"),
+            (2, 3, "'loop' without 'again'".to_string())
+        );
+        assert_eq!(
+            error(": main\n  v0 := 256"),
+            (2, 9, "byte 256 is out of range, -128 to 255".to_string())
+        );
+        assert_eq!(
+            error(": main\n  if v0 == 1 then : x"),
+            (
+                2,
+                19,
+                "'then' must be followed by a single instruction".to_string()
+            )
+        );
+        assert_eq!(
+            error(": main\n  :macro x"),
+            (2, 3, "':macro' is not supported".to_string())
+        );
+        let err = assemble(": main\n  jump mian").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 8));
+        assert_eq!(err.hint.as_deref(), Some("did you mean 'main'?"));
+        let err = assemble(": main\n  :alais x v1").unwrap_err();
+        assert_eq!(err.message, "unknown directive ':alais'");
+        assert_eq!(err.hint.as_deref(), Some("did you mean ':alias'?"));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::conformance::{self, Expectation, Outcome, IBM_LOGO_HASH, SUITE};
+use chip8_core::rom::{self, BytesRom, RomSource};
+use chip8_core::Quirks;
+
+/// Public-domain test ROMs fetched when the build can download, from
+/// Timendus' CHIP-8 test suite, with the hash of their finished display
+/// where a known-good one has been recorded
+const DOWNLOADS: [(&str, &str, Option<u64>); 4] = [
+    (
+        "CHIP-8 logo",
+        "https://github.com/Timendus/chip8-test-suite/raw/main/bin/1-chip8-logo.ch8",
+        None,
+    ),
+    (
+        "IBM logo (suite)",
+        "https://github.com/Timendus/chip8-test-suite/raw/main/bin/2-ibm-logo.ch8",
+        Some(IBM_LOGO_HASH),
+    ),
+    (
+        "Corax+ opcodes",
+        "https://github.com/Timendus/chip8-test-suite/raw/main/bin/3-corax+.ch8",
+        None,
+    ),
+    (
+        "Flags",
+        "https://github.com/Timendus/chip8-test-suite/raw/main/bin/4-flags.ch8",
+        None,
+    ),
+];
+
+/// Result of one ROM in the self test
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub outcome: Result<(), String>,
+    /// Whether the ROM's result was checked, against a known-good final
+    /// display or its failure handler. Without either, an Ok outcome only
+    /// means the ROM ran to its end.
+    pub verified: bool,
+}
+
+/// Run the vendored test ROMs, plus the downloadable ones unless `offline`
+/// or the build cannot fetch URLs. A ROM passes when its final display
+/// matches the known-good one; ROMs without one are reported as unverified.
+pub fn checks(quirks: Quirks, offline: bool) -> Vec<Check> {
+    let mut checks = vec![];
+    for rom in SUITE.iter() {
+        let source = BytesRom {
+            name: rom.name.to_string(),
+            data: rom.data,
+        };
+        checks.push(check(&source, rom.expect, quirks));
+    }
+    if !offline && rom::supports_urls() {
+        for &(name, url, display_hash) in DOWNLOADS.iter() {
+            let expect = Expectation {
+                fail_addr: None,
+                display_hash,
+            };
+            let check = match rom::open(url).read() {
+                Ok(data) => {
+                    let source = BytesRom {
+                        name: name.to_string(),
+                        data: &data,
+                    };
+                    check(&source, expect, quirks)
+                }
+                Err(err) => Check {
+                    name: name.to_string(),
+                    outcome: Err(format!("could not download: {}", err)),
+                    verified: false,
+                },
+            };
+            checks.push(check);
+        }
+    }
+    checks
+}
+
+fn check(source: &dyn RomSource, expect: Expectation, quirks: Quirks) -> Check {
+    let outcome = match conformance::run_rom(source, expect, quirks) {
+        Outcome::Pass => Ok(()),
+        Outcome::Fail(reason) => Err(reason),
+    };
+    Check {
+        name: source.name(),
+        outcome,
+        verified: expect.display_hash.is_some() || expect.fail_addr.is_some(),
+    }
+}
+
+/// Friendly summary for `chip8emu selftest`
+pub fn report(checks: &[Check], offline: bool) -> String {
+    let mut out = String::new();
+    for check in checks {
+        match &check.outcome {
+            Ok(()) if check.verified => out.push_str(&format!("  ok    {}\n", check.name)),
+            Ok(()) => out.push_str(&format!("  ran   {}\n", check.name)),
+            Err(reason) => out.push_str(&format!("  FAIL  {}: {}\n", check.name, reason)),
+        }
+    }
+    let passed = checks
+        .iter()
+        .filter(|check| check.outcome.is_ok() && check.verified)
+        .count();
+    let failed = checks.iter().filter(|check| check.outcome.is_err()).count();
+    out.push_str(&format!(
+        "\n{} of {} test ROMs passed.\n",
+        passed,
+        checks.len()
+    ));
+    if passed + failed < checks.len() {
+        out.push_str(&format!(
+            "{} more ran to the end, but have no known-good display to compare against.\n",
+            checks.len() - passed - failed
+        ));
+    }
+    if failed == 0 {
+        out.push_str("Your build works with these settings.\n");
+    } else {
+        out.push_str(
+            "Some ROMs failed. These test ROMs don't rely on quirks, so this \
+             points at a bug rather than your settings: please report it with \
+             the output of `chip8emu conformance`.\n",
+        );
+    }
+    if !offline && !rom::supports_urls() {
+        out.push_str(
+            "Only the bundled ROMs were run: build with --features http to \
+             also fetch the public test suite.\n",
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_checks() {
+        let checks = checks(Quirks::COSMAC_VIP, true);
+        assert_eq!(checks.len(), SUITE.len());
+        assert_eq!(
+            checks[0],
+            Check {
+                name: String::from("IBM logo"),
+                outcome: Ok(()),
+                verified: true
+            }
+        );
+        assert!(report(&checks, true).contains("of 2 test ROMs passed"));
+    }
+
+    #[test]
+    fn test_report() {
+        let result = |name: &str, outcome, verified| Check {
+            name: name.to_string(),
+            outcome,
+            verified,
+        };
+        let checks = [
+            result("IBM logo", Ok(()), true),
+            result("Flags", Ok(()), false),
+        ];
+        let text = report(&checks, true);
+        assert!(text.contains("  ok    IBM logo\n"));
+        assert!(text.contains("  ran   Flags\n"));
+        assert!(text.contains("1 of 2 test ROMs passed."));
+        assert!(text.contains("1 more ran to the end"));
+        assert!(text.contains("Your build works"));
+        let failure = Err(String::from("unexpected display"));
+        let checks = [result("IBM logo", failure, true)];
+        assert!(report(&checks, true).contains("points at a bug"));
+    }
+}
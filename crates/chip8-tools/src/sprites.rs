@@ -0,0 +1,236 @@
+//! Finds the sprite data in a ROM without running it: control flow is
+//! followed from the entry point, tracking what ANNN last loaded into I,
+//! and every DXYN reached with a known I marks the bytes it draws.
+
+use crate::flow::{self, EdgeKind};
+use chip8_core::instruction::{self, Instruction};
+use chip8_core::PROGRAM_START;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// Sprites per row of the sheet
+const SHEET_COLUMNS: usize = 8;
+/// Cells are one sprite wide plus a gap, and as tall as the tallest sprite
+const CELL_WIDTH: usize = 9;
+/// Size of a sprite pixel in the sheet
+const SHEET_SCALE: usize = 4;
+const SHEET_BACKGROUND: u8 = 0x40;
+
+/// Sprite data drawn by the program, as found by `analyze`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sprite {
+    pub addr: u16,
+    /// Rows drawn, the largest N of the DXYN instructions using it
+    pub height: u8,
+    /// Whether the bytes were also reached as code, e.g. when a program
+    /// draws its own instructions for a noise effect
+    pub overlaps_code: bool,
+}
+
+impl Sprite {
+    /// Name used in reports and by tools that consume them
+    pub fn label(&self) -> String {
+        format!("sprite_{:03x}", self.addr)
+    }
+}
+
+/// Addresses of the instructions reachable from the entry point, and the
+/// sprites they draw
+pub fn analyze(rom: &[u8]) -> (BTreeSet<u16>, Vec<Sprite>) {
+    let end = PROGRAM_START as usize + rom.len();
+    let mut code = BTreeSet::new();
+    let mut heights: BTreeMap<u16, u8> = BTreeMap::new();
+    // States are an address and I there if known
+    let start = (PROGRAM_START, None::<u16>);
+    flow::walk(rom, start, |(addr, i), opcode, edges| {
+        code.insert(addr);
+        let i = match instruction::decode(opcode) {
+            Ok(Instruction::IANNN(op)) => Some(op.nnn),
+            Ok(Instruction::IDXYN(op)) => {
+                let height = op.n;
+                if let Some(i) = i.filter(|_| height > 0) {
+                    let entry = heights.entry(i).or_insert(0);
+                    *entry = (*entry).max(height);
+                }
+                i
+            }
+            // Anything else that moves I: FX1E, FX29 and, with some quirks,
+            // FX55 and FX65
+            Ok(Instruction::IFX1E(_))
+            | Ok(Instruction::IFX29(_))
+            | Ok(Instruction::IFX55(_))
+            | Ok(Instruction::IFX65(_)) => None,
+            _ => i,
+        };
+        edges
+            .iter()
+            .map(|edge| match edge.kind {
+                // The subroutine may change I before returning
+                EdgeKind::Return => (edge.to, None),
+                _ => (edge.to, i),
+            })
+            .collect()
+    });
+    let is_code =
+        |byte_addr: u16| code.contains(&byte_addr) || code.contains(&byte_addr.wrapping_sub(1));
+    let sprites = heights
+        .into_iter()
+        .filter(|&(addr, _)| addr >= PROGRAM_START && (addr as usize) < end)
+        .map(|(addr, height)| Sprite {
+            addr,
+            height: height.min((end - addr as usize).min(255) as u8),
+            overlaps_code: (addr..addr + height as u16).any(is_code),
+        })
+        .collect();
+    (code, sprites)
+}
+
+/// One line per sprite: label, address, rows and cell in the sheet
+pub fn report(sprites: &[Sprite]) -> String {
+    let mut out = String::from("label        address rows  sheet cell\n");
+    for (index, sprite) in sprites.iter().enumerate() {
+        out += &format!(
+            "{:<12} {:#05x} {:>5}  row {}, column {}{}\n",
+            sprite.label(),
+            sprite.addr,
+            sprite.height,
+            index / SHEET_COLUMNS,
+            index % SHEET_COLUMNS,
+            if sprite.overlaps_code {
+                "  (also code)"
+            } else {
+                ""
+            }
+        );
+    }
+    out
+}
+
+/// Grayscale pixels of the sprite sheet, with its width and height
+pub fn sheet(rom: &[u8], sprites: &[Sprite]) -> (usize, usize, Vec<u8>) {
+    let cell_height = sprites.iter().map(|s| s.height as usize).max().unwrap_or(0) + 1;
+    let rows = sprites.len().div_ceil(SHEET_COLUMNS);
+    let width = SHEET_COLUMNS * CELL_WIDTH * SHEET_SCALE;
+    let height = rows * cell_height * SHEET_SCALE;
+    let mut pixels = vec![SHEET_BACKGROUND; width * height];
+    for (index, sprite) in sprites.iter().enumerate() {
+        let left = index % SHEET_COLUMNS * CELL_WIDTH;
+        let top = index / SHEET_COLUMNS * cell_height;
+        for row in 0..sprite.height as usize {
+            let byte = rom[(sprite.addr - PROGRAM_START) as usize + row];
+            for bit in 0..8 {
+                let value = if byte & (0x80 >> bit) != 0 {
+                    0xFF
+                } else {
+                    0x00
+                };
+                for dy in 0..SHEET_SCALE {
+                    let y = (top + row) * SHEET_SCALE + dy;
+                    let x = (left + bit) * SHEET_SCALE;
+                    pixels[y * width + x..y * width + x + SHEET_SCALE].fill(value);
+                }
+            }
+        }
+    }
+    (width, height, pixels)
+}
+
+/// Write the sprite sheet as a PNG, in the order `report` lists them
+pub fn save_sheet(rom: &[u8], sprites: &[Sprite], path: &Path) -> io::Result<()> {
+    let (width, height, pixels) = sheet(rom, sprites);
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height.max(1) as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    let pixels = if pixels.is_empty() {
+        vec![SHEET_BACKGROUND; width]
+    } else {
+        pixels
+    };
+    writer.write_image_data(&pixels).map_err(io::Error::other)
+}
+
+/// `chip8emu sprites ROM [SHEET.png]`
+pub fn run(rom_path: &str, sheet_path: Option<&str>) -> Result<(), String> {
+    let rom =
+        std::fs::read(rom_path).map_err(|err| format!("cannot read {}: {}", rom_path, err))?;
+    let (code, sprites) = analyze(&rom);
+    println!(
+        "{} instructions reachable, {} sprites",
+        code.len(),
+        sprites.len()
+    );
+    print!("{}", report(&sprites));
+    if let Some(path) = sheet_path {
+        save_sheet(&rom, &sprites, Path::new(path))
+            .map_err(|err| format!("cannot write {}: {}", path, err))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draws one sprite through I = 0x20A, skips over the data with a
+    /// jump, and draws a second through a subroutine
+    const ROM: [u8; 22] = [
+        0xA2, 0x0A, // 0x200: I = 0x20A
+        0xD0, 0x13, // 0x202: draw 3 rows
+        0x22, 0x0E, // 0x204: call 0x20E
+        0x12, 0x06, // 0x206: spin
+        0x00, 0x00, // 0x208
+        0xF0, 0x90, 0xF0, // 0x20A: sprite
+        0x00, // 0x20D
+        0xA2, 0x14, // 0x20E: I = 0x214
+        0xD0, 0x12, // 0x210: draw 2 rows
+        0x00, 0xEE, // 0x212: return
+        0x81, 0x42, // 0x214: sprite
+    ];
+
+    #[test]
+    fn test_analyze() {
+        let (code, sprites) = analyze(&ROM);
+        assert!(code.contains(&0x212) && !code.contains(&0x20A));
+        assert_eq!(
+            sprites,
+            [
+                Sprite {
+                    addr: 0x20A,
+                    height: 3,
+                    overlaps_code: false
+                },
+                Sprite {
+                    addr: 0x214,
+                    height: 2,
+                    overlaps_code: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_end_of_rom() {
+        // The sprite starts 256 bytes before the end of the ROM
+        let mut rom = vec![0xA2, 0x04, 0xD0, 0x15, 0x12, 0x04];
+        rom.resize(4 + 256, 0);
+        assert_eq!(analyze(&rom).1[0].height, 5);
+        // Code running past the end of memory
+        let rom = [0x60, 0x00].repeat(0x7F00);
+        assert_eq!(analyze(&rom).0.len(), 0x700);
+    }
+
+    #[test]
+    fn test_sheet() {
+        let (_, sprites) = analyze(&ROM);
+        let (width, height, pixels) = sheet(&ROM, &sprites);
+        assert_eq!((width, height), (8 * 9 * 4, 4 * 4));
+        // Top left pixel of the first sprite is lit, the gap after it isn't
+        assert_eq!(pixels[0], 0xFF);
+        assert_eq!(pixels[8 * 4], SHEET_BACKGROUND);
+        assert!(report(&sprites).contains("sprite_214"));
+    }
+}
@@ -0,0 +1,96 @@
+use chip8_core::MachineState;
+
+/// Print the differences between two saved machine states
+pub fn run(path_a: &str, path_b: &str) -> Result<(), String> {
+    let a = MachineState::load(path_a)?;
+    let b = MachineState::load(path_b)?;
+    print!("{}", diff(&a, &b));
+    Ok(())
+}
+
+fn diff(a: &MachineState, b: &MachineState) -> String {
+    let mut out = String::from("registers:\n");
+    let mut registers: Vec<(String, u16, u16)> = (0..16)
+        .map(|x| {
+            (
+                format!("V{:X}", x),
+                a.registers_v[x] as u16,
+                b.registers_v[x] as u16,
+            )
+        })
+        .collect();
+    registers.push((String::from("I"), a.register_i, b.register_i));
+    registers.push((String::from("PC"), a.register_pc, b.register_pc));
+    registers.push((String::from("DT"), a.delay_timer, b.delay_timer));
+    registers.push((String::from("ST"), a.sound_timer, b.sound_timer));
+    for (name, before, after) in registers {
+        if before != after {
+            out += &format!("  {:<3} {:#06x} -> {:#06x}\n", name, before, after);
+        }
+    }
+    if a.stack != b.stack {
+        out += &format!("  stack {:x?} -> {:x?}\n", a.stack, b.stack);
+    }
+    if a.rom_hash != b.rom_hash {
+        out += &format!("  rom hash {:x?} -> {:x?}\n", a.rom_hash, b.rom_hash);
+    }
+    if a.quirks != b.quirks {
+        out += &format!("  quirks {:?} -> {:?}\n", a.quirks, b.quirks);
+    }
+
+    let changed: Vec<usize> = (0..a.mem.len()).filter(|&i| a.mem[i] != b.mem[i]).collect();
+    out += &format!("memory ({} bytes changed):\n", changed.len());
+    for addr in changed {
+        out += &format!(
+            "  {:#05x}: {:#04x} -> {:#04x}\n",
+            addr, a.mem[addr], b.mem[addr]
+        );
+    }
+
+    let mut xor = String::new();
+    let mut differing = 0;
+    for (row_a, row_b) in a.frame_buffer.rows().zip(b.frame_buffer.rows()) {
+        for (pixel_a, pixel_b) in row_a.iter().zip(row_b.iter()) {
+            if pixel_a != pixel_b {
+                differing += 1;
+                xor.push('■');
+            } else {
+                xor.push('·');
+            }
+        }
+        xor.push('\n');
+    }
+    out += &format!("display XOR ({} pixels differ):\n", differing);
+    if differing > 0 {
+        out += &xor;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::rom::BytesRom;
+    use chip8_core::Chip8Interpreter;
+
+    #[test]
+    fn test_diff() {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        // 6305 A000 D001: V3 = 5, I = 0, draw one row of the "0" glyph
+        cpu.load_rom_from(&BytesRom {
+            name: String::from("diff"),
+            data: &[0x63, 0x05, 0xA0, 0x00, 0xD0, 0x01],
+        })
+        .unwrap();
+        let before = cpu.state();
+        for _ in 0..3 {
+            cpu.step().unwrap();
+        }
+        let out = diff(&before, &cpu.state());
+        assert!(out.contains("V3  0x0000 -> 0x0005"));
+        assert!(out.contains("PC  0x0200 -> 0x0206"));
+        assert!(out.contains("memory (0 bytes changed)"));
+        assert!(out.contains("display XOR (4 pixels differ)"));
+    }
+}
@@ -0,0 +1,75 @@
+//! Plug a renderer of your own into the registry, next to the built-in
+//! scale filters, and use it the way the window frontend does.
+//!
+//!     cargo run --example custom_renderer
+
+use chip8_core::plugin::{Registry, Renderer};
+use chip8_core::rom::BytesRom;
+use chip8_core::{Chip8Interpreter, FrameBuffer, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+
+/// Draws every CHIP-8 pixel as a square with a one pixel dark outline,
+/// like the LCD grid of a handheld
+struct Scanlines {
+    lit: u32,
+    unlit: u32,
+}
+
+impl Renderer for Scanlines {
+    fn render(&mut self, frame_buffer: &FrameBuffer, width: usize, height: usize) -> Vec<u32> {
+        let scale = (width / FRAME_BUFFER_WIDTH)
+            .min(height / FRAME_BUFFER_HEIGHT)
+            .max(1);
+        let mut out = vec![0; width * height];
+        for y in 0..height.min(FRAME_BUFFER_HEIGHT * scale) {
+            for x in 0..width.min(FRAME_BUFFER_WIDTH * scale) {
+                let edge = x % scale == scale - 1 || y % scale == scale - 1;
+                out[y * width + x] = match frame_buffer[y / scale][x / scale] {
+                    _ if edge => 0,
+                    1 => self.lit,
+                    _ => self.unlit,
+                };
+            }
+        }
+        out
+    }
+}
+
+fn main() {
+    let mut registry = Registry::new();
+    chip8_frontends::register_builtins(&mut registry).unwrap();
+    registry
+        .register_renderer(
+            "lcd",
+            Box::new(|| {
+                Box::new(Scanlines {
+                    lit: 0x0F380F,
+                    unlit: 0x9BBC0F,
+                })
+            }),
+        )
+        .unwrap();
+    println!(
+        "renderers: {}",
+        registry.renderer_names().collect::<Vec<_>>().join(", ")
+    );
+
+    let mut cpu = Chip8Interpreter::new();
+    cpu.set_echo_display(false);
+    cpu.load_rom_from(&BytesRom {
+        name: String::from("ibmrom.ch8"),
+        data: include_bytes!("../crates/chip8-tools/roms/ibmrom.ch8"),
+    })
+    .unwrap();
+    for frame in cpu.frames().take(30) {
+        frame.unwrap();
+    }
+    let (width, height) = (FRAME_BUFFER_WIDTH * 4, FRAME_BUFFER_HEIGHT * 4);
+    for name in ["nearest", "lcd"] {
+        let mut renderer = registry.renderer(name).unwrap();
+        let pixels = renderer.render(cpu.frame_buffer(), width, height);
+        let lit = pixels
+            .iter()
+            .filter(|&&pixel| pixel == 0x0F380F || pixel == 0xFFFFFF);
+        println!("{}: {} of {} pixels lit", name, lit.count(), pixels.len());
+    }
+}
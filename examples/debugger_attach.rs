@@ -0,0 +1,53 @@
+//! Drive the interpreter one instruction at a time, the way a debugger
+//! attaches: break at an address, trace with `explain`, patch code while
+//! paused and look at the machine state.
+//!
+//!     cargo run --example debugger_attach
+
+use chip8_core::rom::BytesRom;
+use chip8_core::{parse_snippet, Chip8Interpreter};
+
+/// Where the IBM logo moves on from its first letter
+const BREAKPOINT: u16 = 0x20C;
+
+fn main() {
+    let mut cpu = Chip8Interpreter::new();
+    cpu.set_echo_display(false);
+    cpu.enable_stats();
+    cpu.load_rom_from(&BytesRom {
+        name: String::from("ibmrom.ch8"),
+        data: include_bytes!("../crates/chip8-tools/roms/ibmrom.ch8"),
+    })
+    .unwrap();
+
+    cpu.debugger_mut().set_breakpoint(BREAKPOINT);
+    while cpu.paused().is_none() {
+        println!("{:#05X}  {}", cpu.pc(), cpu.explain());
+        cpu.step().unwrap();
+    }
+    println!("{}: {}", cpu.paused().unwrap(), cpu.explain());
+
+    // Replace the next instruction with "V0 = 0" while paused, step over it, put it back
+    cpu.patch(BREAKPOINT, &parse_snippet("6000").unwrap())
+        .unwrap();
+    println!("patched: {}", cpu.explain());
+    cpu.resume();
+    cpu.step().unwrap();
+    let undone = cpu.undo_patch().unwrap();
+    println!(
+        "restored {} bytes at {:#05X}",
+        undone.original.len(),
+        undone.addr
+    );
+
+    for _ in 0..100 {
+        if cpu.is_spinning() {
+            break;
+        }
+        cpu.step().unwrap();
+    }
+    print!("{}", cpu);
+    if let Some(stats) = cpu.stats() {
+        print!("{}", stats);
+    }
+}
@@ -0,0 +1,49 @@
+//! Run a ROM without a window and print the display it ends up with.
+//!
+//!     cargo run --example headless_run [rom.ch8]
+
+use chip8_core::rom::{BytesRom, FileRom, RomSource};
+use chip8_core::{Chip8Interpreter, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH};
+
+/// Frames to run, one second of emulated time
+const FRAMES: usize = 60;
+
+fn main() {
+    let source: Box<dyn RomSource> = match std::env::args().nth(1) {
+        Some(path) => Box::new(FileRom::new(path)),
+        None => Box::new(BytesRom {
+            name: String::from("ibmrom.ch8"),
+            data: include_bytes!("../crates/chip8-tools/roms/ibmrom.ch8"),
+        }),
+    };
+    let mut cpu = Chip8Interpreter::new();
+    cpu.set_echo_display(false);
+    if let Err(err) = cpu.load_rom_from(&*source) {
+        eprintln!("Err: {}", err);
+        std::process::exit(1);
+    }
+    let mut last = None;
+    for frame in cpu.frames().take(FRAMES) {
+        match frame {
+            Ok(frame) => last = Some(frame),
+            Err(err) => {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    let Some(frame) = last else {
+        println!("{} halted before its first frame", source.name());
+        return;
+    };
+    let region = frame
+        .framebuffer
+        .region(0, 0, FRAME_BUFFER_WIDTH, FRAME_BUFFER_HEIGHT);
+    for y in 0..region.height {
+        let line: String = (0..region.width)
+            .map(|x| if region.get(x, y) { '#' } else { '.' })
+            .collect();
+        println!("{}", line);
+    }
+    println!("frame {} hash {:016x}", frame.index, region.hash());
+}
@@ -0,0 +1,47 @@
+//! Feed the keypad from a script instead of a keyboard, e.g. for automated
+//! playthroughs or tests of input handling.
+//!
+//!     cargo run --example scripted_input
+
+use chip8_core::rom::BytesRom;
+use chip8_core::Chip8Interpreter;
+
+/// Waits for a key with FX0A, then draws its hex digit and spins
+const ROM: [u8; 12] = [
+    0xF0, 0x0A, // V0 = key, once pressed and released
+    0xF0, 0x29, // I = font sprite of V0
+    0x61, 0x0C, // V1 = 12
+    0xD1, 0x15, // draw it at (V1, V1)
+    0x12, 0x08, // spin
+    0x00, 0x00,
+];
+
+/// (frame, key, pressed) events to play back
+const SCRIPT: [(u64, u8, bool); 2] = [(10, 0x7, true), (12, 0x7, false)];
+
+fn main() {
+    let mut cpu = Chip8Interpreter::new();
+    cpu.set_echo_display(false);
+    cpu.load_rom_from(&BytesRom {
+        name: String::from("wait-for-key"),
+        data: &ROM,
+    })
+    .unwrap();
+    for frame in 0..30 {
+        for &(_, key, down) in SCRIPT.iter().filter(|(at, ..)| *at == frame) {
+            println!(
+                "frame {}: key {:X} {}",
+                frame,
+                key,
+                if down { "down" } else { "up" }
+            );
+            cpu.set_key(key, down);
+        }
+        cpu.run_frame().unwrap();
+        if cpu.is_spinning() {
+            println!("frame {}: program is done waiting", frame);
+            break;
+        }
+    }
+    println!("{}", cpu.state());
+}
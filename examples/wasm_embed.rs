@@ -0,0 +1,61 @@
+//! The shape of an embedding in a host that owns the main loop and wants
+//! raw pixels, such as a browser calling into WebAssembly every animation
+//! frame. `Emulator` is what would be exported to JavaScript; `main` plays
+//! the host here so the example runs natively.
+//!
+//!     cargo run --example wasm_embed
+
+use chip8_core::rom::BytesRom;
+use chip8_core::{Chip8Interpreter, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH, KEYPAD_SIZE};
+
+/// One interpreter plus an RGBA buffer the host can copy into a canvas
+pub struct Emulator {
+    cpu: Chip8Interpreter,
+    rgba: Vec<u8>,
+}
+
+impl Emulator {
+    pub fn new(rom: &[u8]) -> Result<Emulator, String> {
+        let mut cpu = Chip8Interpreter::new();
+        cpu.set_echo_display(false);
+        cpu.load_rom_from(&BytesRom {
+            name: String::from("rom"),
+            data: rom,
+        })
+        .map_err(|err| err.to_string())?;
+        Ok(Emulator {
+            cpu,
+            rgba: vec![0; FRAME_BUFFER_WIDTH * FRAME_BUFFER_HEIGHT * 4],
+        })
+    }
+
+    /// Advance one 60Hz frame with the keys held down as a bit mask, key 0
+    /// in bit 0. Returns whether the buzzer should sound.
+    pub fn frame(&mut self, keys: u16) -> Result<bool, String> {
+        for key in 0..KEYPAD_SIZE {
+            self.cpu.set_key(key, keys & (1 << key) != 0);
+        }
+        self.cpu.run_frame().map_err(|err| err.to_string())?;
+        let pixels = self.cpu.frame_buffer().rows().flatten();
+        for (rgba, &pixel) in self.rgba.chunks_mut(4).zip(pixels) {
+            let level = if pixel == 1 { 0xFF } else { 0x00 };
+            rgba.copy_from_slice(&[level, level, level, 0xFF]);
+        }
+        Ok(self.cpu.state().sound_timer > 0)
+    }
+
+    /// 64x32 RGBA pixels of the last frame
+    pub fn pixels(&self) -> &[u8] {
+        &self.rgba
+    }
+}
+
+fn main() {
+    let mut emulator =
+        Emulator::new(include_bytes!("../crates/chip8-tools/roms/ibmrom.ch8")).unwrap();
+    for _ in 0..30 {
+        emulator.frame(0).unwrap();
+    }
+    let lit = emulator.pixels().chunks(4).filter(|rgba| rgba[0] != 0);
+    println!("{} pixels lit after 30 frames", lit.count());
+}
@@ -0,0 +1,55 @@
+use chip8emu::plugin::Registry;
+use chip8emu::Quirks;
+use serde_json::{json, Value};
+
+/// What this build supports, for launchers and test harnesses to adapt to
+pub fn report(registry: &Registry) -> Value {
+    json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "extensions": ["chip-8"],
+        "quirks": {
+            "flags": Quirks::FLAGS,
+            "presets": Quirks::PRESETS.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+        },
+        "frontends": frontends(),
+        "features": {
+            "audio": cfg!(feature = "audio"),
+            "egui": cfg!(feature = "egui"),
+            "tui": cfg!(feature = "tui"),
+            "http": cfg!(feature = "http"),
+            "zip": cfg!(feature = "zip"),
+        },
+        "plugins": {
+            "analysis": registry.analysis_names().collect::<Vec<_>>(),
+            "renderers": registry.renderer_names().collect::<Vec<_>>(),
+        },
+    })
+}
+
+fn frontends() -> Vec<&'static str> {
+    let mut frontends = vec!["window", "watch", "headless", "debug", "dap"];
+    if cfg!(feature = "tui") {
+        frontends.push("tui");
+    }
+    if cfg!(feature = "egui") {
+        frontends.push("gui");
+    }
+    frontends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let mut registry = Registry::new();
+        chip8emu::tools::register_builtins(&mut registry).unwrap();
+        let report = report(&registry);
+        assert_eq!(report["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(report["quirks"]["flags"][0], "old_shift");
+        assert_eq!(report["plugins"]["analysis"][0], "hotspots");
+        assert!(report["features"]["http"].is_boolean());
+    }
+}
@@ -0,0 +1,30 @@
+//! A CHIP-8 emulator you can embed. The interpreter and everything around
+//! it comes from `chip8_core` and is re-exported here, the real-time
+//! window and the offline tools are under `frontends` and `tools`. The
+//! `chip8emu` binary is a command line frontend over this same API.
+//!
+//! ```
+//! use chip8emu::rom::BytesRom;
+//! use chip8emu::{Chip8Interpreter, Instruction};
+//!
+//! let rom = BytesRom {
+//!     name: String::from("demo"),
+//!     // V0 = 0x2A, then stop
+//!     data: &[0x60, 0x2A, 0x00, 0x00],
+//! };
+//! let mut cpu = Chip8Interpreter::new();
+//! cpu.set_echo_display(false);
+//! cpu.load_rom_from(&rom).unwrap();
+//! while !cpu.halted() {
+//!     cpu.step().unwrap();
+//! }
+//! assert_eq!(cpu.pc(), 0x204);
+//! assert!(matches!(
+//!     Instruction::from_raw_opcode(0x602A),
+//!     Ok(Instruction::I6XNN(_))
+//! ));
+//! ```
+
+pub use chip8_core::*;
+pub use chip8_frontends as frontends;
+pub use chip8_tools as tools;
@@ -1,22 +1,1133 @@
-extern crate minifb;
-mod chip8;
+mod capabilities;
+mod settings;
+
+use chip8emu::frontends::{
+    parse_color, save_heatmap_png, supports_audio, Buzzer, DapServer, Hotkeys, Keymap, Repl,
+    SaveSlots, ScaleFilter, ScreenshotSequence, SharedFrame, SoundFlash, Tone, VideoOptions,
+    WavRecorder, Waveform, WindowFrontend,
+};
+use chip8emu::plugin::Registry;
+use chip8emu::rom;
+use chip8emu::tools::{asm, calibrate, cfg, conformance, disasm, selftest, sprites, statediff};
+use chip8emu::{
+    Audio, Chip8Interpreter, Fault, Frame, MachineState, NoAudio, OutOfBounds, PowerOn, Quirks,
+    Replay, Symbols, SysPolicy, Watchpoint, FRAME_BUFFER_HEIGHT, FRAME_BUFFER_WIDTH,
+};
+use std::path::Path;
+use tracing_subscriber::EnvFilter;
+/// Instructions kept in the journal written on a fault
+const JOURNAL_LENGTH: usize = 64;
+/// Where the journal goes on a fault unless told otherwise
+const CRASH_DUMP_PATH: &str = "chip8emu-crash.txt";
+/// How far back the rewind hotkey goes unless told otherwise
+const DEFAULT_REWIND_SECONDS: f64 = 10.;
+/// Executed instructions shown on a fault unless told otherwise
+const DEFAULT_HISTORY_LENGTH: usize = 16;
+
+/// How a headless run reports the buzzer
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Beeps {
+    /// A "BEEP at cycle N" line when the sound timer starts
+    Log,
+    /// The terminal bell
+    Bell,
+}
+
+/// Options for running a ROM
+struct RunOptions {
+    rom: String,
+    quirks: Option<Quirks>,
+    /// Pick the quirks preset by trying the ROM under each one
+    calibrate: bool,
+    strict: bool,
+    stack_limit: Option<usize>,
+    /// How sprites running past the end of memory are read
+    out_of_bounds: OutOfBounds,
+    /// What 0NNN calls to machine code routines do
+    sys_policy: SysPolicy,
+    /// Halt when the ROM jumps to itself
+    halt_on_spin: bool,
+    /// Seconds without drawing, input or timers before halting
+    idle_halt: Option<f64>,
+    /// Experimental number of 4K memory banks, switched with 0BNN
+    banks: usize,
+    /// Where to write a journal if the machine faults, a crash dump for bug
+    /// reports
+    journal: Option<String>,
+    video: VideoOptions,
+    /// Run without a window for this many frames
+    headless: Option<u64>,
+    beeps: Option<Beeps>,
+    /// Where to write the machine state after a headless run, or when D is
+    /// pressed on a fault. The save state hotkey uses slots next to the ROM.
+    save_state: Option<String>,
+    /// Part of the display (x, y, width, height) printed after a headless run
+    region: (usize, usize, usize, usize),
+    /// Print per-opcode statistics after a headless run
+    stats: bool,
+    /// Print which ROM bytes and opcode families ran when the run ends
+    coverage: bool,
+    /// Print host time spent per instruction variant when the run ends
+    profile_opcodes: bool,
+    /// Print a flame view of time spent per subroutine after a headless run
+    profile_calls: bool,
+    /// Record RNG draws and wall-clock reads, Some(true) when the run is
+    /// claimed to be deterministic
+    audit: Option<bool>,
+    hotkeys: Hotkeys,
+    /// Host keys for the hex keypad
+    keymap: Keymap,
+    /// Where to write the frame timing histograms when the run ends
+    timing: Option<String>,
+    /// Where to write the memory access heatmap when the run ends, as a
+    /// PNG if it ends in .png and CSV otherwise
+    heatmap: Option<String>,
+    /// Experimental delay and sound timer frequency
+    timer_hz: Option<f64>,
+    /// Instructions per second
+    ips: Option<f64>,
+    /// Seed for CXNN's random numbers, for repeatable runs
+    seed: Option<u64>,
+    /// Faults to inject for robustness testing
+    faults: Vec<Fault>,
+    /// Addresses or symbol names to pause at for the debugger
+    breakpoints: Vec<String>,
+    /// Symbol file naming addresses for the debuggers and traces
+    symbols: Option<String>,
+    /// Registers and memory to pause on changes to
+    watchpoints: Vec<Watchpoint>,
+    /// Plugin analysis passes to run, reported after a headless run
+    analyses: Vec<String>,
+    /// Plugin renderer to draw the window with
+    renderer: Option<String>,
+    /// Directory to write a numbered PNG per frame of a headless run
+    screenshots: Option<String>,
+    /// Only write screenshots of frames where the display changed
+    changed_only: bool,
+    /// File to publish the display into for other apps, e.g. under /dev/shm
+    share_frame: Option<String>,
+    /// Memory dump to power on with, e.g. of a COSMAC VIP's interpreter area
+    power_on_image: Option<String>,
+    /// What the registers and the display start with
+    power_on: PowerOn,
+    /// Refresh rate to present at with frames interpolated in between
+    interpolate: Option<f64>,
+    /// Display region and hash that mean game over in practice mode
+    game_over: Option<((usize, usize, usize, usize), u64)>,
+    /// Keep the buzzer silent
+    mute: bool,
+    tone: Tone,
+    /// Also write the buzzer to this WAV file
+    record_audio: Option<String>,
+    /// Run at full speed until the ROM first reads the keypad
+    fast_boot: bool,
+    /// Leave the window open on the final screen when the program ends
+    keep_open: bool,
+    /// Seconds of play the rewind hotkey can go back through
+    rewind: f64,
+    /// Executed instructions shown when the machine faults
+    history: usize,
+    /// Record the keypad, timer ticks and seed into this replay file
+    record_input: Option<String>,
+    /// Replay file to take the keypad, timer ticks and seed from
+    replay: Option<String>,
+    /// Write the shareable settings to this profile file and exit
+    export_profile: Option<String>,
+}
 
-use crate::chip8::Chip8Interpreter;
-use minifb::{Key, Window, WindowOptions};
-const FRAME_BUFFER_WIDTH: usize = 64;
-const FRAME_BUFFER_HEIGHT: usize = 32;
 fn main() {
-    let mut window = Window::new(
-        "Chip8 Emulator",
-        FRAME_BUFFER_WIDTH*10,
-        FRAME_BUFFER_HEIGHT*10,
-        WindowOptions::default(),
-    )
-    .unwrap_or_else(|e| {
-        panic!("{}", e);
+    // RUST_LOG=debug logs frames, timer ticks and the display, trace every
+    // executed instruction
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut registry = Registry::new();
+    chip8emu::frontends::register_builtins(&mut registry).unwrap();
+    chip8emu::tools::register_builtins(&mut registry).unwrap();
+    if args.iter().any(|arg| arg == "--version") {
+        if args.iter().any(|arg| arg == "--capabilities") {
+            println!("{:#}", capabilities::report(&registry));
+        } else {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        }
+        return;
+    }
+    match args.first().map(String::as_str) {
+        Some("conformance") => {
+            conformance::run();
+            return;
+        }
+        Some("selftest") => {
+            let (quirks, offline) = parse_selftest_options(&args[1..]).unwrap_or_else(|err| {
+                eprintln!("Err: {}", err);
+                std::process::exit(2);
+            });
+            println!("Running the CHIP-8 test ROMs to check this build...");
+            let checks = selftest::checks(quirks, offline);
+            print!("{}", selftest::report(&checks, offline));
+            if checks.iter().any(|check| check.outcome.is_err()) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("sprites") => {
+            if args.len() != 2 && args.len() != 3 {
+                eprintln!("Usage: chip8emu sprites rom.ch8 [sheet.png]");
+                std::process::exit(2);
+            }
+            if let Err(err) = sprites::run(&args[1], args.get(2).map(String::as_str)) {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("cfg") => {
+            if args.len() != 2 && args.len() != 3 {
+                eprintln!("Usage: chip8emu cfg rom.ch8 [graph.dot]");
+                std::process::exit(2);
+            }
+            if let Err(err) = cfg::run(&args[1], args.get(2).map(String::as_str)) {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("asm") => {
+            let (source, rom, symbols) = parse_asm_options(&args[1..]).unwrap_or_else(|err| {
+                eprintln!("Err: {}", err);
+                eprintln!(
+                    "Usage: chip8emu asm program.s|program.8o [-o out.ch8] [--symbols out.sym]"
+                );
+                std::process::exit(2);
+            });
+            if let Err(err) = asm::run(&source, &rom, symbols.as_deref()) {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("disasm") => {
+            let symbols = match &args[1..] {
+                [_] => None,
+                [_, flag, path] if flag == "--symbols" => Some(path.as_str()),
+                _ => {
+                    eprintln!("Usage: chip8emu disasm rom.ch8 [--symbols labels.sym]");
+                    std::process::exit(2);
+                }
+            };
+            if let Err(err) = disasm::run(&args[1], symbols) {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("statediff") => {
+            if args.len() != 3 {
+                eprintln!("Usage: chip8emu statediff a.c8state b.c8state");
+                std::process::exit(2);
+            }
+            if let Err(err) = statediff::run(&args[1], &args[2]) {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("convert") => {
+            if args.len() != 3 {
+                eprintln!("Usage: chip8emu convert in.c8state out.json");
+                std::process::exit(2);
+            }
+            let state = MachineState::load(&args[1]).unwrap_or_else(|err| {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            });
+            if let Err(err) = state.save(&args[2]) {
+                eprintln!("Err: cannot write {}: {}", args[2], err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("plugins") => {
+            println!("analysis passes:");
+            for name in registry.analysis_names() {
+                println!("  {}", name);
+            }
+            println!("renderers:");
+            for name in registry.renderer_names() {
+                println!("  {}", name);
+            }
+            return;
+        }
+        _ => {}
+    }
+    let (mode, args) = match args.first().map(String::as_str) {
+        Some(mode @ ("watch" | "debug" | "dap" | "tui" | "gui")) => (Some(mode), &args[1..]),
+        _ => (None, &args[..]),
+    };
+    let watch = mode == Some("watch");
+    let mut options = parse_run_options(args, &registry).unwrap_or_else(|err| {
+        eprintln!("Err: {}", err);
+        std::process::exit(2);
+    });
+    if let Some(path) = &options.export_profile {
+        if let Err(err) = std::fs::write(path, settings::export(&options)) {
+            eprintln!("Err: cannot write {}: {}", path, err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if options.calibrate {
+        if watch {
+            eprintln!("Err: --quirks auto needs a single ROM, not watch mode");
+            std::process::exit(2);
+        }
+        let trials = calibrate::trials(&*rom::open(&options.rom)).unwrap_or_else(|err| {
+            eprintln!("Err: {}", err);
+            std::process::exit(1);
+        });
+        let best = calibrate::best(&trials);
+        print!("{}", calibrate::report(&trials));
+        println!("Using quirks preset {}", best.preset);
+        options.quirks = Some(best.quirks);
+    }
+    match options.headless {
+        _ if mode == Some("debug") => run_debugger(&options, &registry),
+        _ if mode == Some("dap") => run_dap(&options, &registry),
+        #[cfg(feature = "tui")]
+        _ if mode == Some("tui") => run_terminal(&options, &registry),
+        #[cfg(not(feature = "tui"))]
+        _ if mode == Some("tui") => {
+            eprintln!("Err: this build has no terminal UI, rebuild with --features tui");
+            std::process::exit(2);
+        }
+        #[cfg(feature = "egui")]
+        _ if mode == Some("gui") => run_gui(&options, &registry),
+        #[cfg(not(feature = "egui"))]
+        _ if mode == Some("gui") => {
+            eprintln!("Err: this build has no debugger window, rebuild with --features egui");
+            std::process::exit(2);
+        }
+        Some(frames) => run_headless(&options, &registry, frames),
+        None if watch => run_window(&options, &registry, |frontend, cpu| {
+            frontend.run_watch(cpu, Path::new(&options.rom))
+        }),
+        None => run_window(&options, &registry, |frontend, cpu| {
+            if let Err(err) = frontend.run(cpu, &*rom::open(&options.rom)) {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+        }),
+    }
+}
+
+fn default_options() -> RunOptions {
+    RunOptions {
+        rom: String::from("crates/chip8-tools/roms/ibmrom.ch8"),
+        quirks: None,
+        calibrate: false,
+        strict: false,
+        stack_limit: None,
+        out_of_bounds: OutOfBounds::default(),
+        sys_policy: SysPolicy::default(),
+        halt_on_spin: false,
+        idle_halt: None,
+        banks: 1,
+        journal: Some(String::from(CRASH_DUMP_PATH)),
+        video: VideoOptions::default(),
+        headless: None,
+        beeps: None,
+        save_state: None,
+        region: (0, 0, FRAME_BUFFER_WIDTH, FRAME_BUFFER_HEIGHT),
+        stats: false,
+        profile_opcodes: false,
+        coverage: false,
+        profile_calls: false,
+        audit: None,
+        hotkeys: Hotkeys::default(),
+        keymap: Keymap::default(),
+        timing: None,
+        heatmap: None,
+        timer_hz: None,
+        ips: None,
+        seed: None,
+        faults: vec![],
+        breakpoints: vec![],
+        symbols: None,
+        watchpoints: vec![],
+        analyses: vec![],
+        renderer: None,
+        screenshots: None,
+        changed_only: false,
+        share_frame: None,
+        power_on_image: None,
+        power_on: PowerOn::default(),
+        interpolate: None,
+        game_over: None,
+        mute: false,
+        tone: Tone::default(),
+        record_audio: None,
+        fast_boot: false,
+        keep_open: false,
+        rewind: DEFAULT_REWIND_SECONDS,
+        history: DEFAULT_HISTORY_LENGTH,
+        record_input: None,
+        replay: None,
+        export_profile: None,
+    }
+}
+
+fn parse_run_options(args: &[String], registry: &Registry) -> Result<RunOptions, String> {
+    let mut options = default_options();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict" => options.strict = true,
+            "--quirks" => {
+                let name = args.next().ok_or("--quirks expects a preset name")?;
+                if name == "auto" {
+                    options.calibrate = true;
+                } else {
+                    let quirks = Quirks::preset(name)
+                        .ok_or_else(|| format!("unknown quirks preset '{}'", name))?;
+                    options.quirks = Some(quirks);
+                }
+            }
+            "--quirk" => {
+                let flag = args.next().ok_or("--quirk expects a quirk name")?;
+                options
+                    .quirks
+                    .get_or_insert_with(Quirks::default)
+                    .set(flag, true)?;
+            }
+            "--filter" => {
+                let name = args.next().ok_or("--filter expects nearest or smooth")?;
+                options.video.filter = ScaleFilter::from_name(name)
+                    .ok_or_else(|| format!("unknown scale filter '{}'", name))?;
+            }
+            "--stack-limit" => {
+                let limit = args.next().ok_or("--stack-limit expects a depth")?;
+                let limit = limit
+                    .parse()
+                    .map_err(|_| format!("invalid stack limit '{}'", limit))?;
+                options.stack_limit = Some(limit);
+            }
+            "--out-of-bounds" => {
+                let name = args
+                    .next()
+                    .ok_or("--out-of-bounds expects wrap, zeros or fault")?;
+                options.out_of_bounds = OutOfBounds::from_name(name)
+                    .ok_or_else(|| format!("unknown out-of-bounds policy '{}'", name))?;
+            }
+            "--sys" => {
+                let name = args.next().ok_or("--sys expects ignore, halt or error")?;
+                options.sys_policy = SysPolicy::from_name(name)
+                    .ok_or_else(|| format!("unknown SYS policy '{}'", name))?;
+            }
+            "--halt-on-spin" => options.halt_on_spin = true,
+            "--idle-halt" => {
+                let seconds = args
+                    .next()
+                    .and_then(|seconds| seconds.parse::<f64>().ok())
+                    .filter(|seconds| seconds.is_finite() && *seconds > 0.)
+                    .ok_or("--idle-halt expects a positive number of seconds")?;
+                options.idle_halt = Some(seconds);
+            }
+            "--banks" => {
+                let count = args.next().ok_or("--banks expects a bank count")?;
+                options.banks = count
+                    .parse()
+                    .ok()
+                    .filter(|count| (1..=256).contains(count))
+                    .ok_or_else(|| format!("invalid bank count '{}'", count))?;
+            }
+            "--headless" => {
+                let frames = args.next().ok_or("--headless expects a frame count")?;
+                let frames = frames
+                    .parse()
+                    .map_err(|_| format!("invalid frame count '{}'", frames))?;
+                options.headless = Some(frames);
+            }
+            "--beeps" => {
+                options.beeps = match args.next().map(String::as_str) {
+                    Some("log") => Some(Beeps::Log),
+                    Some("bell") => Some(Beeps::Bell),
+                    _ => return Err("--beeps expects log or bell".to_string()),
+                };
+            }
+            "--journal" => {
+                let path = args.next().ok_or("--journal expects a file path")?;
+                options.journal = Some(path.to_string());
+            }
+            "--no-journal" => options.journal = None,
+            "--save-state" => {
+                let path = args.next().ok_or("--save-state expects a file path")?;
+                options.save_state = Some(path.to_string());
+            }
+            "--region" => {
+                let region = args.next().ok_or("--region expects x,y,width,height")?;
+                let parts: Vec<usize> = region
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid region '{}'", region))?;
+                match parts[..] {
+                    [x, y, width, height] => options.region = (x, y, width, height),
+                    _ => return Err(format!("invalid region '{}'", region)),
+                }
+            }
+            "--game-over" => {
+                let spec = args
+                    .next()
+                    .ok_or("--game-over expects x,y,width,height=hash")?;
+                options.game_over = Some(
+                    parse_game_over(spec)
+                        .ok_or_else(|| format!("invalid game over screen '{}'", spec))?,
+                );
+            }
+            "--stats" => options.stats = true,
+            "--coverage" => options.coverage = true,
+            "--profile-calls" => options.profile_calls = true,
+            "--profile-opcodes" => options.profile_opcodes = true,
+            "--audit" => options.audit = Some(options.audit.unwrap_or(false)),
+            "--deterministic" => options.audit = Some(true),
+            "--hotkey" => {
+                let spec = args.next().ok_or("--hotkey expects action=key")?;
+                options.hotkeys.bind(spec)?;
+            }
+            "--grab-keys" => options.hotkeys.grab_all = true,
+            "--key" => {
+                let spec = args.next().ok_or("--key expects hex=key")?;
+                options.keymap.bind(spec)?;
+            }
+            "--timer-hz" => {
+                let hz = args
+                    .next()
+                    .and_then(|hz| hz.parse::<f64>().ok())
+                    .filter(|hz| hz.is_finite() && *hz > 0.)
+                    .ok_or("--timer-hz expects a positive frequency")?;
+                options.timer_hz = Some(hz);
+            }
+            "--ips" => {
+                let ips = args
+                    .next()
+                    .and_then(|ips| ips.parse::<f64>().ok())
+                    .filter(|ips| ips.is_finite() && *ips > 0.)
+                    .ok_or("--ips expects a positive instruction rate")?;
+                options.ips = Some(ips);
+            }
+            "--seed" => {
+                let seed = args.next().ok_or("--seed expects a number")?;
+                let seed = seed
+                    .parse()
+                    .map_err(|_| format!("invalid seed '{}'", seed))?;
+                options.seed = Some(seed);
+            }
+            "--analysis" => {
+                let name = args.next().ok_or("--analysis expects a name")?;
+                if registry.analysis(name).is_none() {
+                    return Err(format!("unknown analysis pass '{}'", name));
+                }
+                options.analyses.push(name.to_string());
+            }
+            "--renderer" => {
+                let name = args.next().ok_or("--renderer expects a name")?;
+                if registry.renderer(name).is_none() {
+                    return Err(format!("unknown renderer '{}'", name));
+                }
+                options.renderer = Some(name.to_string());
+            }
+            "--inject" => {
+                let spec = args.next().ok_or("--inject expects a fault")?;
+                options.faults.push(spec.parse()?);
+            }
+            "--break" => {
+                let addr = args.next().ok_or("--break expects an address")?;
+                options.breakpoints.push(addr.to_string());
+            }
+            "--symbols" => {
+                let path = args.next().ok_or("--symbols expects a file path")?;
+                options.symbols = Some(path.to_string());
+            }
+            "--watch" => {
+                let spec = args
+                    .next()
+                    .ok_or("--watch expects a register or address range")?;
+                options.watchpoints.push(spec.parse()?);
+            }
+            "--timing" => {
+                let path = args.next().ok_or("--timing expects a file path")?;
+                options.timing = Some(path.to_string());
+            }
+            "--heatmap" => {
+                let path = args.next().ok_or("--heatmap expects a file path")?;
+                options.heatmap = Some(path.to_string());
+            }
+            "--screenshots" => {
+                let dir = args.next().ok_or("--screenshots expects a directory")?;
+                options.screenshots = Some(dir.to_string());
+            }
+            "--changed-only" => options.changed_only = true,
+            "--power-on-image" => {
+                let path = args.next().ok_or("--power-on-image expects a file path")?;
+                options.power_on_image = Some(path.to_string());
+            }
+            "--power-on" => {
+                let name = args.next().ok_or("--power-on expects blank or vip")?;
+                options.power_on = PowerOn::from_name(name)
+                    .ok_or_else(|| format!("unknown power-on state '{}'", name))?;
+            }
+            "--share-frame" => {
+                let path = args.next().ok_or("--share-frame expects a file path")?;
+                options.share_frame = Some(path.to_string());
+            }
+            "--glow" => options.video.glow = true,
+            "--sound-flash" => {
+                let name = args.next().ok_or("--sound-flash expects invert or tint")?;
+                options.video.sound_flash = SoundFlash::from_name(name)
+                    .ok_or_else(|| format!("unknown sound flash '{}'", name))?;
+            }
+            "--fast-boot" => options.fast_boot = true,
+            "--keep-open" => options.keep_open = true,
+            "--rewind" => {
+                let seconds = args
+                    .next()
+                    .and_then(|seconds| seconds.parse::<f64>().ok())
+                    .filter(|seconds| seconds.is_finite() && *seconds >= 0.)
+                    .ok_or("--rewind expects a number of seconds, 0 to turn it off")?;
+                options.rewind = seconds;
+            }
+            "--history" => {
+                options.history = args
+                    .next()
+                    .and_then(|count| count.parse().ok())
+                    .ok_or("--history expects a number of instructions, 0 to turn it off")?;
+            }
+            "--record-input" => {
+                let path = args.next().ok_or("--record-input expects a file path")?;
+                options.record_input = Some(path.to_string());
+            }
+            "--replay" => {
+                let path = args.next().ok_or("--replay expects a file path")?;
+                options.replay = Some(path.to_string());
+            }
+            "--mute" => options.mute = true,
+            "--waveform" => {
+                let name = args
+                    .next()
+                    .ok_or("--waveform expects square, sine, triangle or noise")?;
+                options.tone.waveform = Waveform::from_name(name)
+                    .ok_or_else(|| format!("unknown waveform '{}'", name))?;
+            }
+            "--pitch" => {
+                options.tone.pitch = args
+                    .next()
+                    .and_then(|hz| hz.parse::<f32>().ok())
+                    .filter(|hz| (20. ..=20000.).contains(hz))
+                    .ok_or("--pitch expects a frequency between 20 and 20000Hz")?;
+            }
+            "--interpolate" => {
+                let hz = args
+                    .next()
+                    .and_then(|hz| hz.parse::<f64>().ok())
+                    .filter(|hz| hz.is_finite() && *hz > 60.)
+                    .ok_or("--interpolate expects a refresh rate above 60Hz")?;
+                options.interpolate = Some(hz);
+            }
+            "--palette" => {
+                let palette = args.next().ok_or("--palette expects fg,bg colors")?;
+                match palette.split(',').map(parse_color).collect::<Vec<_>>()[..] {
+                    [Some(fg), Some(bg)] => {
+                        options.video.foreground = fg;
+                        options.video.background = bg;
+                    }
+                    _ => return Err(format!("invalid palette '{}'", palette)),
+                }
+            }
+            "--profile" => {
+                let path = args.next().ok_or("--profile expects a file path")?;
+                let text = std::fs::read_to_string(path)
+                    .map_err(|err| format!("cannot read {}: {}", path, err))?;
+                settings::import(&mut options, &text)
+                    .map_err(|err| format!("{}: {}", path, err))?;
+            }
+            "--record-audio" => {
+                let path = args.next().ok_or("--record-audio expects a file path")?;
+                options.record_audio = Some(path.to_string());
+            }
+            "--export-profile" => {
+                let path = args.next().ok_or("--export-profile expects a file path")?;
+                options.export_profile = Some(path.to_string());
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+            rom => options.rom = rom.to_string(),
+        }
+    }
+    Ok(options)
+}
+
+/// "x,y,width,height=hash", with the hash in hex as headless runs print it
+fn parse_game_over(spec: &str) -> Option<((usize, usize, usize, usize), u64)> {
+    let (region, hash) = spec.split_once('=')?;
+    let parts: Vec<usize> = region
+        .split(',')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let hash = u64::from_str_radix(hash, 16).ok()?;
+    match parts[..] {
+        [x, y, width, height] => Some(((x, y, width, height), hash)),
+        _ => None,
+    }
+}
+
+fn run_window<F>(options: &RunOptions, registry: &Registry, run: F)
+where
+    F: FnOnce(&mut WindowFrontend, &mut Chip8Interpreter),
+{
+    let check = Chip8Interpreter::self_check();
+    if !check.passed() {
+        eprint!(
+            "Warning: the emulator core failed its self check\n{}",
+            check
+        );
+    }
+    let mut frontend = WindowFrontend::open().unwrap_or_else(|e| {
+        eprintln!("Err: {}", e);
+        std::process::exit(1);
+    });
+    frontend.set_video_options(options.video);
+    if let Some(name) = &options.renderer {
+        frontend.set_renderer(registry.renderer(name).unwrap());
+    }
+    frontend.set_hotkeys(options.hotkeys.clone());
+    frontend.set_keymap(options.keymap.clone());
+    frontend.set_fast_boot(options.fast_boot);
+    frontend.set_keep_open(options.keep_open);
+    if let Some(hz) = options.interpolate {
+        frontend.set_interpolation(hz);
+    }
+    if let Some((region, hash)) = options.game_over {
+        frontend.set_game_over_screen(region, hash);
+    }
+    if let Some(path) = &options.save_state {
+        frontend.set_quicksave_path(path);
+    }
+    let save_slots = SaveSlots::for_rom(&options.rom);
+    frontend.set_save_slots(save_slots.clone());
+    if let Some(path) = &options.share_frame {
+        match SharedFrame::create(Path::new(path)) {
+            Ok(shared_frame) => frontend.set_shared_frame(shared_frame),
+            Err(err) => {
+                eprintln!("Err: cannot share the display through {}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut cpu = Chip8Interpreter::new();
+    configure(&mut cpu, options, registry);
+    // Flags left from an earlier session would make a replay play out
+    // differently
+    if options.record_input.is_none() && options.replay.is_none() {
+        if let Err(err) = save_slots.keep_flags(&mut cpu) {
+            eprintln!("Warning: not keeping the RPL flags: {}", err);
+        }
+    }
+    // One snapshot per 60Hz frame
+    cpu.enable_rewind(
+        (options.rewind * 60.) as usize,
+        (cpu.clock_speed() / 60.) as u64,
+    );
+    let mut audio: Box<dyn Audio> = Box::new(NoAudio);
+    if !options.mute && supports_audio() {
+        match Buzzer::open(options.tone) {
+            Ok(buzzer) => audio = Box::new(buzzer),
+            Err(err) => eprintln!("No sound: {}", err),
+        }
+    }
+    cpu.set_audio(record_audio(options, audio));
+    run(&mut frontend, &mut cpu);
+    print_reports(&cpu, options);
+    save_replay(&cpu, options);
+}
+
+/// Wrap `audio` in a recorder when asked to record the session
+fn record_audio(options: &RunOptions, audio: Box<dyn Audio>) -> Box<dyn Audio> {
+    let Some(path) = &options.record_audio else {
+        return audio;
+    };
+    match WavRecorder::create(Path::new(path), options.tone, audio) {
+        Ok(recorder) => Box::new(recorder),
+        Err(err) => {
+            eprintln!("Err: cannot write {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn load(cpu: &mut Chip8Interpreter, rom: &str) {
+    if let Err(err) = cpu.load_rom_from(&*rom::open(rom)) {
+        eprintln!("Err: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// `chip8emu selftest [--quirks PRESET] [--offline]`, the COSMAC VIP
+/// preset unless told otherwise
+fn parse_selftest_options(args: &[String]) -> Result<(Quirks, bool), String> {
+    let mut quirks = Quirks::COSMAC_VIP;
+    let mut offline = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                let name = args.next().ok_or("--quirks expects a preset name")?;
+                quirks = Quirks::preset(name)
+                    .ok_or_else(|| format!("unknown quirks preset '{}'", name))?;
+            }
+            "--offline" => offline = true,
+            other => return Err(format!("unknown selftest option '{}'", other)),
+        }
+    }
+    Ok((quirks, offline))
+}
+
+/// `chip8emu asm SOURCE [-o ROM] [--symbols FILE]`, the ROM next to the
+/// source unless told otherwise
+fn parse_asm_options(args: &[String]) -> Result<(String, String, Option<String>), String> {
+    let mut source = None;
+    let mut rom = None;
+    let mut symbols = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => rom = Some(args.next().ok_or("-o expects a file path")?.to_string()),
+            "--symbols" => {
+                symbols = Some(
+                    args.next()
+                        .ok_or("--symbols expects a file path")?
+                        .to_string(),
+                )
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown asm option '{}'", flag)),
+            path => source = Some(path.to_string()),
+        }
+    }
+    let source = source.ok_or("asm expects a source file")?;
+    let rom = rom.unwrap_or_else(|| {
+        Path::new(&source)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .into_owned()
+    });
+    if rom == source {
+        return Err(format!("{} would overwrite the source", rom));
+    }
+    Ok((source, rom, symbols))
+}
+
+fn configure(cpu: &mut Chip8Interpreter, options: &RunOptions, registry: &Registry) {
+    cpu.set_power_on(options.power_on);
+    if let Some(path) = &options.power_on_image {
+        let image = std::fs::read(path)
+            .map_err(|err| format!("cannot read {}: {}", path, err))
+            .and_then(|image| cpu.set_power_on_image(Some(image)));
+        if let Err(err) = image {
+            eprintln!("Err: {}", err);
+            std::process::exit(1);
+        }
+    }
+    if options.banks > 1 {
+        cpu.set_bank_count(options.banks);
+    }
+    if let Some(quirks) = options.quirks {
+        cpu.set_quirks(quirks);
+    }
+    cpu.set_strict(options.strict);
+    cpu.set_stack_limit(options.stack_limit);
+    cpu.set_out_of_bounds(options.out_of_bounds);
+    cpu.set_sys_policy(options.sys_policy);
+    if let Some(path) = &options.journal {
+        cpu.enable_journal(path, JOURNAL_LENGTH);
+    }
+    if options.history > 0 {
+        cpu.enable_history(options.history);
+    }
+    if options.coverage {
+        cpu.enable_coverage();
+    }
+    if options.heatmap.is_some() {
+        cpu.enable_heatmap();
+    }
+    if options.profile_opcodes {
+        cpu.enable_profile();
+    }
+    if let Some(deterministic) = options.audit {
+        cpu.enable_audit(deterministic);
+    }
+    if let Some(path) = &options.timing {
+        cpu.enable_timing(path);
+    }
+    if let Some(hz) = options.timer_hz {
+        if let Err(err) = cpu.set_timer_frequency(hz) {
+            eprintln!("Err: {}", err);
+            std::process::exit(1);
+        }
+    }
+    if let Some(ips) = options.ips {
+        if let Err(err) = cpu.set_clock_speed(ips) {
+            eprintln!("Err: {}", err);
+            std::process::exit(1);
+        }
+    }
+    cpu.set_halt_on_spin(options.halt_on_spin);
+    if let Some(seconds) = options.idle_halt {
+        cpu.set_idle_limit(Some((seconds * cpu.clock_speed()) as u64));
+    }
+    if let Some(seed) = options.seed {
+        cpu.seed_rng(seed);
+    }
+    if options.record_input.is_some() {
+        cpu.record_replay(options.seed.unwrap_or_else(clock_seed));
+    }
+    if let Some(path) = &options.replay {
+        match Replay::load(path) {
+            Ok(replay) => cpu.play_replay(replay),
+            Err(err) => {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    for &fault in options.faults.iter() {
+        cpu.inject_fault(fault);
+    }
+    if let Some(path) = &options.symbols {
+        match Symbols::load(path) {
+            Ok(symbols) => cpu.set_symbols(symbols),
+            Err(err) => {
+                eprintln!("Err: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    for addr in options.breakpoints.iter() {
+        match cpu.symbols().resolve(addr) {
+            Some(addr) => cpu.debugger_mut().set_breakpoint(addr),
+            None => {
+                eprintln!("Err: invalid address '{}'", addr);
+                std::process::exit(2);
+            }
+        };
+    }
+    for &watchpoint in options.watchpoints.iter() {
+        cpu.debugger_mut().add_watchpoint(watchpoint);
+    }
+    for name in options.analyses.iter() {
+        cpu.add_analysis(registry.analysis(name).unwrap());
+    }
+}
+
+/// A seed for recordings run without --seed, so each one differs
+fn clock_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/// Print and write the reports asked for when the run ends
+fn print_reports(cpu: &Chip8Interpreter, options: &RunOptions) {
+    if let Some(profile) = cpu.profile() {
+        print!("{}", profile);
+    }
+    if let Some(coverage) = cpu.coverage() {
+        print!("{}", coverage);
+    }
+    if let (Some(path), Some(heatmap)) = (&options.heatmap, cpu.heatmap()) {
+        let written = if path.ends_with(".png") {
+            save_heatmap_png(heatmap, Path::new(path))
+        } else {
+            std::fs::write(path, heatmap.to_csv())
+        };
+        if let Err(err) = written {
+            eprintln!("Err: cannot write {}: {}", path, err);
+        }
+    }
+}
+
+/// Write the input recorded during the run, if asked to
+fn save_replay(cpu: &Chip8Interpreter, options: &RunOptions) {
+    if let (Some(path), Some(replay)) = (&options.record_input, cpu.recorded_replay()) {
+        if let Err(err) = replay.save(path) {
+            eprintln!("Err: cannot write {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run the ROM under the debugger prompt in the terminal
+fn run_debugger(options: &RunOptions, registry: &Registry) {
+    let mut cpu = Chip8Interpreter::new();
+    configure(&mut cpu, options, registry);
+    cpu.set_echo_display(false);
+    load(&mut cpu, &options.rom);
+    if let Err(err) = Repl::new().run(&mut cpu) {
+        eprintln!("Err: {}", err);
+        std::process::exit(1);
+    }
+    print_reports(&cpu, options);
+    save_replay(&cpu, options);
+}
+
+/// Serve the Debug Adapter Protocol on stdin and stdout for an editor
+fn run_dap(options: &RunOptions, registry: &Registry) {
+    let mut cpu = Chip8Interpreter::new();
+    configure(&mut cpu, options, registry);
+    cpu.set_echo_display(false);
+    load(&mut cpu, &options.rom);
+    if let Err(err) = DapServer::new().run(&mut cpu) {
+        eprintln!("Err: {}", err);
+        std::process::exit(1);
+    }
+    save_replay(&cpu, options);
+}
+
+/// Run the ROM in the full-screen terminal UI
+#[cfg(feature = "tui")]
+fn run_terminal(options: &RunOptions, registry: &Registry) {
+    let mut cpu = Chip8Interpreter::new();
+    configure(&mut cpu, options, registry);
+    cpu.set_echo_display(false);
+    load(&mut cpu, &options.rom);
+    if let Err(err) = chip8emu::frontends::TerminalFrontend::new().run(&mut cpu) {
+        eprintln!("Err: {}", err);
+        std::process::exit(1);
+    }
+    print_reports(&cpu, options);
+    save_replay(&cpu, options);
+}
+
+/// Run the ROM in the egui debugger window
+#[cfg(feature = "egui")]
+fn run_gui(options: &RunOptions, registry: &Registry) {
+    let mut cpu = Chip8Interpreter::new();
+    configure(&mut cpu, options, registry);
+    cpu.set_echo_display(false);
+    load(&mut cpu, &options.rom);
+    if let Err(err) = chip8emu::frontends::GuiDebugger::new(cpu).run() {
+        eprintln!("Err: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Run the ROM without a window and print the display (or the selected
+/// region of it) with its hash after the last frame
+fn run_headless(options: &RunOptions, registry: &Registry, frames: u64) {
+    let mut cpu = Chip8Interpreter::new();
+    configure(&mut cpu, options, registry);
+    cpu.set_echo_display(false);
+    if options.record_audio.is_some() {
+        cpu.set_audio(record_audio(options, Box::new(NoAudio)));
+    }
+    if let Some(beeps) = options.beeps {
+        cpu.on_sound(Box::new(move |active, cycle| match beeps {
+            Beeps::Log if active => println!("BEEP at cycle {}", cycle),
+            Beeps::Bell if active => print!("\x07"),
+            _ => {}
+        }));
+    }
+    if options.stats {
+        cpu.enable_stats();
+    }
+    if options.profile_calls {
+        cpu.enable_call_profile();
+    }
+    load(&mut cpu, &options.rom);
+    let mut screenshots = options.screenshots.as_ref().map(|dir| {
+        ScreenshotSequence::new(Path::new(dir), options.changed_only).unwrap_or_else(|err| {
+            eprintln!("Err: cannot create {}: {}", dir, err);
+            std::process::exit(1);
+        })
     });
-    // Limit to max ~60 fps update rate
-    window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
-    let mut cpu = Chip8Interpreter::new(Some(&mut window));
-    cpu.run_rom("ibmrom.ch8");
-}
\ No newline at end of file
+    let last = cpu
+        .frames()
+        .take(frames as usize)
+        .inspect(|frame| {
+            if let (Some(sequence), Ok(frame)) = (screenshots.as_mut(), frame) {
+                if let Err(err) = sequence.capture(frame) {
+                    eprintln!("Err: cannot write screenshot: {}", err);
+                    std::process::exit(1);
+                }
+            }
+        })
+        .last();
+    match last {
+        Some(Ok(frame)) => print_frame(&frame, options.region, options.video.sound_flash),
+        Some(Err(err)) => {
+            eprintln!("Err: {}", err);
+            if let Some(history) = cpu.history() {
+                eprint!("last instructions:\n{}", history);
+            }
+            match cpu.write_journal(&err) {
+                Ok(Some(path)) => eprintln!("Wrote a crash dump to {}", path),
+                Ok(None) => {}
+                Err(io_err) => eprintln!("Err: cannot write journal: {}", io_err),
+            }
+            std::process::exit(1);
+        }
+        None => {}
+    }
+    if let Some(reason) = cpu.paused() {
+        println!("Paused on {}", reason);
+        print!("{}", cpu);
+    }
+    if let Some(stats) = cpu.stats() {
+        print!("{}", stats);
+    }
+    print_reports(&cpu, options);
+    if let Some(profile) = cpu.call_profile() {
+        print!("{}", profile.tree());
+    }
+    if let Some(audit) = cpu.audit() {
+        print!("{}", audit);
+    }
+    for pass in cpu.analyses() {
+        print!("{}:\n{}", pass.name(), pass.report());
+    }
+    if let Some(timing) = cpu.timing() {
+        if let Err(err) = timing.save() {
+            eprintln!("Err: cannot write {}: {}", timing.path(), err);
+        }
+    }
+    if let Some(path) = &options.save_state {
+        if let Err(err) = cpu.state().save(path) {
+            eprintln!("Err: cannot write {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+    save_replay(&cpu, options);
+}
+
+/// Print a region of the frame, inverted when the buzzer is sounding and a
+/// sound flash is on so logs show it too
+fn print_frame(
+    frame: &Frame,
+    (x, y, width, height): (usize, usize, usize, usize),
+    sound_flash: SoundFlash,
+) {
+    let region = frame.framebuffer.region(x, y, width, height);
+    let invert = frame.sound_active && sound_flash != SoundFlash::Off;
+    for y in 0..region.height {
+        let line: String = (0..region.width)
+            .map(|x| {
+                if region.get(x, y) != invert {
+                    '■'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        println!("{}", line);
+    }
+    println!("hash: {:016x}", region.hash());
+}
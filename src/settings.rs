@@ -0,0 +1,214 @@
+use crate::RunOptions;
+use chip8emu::frontends::{parse_color, Hotkeys, Keymap, ScaleFilter, SoundFlash, Waveform};
+use chip8emu::Quirks;
+use serde_json::{json, Map, Value};
+
+const FORMAT: &str = "chip8-profile";
+const VERSION: u64 = 1;
+
+/// The shareable part of the options: quirks, keypad keys and hotkeys,
+/// video and buzzer settings, instruction rate and timer speed, as a small
+/// JSON document
+pub fn export(options: &RunOptions) -> String {
+    let mut profile = json!({
+        "format": FORMAT,
+        "version": VERSION,
+        "keymap": options.keymap.specs(),
+        "hotkeys": options.hotkeys.specs(),
+        "grab_keys": options.hotkeys.grab_all,
+        "video": {
+            "filter": options.video.filter.name(),
+            "glow": options.video.glow,
+            "sound_flash": options.video.sound_flash.name(),
+            "foreground": format!("{:06X}", options.video.foreground),
+            "background": format!("{:06X}", options.video.background),
+        },
+        "audio": {
+            "waveform": options.tone.waveform.name(),
+            "pitch": options.tone.pitch,
+        },
+    });
+    if let Some(quirks) = options.quirks {
+        let flags: Map<String, Value> = Quirks::FLAGS
+            .iter()
+            .map(|&flag| (flag.to_string(), json!(quirks.get(flag))))
+            .collect();
+        profile["quirks"] = Value::Object(flags);
+    }
+    if let Some(ips) = options.ips {
+        profile["ips"] = json!(ips);
+    }
+    if let Some(hz) = options.timer_hz {
+        profile["timer_hz"] = json!(hz);
+    }
+    serde_json::to_string_pretty(&profile).unwrap()
+}
+
+/// Apply a profile written by `export` on top of `options`. Settings the
+/// profile leaves out are kept.
+pub fn import(options: &mut RunOptions, text: &str) -> Result<(), String> {
+    let profile: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    if profile["format"] != FORMAT {
+        return Err(format!("not a {} document", FORMAT));
+    }
+    if profile["version"] != VERSION {
+        return Err(format!(
+            "unsupported profile version {}",
+            profile["version"]
+        ));
+    }
+    if let Some(flags) = profile["quirks"].as_object() {
+        let mut quirks = Quirks::default();
+        for (flag, on) in flags {
+            let on = on
+                .as_bool()
+                .ok_or_else(|| format!("quirk {} is not a boolean", flag))?;
+            quirks.set(flag, on)?;
+        }
+        options.quirks = Some(quirks);
+    }
+    if let Some(specs) = profile["keymap"].as_array() {
+        let specs = specs
+            .iter()
+            .map(|spec| spec.as_str().ok_or("keymap must be hex=key strings"))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Unmap everything first so swapped keys don't clash midway
+        let mut keymap = Keymap::default();
+        for spec in specs.iter() {
+            let hex = spec.split('=').next().unwrap();
+            keymap.bind(&format!("{}=none", hex))?;
+        }
+        for spec in specs {
+            keymap.bind(spec)?;
+        }
+        options.keymap = keymap;
+    }
+    if let Some(specs) = profile["hotkeys"].as_array() {
+        let specs = specs
+            .iter()
+            .map(|spec| spec.as_str().ok_or("hotkeys must be action=key strings"))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Unbind everything first so swapped keys don't clash midway
+        let mut hotkeys = Hotkeys::default();
+        for spec in specs.iter() {
+            let action = spec.split('=').next().unwrap();
+            hotkeys.bind(&format!("{}=none", action))?;
+        }
+        for spec in specs {
+            hotkeys.bind(spec)?;
+        }
+        options.hotkeys = hotkeys;
+    }
+    if let Some(grab) = profile["grab_keys"].as_bool() {
+        options.hotkeys.grab_all = grab;
+    }
+    let video = &profile["video"];
+    if let Some(name) = video["filter"].as_str() {
+        options.video.filter = ScaleFilter::from_name(name)
+            .ok_or_else(|| format!("unknown scale filter '{}'", name))?;
+    }
+    if let Some(glow) = video["glow"].as_bool() {
+        options.video.glow = glow;
+    }
+    if let Some(name) = video["sound_flash"].as_str() {
+        options.video.sound_flash =
+            SoundFlash::from_name(name).ok_or_else(|| format!("unknown sound flash '{}'", name))?;
+    }
+    for (key, color) in [
+        ("foreground", &mut options.video.foreground),
+        ("background", &mut options.video.background),
+    ] {
+        if let Some(text) = video[key].as_str() {
+            *color = parse_color(text).ok_or_else(|| format!("invalid {} color {}", key, text))?;
+        }
+    }
+    let audio = &profile["audio"];
+    if let Some(name) = audio["waveform"].as_str() {
+        options.tone.waveform =
+            Waveform::from_name(name).ok_or_else(|| format!("unknown waveform '{}'", name))?;
+    }
+    // Same range as --pitch
+    match &audio["pitch"] {
+        Value::Null => {}
+        value => {
+            options.tone.pitch = value
+                .as_f64()
+                .filter(|hz| (20. ..=20000.).contains(hz))
+                .ok_or_else(|| format!("pitch must be between 20 and 20000Hz, got {}", value))?
+                as f32;
+        }
+    }
+    if let Some(ips) = positive(&profile, "ips")? {
+        options.ips = Some(ips);
+    }
+    if let Some(hz) = positive(&profile, "timer_hz")? {
+        options.timer_hz = Some(hz);
+    }
+    Ok(())
+}
+
+/// The rate under `key`, which like on the command line must be positive
+fn positive(profile: &Value, key: &str) -> Result<Option<f64>, String> {
+    match &profile[key] {
+        Value::Null => Ok(None),
+        value => value
+            .as_f64()
+            .filter(|rate| rate.is_finite() && *rate > 0.)
+            .map(Some)
+            .ok_or_else(|| format!("{} must be a positive number, got {}", key, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_options;
+
+    #[test]
+    fn test_round_trip() {
+        let mut options = default_options();
+        options.quirks = Some(Quirks::SCHIP);
+        options.hotkeys.bind("pause=Tab").unwrap_err();
+        options.hotkeys.bind("turbo=none").unwrap();
+        options.hotkeys.bind("pause=Tab").unwrap();
+        options.video.foreground = 0xFFB000;
+        options.video.sound_flash = SoundFlash::Tint;
+        options.timer_hz = Some(50.);
+        options.ips = Some(1000.);
+        options.keymap.bind("5=Up").unwrap();
+        options.keymap.bind("8=Down").unwrap();
+        options.keymap.bind("2=W").unwrap();
+        options.tone.waveform = Waveform::Triangle;
+        let mut imported = default_options();
+        import(&mut imported, &export(&options)).unwrap();
+        assert_eq!(imported.quirks, options.quirks);
+        assert_eq!(imported.hotkeys, options.hotkeys);
+        assert_eq!(imported.video, options.video);
+        assert_eq!(imported.timer_hz, Some(50.));
+        assert_eq!(imported.ips, Some(1000.));
+        assert_eq!(imported.keymap, options.keymap);
+        assert_eq!(imported.tone, options.tone);
+        assert!(import(&mut imported, "{\"format\": \"chip8-state\"}").is_err());
+    }
+
+    #[test]
+    fn test_rates() {
+        let mut options = default_options();
+        let profile = |rates: &str| {
+            format!(
+                "{{\"format\": \"{}\", \"version\": {}, {}}}",
+                FORMAT, VERSION, rates
+            )
+        };
+        assert!(import(&mut options, &profile("\"timer_hz\": 0")).is_err());
+        assert!(import(&mut options, &profile("\"timer_hz\": -50")).is_err());
+        assert!(import(&mut options, &profile("\"ips\": \"fast\"")).is_err());
+        import(&mut options, &profile("\"ips\": 500, \"timer_hz\": 50")).unwrap();
+        assert_eq!(options.ips, Some(500.));
+        assert_eq!(options.timer_hz, Some(50.));
+        assert!(import(&mut options, &profile("\"audio\": {\"pitch\": 0}")).is_err());
+        assert!(import(&mut options, &profile("\"audio\": {\"pitch\": 1e9}")).is_err());
+        import(&mut options, &profile("\"audio\": {\"pitch\": 440}")).unwrap();
+        assert_eq!(options.tone.pitch, 440.);
+    }
+}